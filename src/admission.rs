@@ -0,0 +1,130 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use crate::{config::Config, transport};
+
+// token'ı geçersiz, izin listesinde olmayan veya cluster dolduğu için kabul edilmeyen node'a dönülen hata
+pub const ERR_REJECTED: u128 = 13;
+
+// cluster_id ve cluster_secret'ten türetilen, her mesajla açık olarak gönderilen anahtar;
+// yanlış yapılandırılmış veya başka cluster'a ait node'ları ayırır, kimlik doğrulama değildir:
+// trafiği görebilen biri token'ı okuyup tekrar kullanabilir
+pub fn cluster_token(cluster_id: &str, cluster_secret: &str) -> String {
+    if cluster_id.is_empty() && cluster_secret.is_empty() {
+        return String::new();
+    }
+    format!(
+        "{:x}",
+        md5::compute(format!("{}:{}", cluster_id, cluster_secret))
+    )
+}
+
+// datagram'ların HMAC anahtarı; token'dan farklı olarak ağa hiç yazılmaz, cluster_secret
+// bilinmeden türetilemez. Secret boşsa anahtar da boştur ve datagram'lar imzalanmaz
+pub fn datagram_key(cluster_id: &str, cluster_secret: &str) -> Vec<u8> {
+    if cluster_secret.is_empty() {
+        return Vec::new();
+    }
+    md5::compute(format!("datagram:{}:{}", cluster_id, cluster_secret))
+        .0
+        .to_vec()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    pub fn parse(value: &str) -> Option<Cidr> {
+        let (ip_part, prefix_part) = match value.trim().split_once('/') {
+            Some((ip_part, prefix_part)) => (ip_part, Some(prefix_part)),
+            None => (value.trim(), None),
+        };
+        let network: IpAddr = ip_part.parse().ok()?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_part {
+            Some(prefix) => prefix.parse::<u8>().ok()?,
+            None => max_len,
+        };
+        if prefix_len > max_len {
+            return None;
+        }
+        Some(Cidr {
+            network,
+            prefix_len,
+        })
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Admission {
+    pub cluster_token: String,
+    pub allow_list: Vec<Cidr>,
+    pub deny_list: Vec<Cidr>,
+    pub max_cluster_size: usize,
+}
+
+impl Admission {
+    pub fn from_config(conf: &Config) -> Admission {
+        Admission {
+            cluster_token: cluster_token(&conf.cluster_id, &conf.cluster_secret),
+            allow_list: conf
+                .allow_list
+                .iter()
+                .filter_map(|c| Cidr::parse(c))
+                .collect(),
+            deny_list: conf
+                .deny_list
+                .iter()
+                .filter_map(|c| Cidr::parse(c))
+                .collect(),
+            max_cluster_size: conf.max_cluster_size,
+        }
+    }
+
+    pub fn is_token_valid(&self, income_token: &str) -> bool {
+        self.cluster_token.eq(income_token)
+    }
+
+    // deny listesi her zaman önceliklidir, allow listesi boş ise tüm ağlara izin verilir
+    pub fn is_ip_allowed(&self, ip: &IpAddr) -> bool {
+        if self.deny_list.iter().any(|c| c.contains(ip)) {
+            return false;
+        }
+        self.allow_list.is_empty() || self.allow_list.iter().any(|c| c.contains(ip))
+    }
+
+    pub fn is_addr_allowed(&self, node_addr: &str) -> bool {
+        // Unix domain socket'teki node aynı makinededir
+        if transport::unix_socket_path(node_addr).is_some() {
+            return self.is_ip_allowed(&IpAddr::V4(Ipv4Addr::LOCALHOST));
+        }
+        match node_addr.parse::<SocketAddr>() {
+            Ok(socket_addr) => self.is_ip_allowed(&socket_addr.ip()),
+            Err(_) => self.allow_list.is_empty(),
+        }
+    }
+
+    pub fn has_room(&self, node_count: usize) -> bool {
+        self.max_cluster_size == 0 || node_count < self.max_cluster_size
+    }
+}
@@ -0,0 +1,82 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Time source for every timing decision: ping intervals, offline retests,
+/// sync throttling and RTT measurement.
+///
+/// Values are microseconds on a unix-like scale. They must never go
+/// backwards; `last_seen` in `PeerInfo` is read from the same clock.
+pub trait Clock: Send + Sync {
+    fn now_micros(&self) -> u128;
+
+    fn now_millis(&self) -> u128 {
+        self.now_micros() / 1000
+    }
+}
+
+/// Wall time read once at creation, advanced by `Instant` afterwards, so
+/// NTP steps or manual clock changes do not move it.
+pub struct MonotonicClock {
+    started: Instant,
+    started_unix_micros: u128,
+}
+
+impl MonotonicClock {
+    pub fn new() -> Self {
+        MonotonicClock {
+            started: Instant::now(),
+            started_unix_micros: match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+                Ok(n) => n.as_micros(),
+                Err(_) => 0,
+            },
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now_micros(&self) -> u128 {
+        self.started_unix_micros + self.started.elapsed().as_micros()
+    }
+}
+
+/// Manually driven clock for tests and the simulator, clones share the same time.
+#[derive(Clone, Default)]
+pub struct MockClock {
+    now_micros: Arc<AtomicU64>,
+}
+
+impl MockClock {
+    pub fn new(start_millis: u64) -> Self {
+        MockClock {
+            now_micros: Arc::new(AtomicU64::new(start_millis * 1000)),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::SeqCst);
+    }
+
+    // geri gitmez, daha eski bir zaman verilirse yok sayılır
+    pub fn set_millis(&self, now_millis: u64) {
+        self.now_micros
+            .fetch_max(now_millis * 1000, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_micros(&self) -> u128 {
+        self.now_micros.load(Ordering::SeqCst) as u128
+    }
+}
@@ -0,0 +1,56 @@
+use crate::structs::Message;
+
+pub const CODEC_LZ4: &str = "lz4";
+// payload'ı açılamayan veya bilinmeyen kodlamayla gelen mesaja dönülen hata
+pub const ERR_UNDECODABLE: u128 = 15;
+
+// sıkıştırılmış payload'ın açılınca ulaşabileceği en büyük boyut
+const MAX_DECODED_BYTES: usize = 64 * 1024 * 1024;
+
+// bu node'un açabildiği payload kodlamaları, ping ve Ok cevaplarında bildirilir
+pub fn supported_codecs() -> Vec<String> {
+    vec![CODEC_LZ4.to_string()]
+}
+
+// payload eşik değerinden büyükse sıkıştırılmış kopyasını döner, kazanç yoksa None
+pub fn compress(msg: &Message, threshold: usize) -> Option<Message> {
+    if threshold == 0 || msg.payload.len() < threshold || !msg.payload_codec.is_empty() {
+        return None;
+    }
+    let compressed = lz4_flex::compress_prepend_size(&msg.payload);
+    if compressed.len() >= msg.payload.len() {
+        return None;
+    }
+    let mut encoded = msg.clone();
+    encoded.payload = compressed;
+    encoded.payload_codec = CODEC_LZ4.to_string();
+    Some(encoded)
+}
+
+// karşı taraf sıkıştırılmış payload açabiliyor mu, eski node'lar hiç kodlama bildirmez
+pub fn accepts_compressed(peer_codecs: &[String]) -> bool {
+    peer_codecs.iter().any(|codec| codec.eq(CODEC_LZ4))
+}
+
+// payload'ı açar, bilinmeyen veya bozuk kodlamada false döner
+pub fn decode(msg: &mut Message) -> bool {
+    if msg.payload_codec.is_empty() {
+        return true;
+    }
+    if !msg.payload_codec.eq(CODEC_LZ4) || msg.payload.len() < 4 {
+        return false;
+    }
+    let mut size_bytes = [0u8; 4];
+    size_bytes.copy_from_slice(&msg.payload[..4]);
+    if u32::from_le_bytes(size_bytes) as usize > MAX_DECODED_BYTES {
+        return false;
+    }
+    match lz4_flex::decompress_size_prepended(&msg.payload) {
+        Ok(payload) => {
+            msg.payload = payload;
+            msg.payload_codec = String::new();
+            true
+        }
+        Err(_) => false,
+    }
+}
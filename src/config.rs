@@ -1,40 +1,183 @@
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
-use std::io::Write;
+use std::{collections::BTreeMap, io::Write};
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Config {
     pub debug: bool,
     pub store_node_list: bool,
+    // "127.0.0.1:0" verilirse işletim sisteminin seçtiği port kullanılır
     pub addr: String,
+    // boş ise ilk açılışta üretilip storage_dir içinde saklanır
+    #[serde(default)]
+    pub node_id: String,
+    // dinlenecek adres, boş ise addr
+    #[serde(default)]
+    pub bind_addr: String,
+    // bootstrap içindeki DNS isimlerinin kaç saniyede bir tekrar çözüleceği, 0 ise sadece başlangıçta
+    #[serde(default = "default_dns_refresh_secs")]
+    pub dns_refresh_secs: u64,
+    // diğer node'lara bildirilen adresler, ilki node'un kimliği olur
+    #[serde(default)]
+    pub advertise_addr: Vec<String>,
     pub bootstrap: Vec<String>,
+    // yerel ağda node duyurularının gönderildiği multicast grubu, boş ise kapalı
+    #[serde(default)]
+    pub discovery_group: String,
+    // duyuruların kaç milisaniyede bir gönderileceği
+    #[serde(default = "default_discovery_interval")]
+    pub discovery_interval: u64,
+    // node listesinin saklanacağı klasör, boş ise çalışma klasörü
+    #[serde(default)]
+    pub storage_dir: String,
+    #[serde(default)]
+    pub cluster_id: String,
+    #[serde(default)]
+    pub cluster_secret: String,
+    #[serde(default)]
+    pub allow_list: Vec<String>,
+    #[serde(default)]
+    pub deny_list: Vec<String>,
+    #[serde(default)]
+    pub max_cluster_size: usize,
+    #[serde(default = "default_worker_count")]
+    pub worker_count: usize,
+    #[serde(default = "default_job_timeout")]
+    pub job_timeout: u64,
+    #[serde(default)]
+    pub metrics_addr: String,
+    // tekrar eden mesajları ayıklamak için hatırlanan son mesaj id sayısı, 0 ise kapalı
+    #[serde(default = "default_dedup_window")]
+    pub dedup_window: usize,
+    // send_stream ile gönderilen her parçanın byte cinsinden boyutu
+    #[serde(default = "default_stream_chunk_size")]
+    pub stream_chunk_size: usize,
+    // alınıp henüz on_event ile okunmamış stream verisinin üst sınırı, dolunca gönderen bekler
+    #[serde(default = "default_stream_window")]
+    pub stream_window: usize,
+    // bu boyuttan büyük payload'lar destekleyen node'lara lz4 ile sıkıştırılarak gider, 0 ise kapalı
+    #[serde(default = "default_compression_threshold")]
+    pub compression_threshold: usize,
+    // XOR uzaklığına göre yönlendirme ve dht_put / dht_get, kapalı ise Dht mesajları reddedilir
+    #[serde(default)]
+    pub dht: bool,
+    // dht_put ile yazılan değerin saklandığı, anahtara en yakın node sayısı
+    #[serde(default = "default_dht_replication")]
+    pub dht_replication: usize,
+    // dinlenen porttan bağlanma, ulaşılabilirlik ölçümü ve hole punching
+    #[serde(default)]
+    pub nat_traversal: bool,
+    // "tcp" veya "quic" (quic feature'ı ile derlenmiş olmalı)
+    #[serde(default = "default_transport")]
+    pub transport: String,
+    // ping ve ControlNodeStatus UDP ile gönderilir, cevap vermeyen node'lara TCP ile gidilir
+    #[serde(default)]
+    pub udp: bool,
+    // UDP ile gönderilen istek ve cevabın en fazla boyutu, büyükleri TCP ile gider
+    #[serde(default = "default_udp_datagram_size")]
+    pub udp_datagram_size: usize,
+    // node'un başlangıç metadata'sı, ör. {"role": "indexer", "region": "eu"}
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+fn default_dns_refresh_secs() -> u64 {
+    30
+}
+
+fn default_discovery_interval() -> u64 {
+    2000
+}
+
+fn default_worker_count() -> usize {
+    4
+}
+
+fn default_job_timeout() -> u64 {
+    5000
+}
+
+fn default_dedup_window() -> usize {
+    4096
+}
+
+fn default_stream_chunk_size() -> usize {
+    16 * 1024
+}
+
+fn default_stream_window() -> usize {
+    1024 * 1024
+}
+
+fn default_compression_threshold() -> usize {
+    1024
+}
+
+fn default_dht_replication() -> usize {
+    3
+}
+
+fn default_transport() -> String {
+    "tcp".to_string()
+}
+
+fn default_udp_datagram_size() -> usize {
+    1400
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            debug: true,
+            store_node_list: true,
+            addr: "127.0.0.1:1111".to_string(),
+            node_id: String::new(),
+            bind_addr: String::new(),
+            advertise_addr: Vec::new(),
+            dns_refresh_secs: default_dns_refresh_secs(),
+            bootstrap: Vec::new(),
+            discovery_group: String::new(),
+            discovery_interval: default_discovery_interval(),
+            storage_dir: String::new(),
+            cluster_id: String::new(),
+            cluster_secret: String::new(),
+            allow_list: Vec::new(),
+            deny_list: Vec::new(),
+            max_cluster_size: 0,
+            worker_count: default_worker_count(),
+            job_timeout: default_job_timeout(),
+            metrics_addr: String::new(),
+            dedup_window: default_dedup_window(),
+            stream_chunk_size: default_stream_chunk_size(),
+            stream_window: default_stream_window(),
+            compression_threshold: default_compression_threshold(),
+            dht: false,
+            dht_replication: default_dht_replication(),
+            nat_traversal: false,
+            transport: default_transport(),
+            udp: false,
+            udp_datagram_size: default_udp_datagram_size(),
+            metadata: BTreeMap::new(),
+        }
+    }
 }
 
 impl Config {
     pub fn new(config_file_name: Option<String>) -> Config {
-        let file_data = std::fs::read_to_string(if config_file_name.is_some() {
-            config_file_name.unwrap()
-        } else {
-            "p2p_config.json".to_string()
+        let file_data = std::fs::read_to_string(match config_file_name {
+            Some(file_name) => file_name,
+            None => "p2p_config.json".to_string(),
         });
-        if file_data.is_ok() {
-            let file_result = file_data.unwrap();
+        if let Ok(file_result) = file_data {
             let result_config: Config =
                 serde_json::from_str(&file_result.clone()).expect("JSON was not well-formatted");
             result_config
         } else {
-            let result = Config {
-                debug: true,
-                store_node_list: true,
-                addr: "127.0.0.1:1111".to_string(),
-                bootstrap: Vec::new(),
-            };
-
-            let file = std::fs::File::create("p2p_config.json".to_string());
-            if file.is_ok() {
+            let result = Config::default();
+
+            if let Ok(mut file_result) = std::fs::File::create("p2p_config.json") {
                 let read_text = serde_json::to_string(&result.clone()).unwrap();
-                let mut file_result = file.unwrap();
-                _ = file_result.write(&read_text.as_bytes());
+                _ = file_result.write_all(read_text.as_bytes());
             }
             result
         }
@@ -0,0 +1,367 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tracing::{debug, trace};
+
+use crate::{
+    handshake::FEATURE_DHT,
+    message_id::MessageIds,
+    metrics::Metrics,
+    structs::{Message, MessageKind, NodeDetails, NodeDetailsToHelper, NodeStatus},
+    transport::Transport,
+};
+
+// her k-bucket'ta ve her find_node cevabında en fazla bu kadar node bulunur
+pub const BUCKET_SIZE: usize = 20;
+// put ile saklanabilecek en büyük değer
+pub const MAX_VALUE_SIZE: usize = 64 * 1024;
+// bir node'un başkaları adına saklayacağı en fazla anahtar sayısı
+const MAX_STORED_VALUES: usize = 4096;
+// bir lookup'ta sorulacak en fazla node sayısı
+const MAX_LOOKUP_QUERIES: usize = 64;
+// DHT kapalı node'un Dht mesajlarına döndüğü hata
+pub const ERR_DHT_DISABLED: u128 = 16;
+// büyük bir değerin cevabı ping cevabından çok daha uzun sürer
+const DHT_REPLY_TIMEOUT: Duration = Duration::from_millis(2000);
+
+/// A node as the DHT knows it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DhtContact {
+    pub node_id: String,
+    pub addr: String,
+}
+
+// saklanan değer, version'u büyük olan kazanır
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DhtValue {
+    #[serde(with = "crate::helper::base64_bytes")]
+    pub data: Vec<u8>,
+    pub version: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum DhtRequest {
+    FindNode { target: u128 },
+    FindValue { key: String },
+    Store { key: String, value: DhtValue },
+}
+
+// Dht mesajının payload'ı, gönderenin adresi Message.sender'dan alınır
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DhtQuery {
+    pub node_id: String,
+    pub request: DhtRequest,
+}
+
+// Ok cevabının payload'ı
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct DhtReply {
+    // hedefe bilinen en yakın node'lar
+    pub nodes: Vec<DhtContact>,
+    pub value: Option<DhtValue>,
+    pub stored: bool,
+}
+
+// node id ve anahtarlar aynı 128 bitlik uzaya yerleşir
+pub fn key_of(name: &str) -> u128 {
+    u128::from_be_bytes(md5::compute(name).0)
+}
+
+fn bucket_index(local_key: u128, key: u128) -> Option<usize> {
+    match local_key ^ key {
+        0 => None,
+        distance => Some(127 - distance.leading_zeros() as usize),
+    }
+}
+
+// XOR uzaklığına göre yönlendirme tablosu ve bu node'da saklanan değerler
+pub struct Dht {
+    pub enabled: bool,
+    pub node_id: String,
+    local_key: u128,
+    // her bucket'ta en eski görülen başta
+    buckets: Vec<Vec<DhtContact>>,
+    values: BTreeMap<String, DhtValue>,
+}
+
+impl Dht {
+    pub fn new(enabled: bool, node_id: &str) -> Self {
+        Dht {
+            enabled,
+            node_id: node_id.to_string(),
+            local_key: key_of(node_id),
+            buckets: vec![Vec::new(); 128],
+            values: BTreeMap::new(),
+        }
+    }
+
+    // yeni görülen node bucket doluysa eklenmez, uzun süredir yaşayan node'lar tercih edilir
+    pub fn insert(&mut self, contact: DhtContact) {
+        if contact.node_id.is_empty() || contact.addr.is_empty() {
+            return;
+        }
+        let Some(index) = bucket_index(self.local_key, key_of(&contact.node_id)) else {
+            return;
+        };
+        let bucket = &mut self.buckets[index];
+        if let Some(position) = bucket.iter().position(|c| c.node_id.eq(&contact.node_id)) {
+            bucket.remove(position);
+            bucket.push(contact);
+        } else if bucket.len() < BUCKET_SIZE {
+            trace!(node_id = %contact.node_id, peer = %contact.addr, "dht contact added");
+            bucket.push(contact);
+        }
+    }
+
+    pub fn remove(&mut self, node_id: &str) {
+        if let Some(index) = bucket_index(self.local_key, key_of(node_id)) {
+            self.buckets[index].retain(|c| !c.node_id.eq(node_id));
+        }
+    }
+
+    // membership'te DHT'yi destekleyen ve offline olmayan node'lar tabloya girer
+    pub fn sync_members(&mut self, node_list: &[NodeDetails]) {
+        for n_info in node_list.iter() {
+            if n_info.status != NodeStatus::Offline
+                && n_info.features.iter().any(|f| f.eq(FEATURE_DHT))
+            {
+                self.insert(DhtContact {
+                    node_id: n_info.node_id.clone(),
+                    addr: n_info.addr.clone(),
+                });
+            }
+        }
+    }
+
+    pub fn contact(&self, node_id: &str) -> Option<DhtContact> {
+        let index = bucket_index(self.local_key, key_of(node_id))?;
+        self.buckets[index]
+            .iter()
+            .find(|c| c.node_id.eq(node_id))
+            .cloned()
+    }
+
+    pub fn closest(&self, target: u128, count: usize) -> Vec<DhtContact> {
+        let mut contacts: Vec<DhtContact> = self.buckets.iter().flatten().cloned().collect();
+        contacts.sort_by_key(|c| key_of(&c.node_id) ^ target);
+        contacts.truncate(count);
+        contacts
+    }
+
+    pub fn value(&self, key: &str) -> Option<DhtValue> {
+        self.values.get(key).cloned()
+    }
+
+    // aynı anahtarın daha yeni bir sürümü varsa eskisi yazılmaz ama kabul edilmiş sayılır
+    pub fn store(&mut self, key: &str, value: DhtValue) -> bool {
+        if value.data.len() > MAX_VALUE_SIZE {
+            return false;
+        }
+        match self.values.get(key) {
+            Some(current) if current.version >= value.version => true,
+            Some(_) => {
+                self.values.insert(key.to_string(), value);
+                true
+            }
+            None if self.values.len() >= MAX_STORED_VALUES => false,
+            None => {
+                self.values.insert(key.to_string(), value);
+                true
+            }
+        }
+    }
+
+    // handle_connection'da gelen sorguyu cevaplar
+    pub fn handle(&mut self, sender: &str, query: DhtQuery) -> DhtReply {
+        self.insert(DhtContact {
+            node_id: query.node_id,
+            addr: sender.to_string(),
+        });
+        match query.request {
+            DhtRequest::FindNode { target } => DhtReply {
+                nodes: self.closest(target, BUCKET_SIZE),
+                ..DhtReply::default()
+            },
+            DhtRequest::FindValue { key } => DhtReply {
+                nodes: self.closest(key_of(&key), BUCKET_SIZE),
+                value: self.value(&key),
+                stored: false,
+            },
+            DhtRequest::Store { key, value } => DhtReply {
+                stored: self.store(&key, value),
+                ..DhtReply::default()
+            },
+        }
+    }
+}
+
+pub fn query_from_bytes(data: &[u8]) -> Option<DhtQuery> {
+    serde_json::from_slice(data).ok()
+}
+
+pub fn reply_to_bytes(reply: &DhtReply) -> Vec<u8> {
+    serde_json::to_vec(reply).unwrap_or_default()
+}
+
+// sorgu gönderen tarafın ihtiyaç duyduğu her şey, çağıranın thread'inde çalışır
+pub struct DhtClient {
+    pub my_addr: String,
+    pub cluster_token: String,
+    pub replication: usize,
+    pub dht: Arc<Mutex<Dht>>,
+    pub node_list: Arc<Mutex<Vec<NodeDetails>>>,
+    pub transport: Arc<dyn Transport>,
+    pub ids: Arc<MessageIds>,
+    pub metrics: Arc<Metrics>,
+}
+
+impl DhtClient {
+    fn call(&self, contact: &DhtContact, request: DhtRequest) -> Option<DhtReply> {
+        let query = DhtQuery {
+            node_id: self.dht.lock().unwrap().node_id.clone(),
+            request,
+        };
+        let msg_data = Message {
+            id: self.ids.next(),
+            sender: self.my_addr.clone(),
+            kind: MessageKind::Dht,
+            payload: serde_json::to_vec(&query).unwrap_or_default(),
+            cluster_token: self.cluster_token.clone(),
+            payload_codec: String::new(),
+            handshake: None,
+            node_metadata: Vec::new(),
+        }
+        .to_compact_byte_array();
+        self.metrics.record_sent(&MessageKind::Dht, msg_data.len());
+        let dial_addr = self.node_list.dial_addr_of(&contact.addr);
+        let result = self
+            .transport
+            .request_with_timeout(&dial_addr, &msg_data, DHT_REPLY_TIMEOUT);
+        let reply = match result.kind {
+            MessageKind::Ok => serde_json::from_slice::<DhtReply>(&result.payload).ok(),
+            _ => None,
+        };
+        if reply.is_none() {
+            // cevap vermeyen node tablodan çıkar, membership'te görünürse tekrar eklenir
+            debug!(peer = %contact.addr, code = result.id, "dht query failed");
+            self.dht.lock().unwrap().remove(&contact.node_id);
+        }
+        reply
+    }
+
+    // hedefe en yakın node'ları bulana kadar, her seferinde bilinen en yakın sorulmamış node sorulur
+    fn lookup(&self, target: u128, value_key: Option<&str>) -> (Vec<DhtContact>, Option<DhtValue>) {
+        let my_node_id = {
+            let mut dht = self.dht.lock().unwrap();
+            dht.sync_members(&self.node_list.lock().unwrap());
+            dht.node_id.clone()
+        };
+        let mut shortlist = self.dht.lock().unwrap().closest(target, BUCKET_SIZE);
+        let mut queried = HashSet::new();
+        let mut responded = Vec::new();
+        let mut best_value: Option<DhtValue> = None;
+        while queried.len() < MAX_LOOKUP_QUERIES {
+            let Some(next) = shortlist
+                .iter()
+                .find(|c| !queried.contains(&c.node_id))
+                .cloned()
+            else {
+                break;
+            };
+            queried.insert(next.node_id.clone());
+            let request = match value_key {
+                Some(key) => DhtRequest::FindValue {
+                    key: key.to_string(),
+                },
+                None => DhtRequest::FindNode { target },
+            };
+            let Some(reply) = self.call(&next, request) else {
+                shortlist.retain(|c| !c.node_id.eq(&next.node_id));
+                continue;
+            };
+            responded.push(next);
+            if let Some(value) = reply.value {
+                if best_value
+                    .as_ref()
+                    .is_none_or(|best| value.version > best.version)
+                {
+                    best_value = Some(value);
+                }
+            }
+            let mut dht = self.dht.lock().unwrap();
+            for contact in reply.nodes {
+                if contact.node_id.eq(&my_node_id) {
+                    continue;
+                }
+                dht.insert(contact.clone());
+                if !shortlist.iter().any(|c| c.node_id.eq(&contact.node_id)) {
+                    shortlist.push(contact);
+                }
+            }
+            shortlist.sort_by_key(|c| key_of(&c.node_id) ^ target);
+            shortlist.truncate(BUCKET_SIZE);
+        }
+        responded.sort_by_key(|c| key_of(&c.node_id) ^ target);
+        (responded, best_value)
+    }
+
+    pub fn find_node(&self, node_id: &str) -> Option<DhtContact> {
+        if let Some(contact) = self.dht.lock().unwrap().contact(node_id) {
+            return Some(contact);
+        }
+        let (responded, _) = self.lookup(key_of(node_id), None);
+        responded.into_iter().find(|c| c.node_id.eq(node_id))
+    }
+
+    // değer anahtara en yakın `replication` node'a, bu node da aralarındaysa kendisine yazılır,
+    // sürüm, bulunan en yeni değerin sürümünden büyük olacak şekilde artırılır
+    pub fn put(&self, key: &str, mut value: DhtValue) -> usize {
+        let target = key_of(key);
+        let (responded, current) = self.lookup(target, Some(key));
+        let local_current = self.dht.lock().unwrap().value(key);
+        for known in [current, local_current].into_iter().flatten() {
+            value.version = value.version.max(known.version + 1);
+        }
+        let local = DhtContact {
+            node_id: self.dht.lock().unwrap().node_id.clone(),
+            addr: self.my_addr.clone(),
+        };
+        let mut replicas = responded;
+        replicas.push(local.clone());
+        replicas.sort_by_key(|c| key_of(&c.node_id) ^ target);
+        let mut stored_count = 0;
+        for contact in replicas.iter().take(self.replication.max(1)) {
+            let stored = match contact.node_id.eq(&local.node_id) {
+                true => self.dht.lock().unwrap().store(key, value.clone()),
+                false => self
+                    .call(
+                        contact,
+                        DhtRequest::Store {
+                            key: key.to_string(),
+                            value: value.clone(),
+                        },
+                    )
+                    .is_some_and(|reply| reply.stored),
+            };
+            if stored {
+                stored_count += 1;
+            }
+        }
+        trace!(key, replicas = stored_count, "dht value stored");
+        stored_count
+    }
+
+    pub fn get(&self, key: &str) -> Option<DhtValue> {
+        let local_value = self.dht.lock().unwrap().value(key);
+        let (_, found) = self.lookup(key_of(key), Some(key));
+        match (local_value, found) {
+            (Some(local_value), Some(found)) if local_value.version >= found.version => {
+                Some(local_value)
+            }
+            (local_value, found) => found.or(local_value),
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use tracing::{debug, error, trace, Span};
+
+use crate::clock::Clock;
+
+// başka uygulamaların aynı gruba gönderdiği paketleri ayırmak için
+const ANNOUNCE_MAGIC: &str = "litep2p";
+// dinleyici bu aralıkla running bayrağını kontrol eder
+const RECV_TIMEOUT: Duration = Duration::from_millis(200);
+
+// multicast grubuna periyodik olarak gönderilen duyuru
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Announcement {
+    magic: String,
+    cluster: String,
+    node_id: String,
+    // cluster_token'ı açık etmeden aynı cluster'dan olduğunu gösterir
+    proof: String,
+    addr: String,
+}
+
+pub struct Discovery {
+    pub group: SocketAddr,
+    pub interval_millis: u128,
+    pub cluster_id: String,
+    pub cluster_token: String,
+    pub node_id: String,
+    pub my_addr: String,
+}
+
+impl Discovery {
+    fn proof(&self, node_id: &str) -> String {
+        format!(
+            "{:x}",
+            md5::compute(format!("{}:{}", self.cluster_token, node_id))
+        )
+    }
+
+    fn announcement(&self) -> Announcement {
+        Announcement {
+            magic: ANNOUNCE_MAGIC.to_string(),
+            cluster: self.cluster_id.clone(),
+            node_id: self.node_id.clone(),
+            proof: self.proof(&self.node_id),
+            addr: self.my_addr.clone(),
+        }
+    }
+
+    // kendi duyurumuz ve başka cluster'ların duyuruları atlanır
+    fn accept(&self, data: &[u8], source: SocketAddr) -> Option<String> {
+        let income: Announcement = serde_json::from_slice(data).ok()?;
+        if !income.magic.eq(ANNOUNCE_MAGIC)
+            || income.node_id.eq(&self.node_id)
+            || !income.cluster.eq(&self.cluster_id)
+            || !income.proof.eq(&self.proof(&income.node_id))
+        {
+            return None;
+        }
+        // 0.0.0.0 dinleyen node'a paketin geldiği IP'den ulaşılır
+        match income.addr.parse::<SocketAddr>() {
+            Ok(socket_addr) if socket_addr.ip().is_unspecified() => {
+                Some(SocketAddr::new(source.ip(), socket_addr.port()).to_string())
+            }
+            _ => Some(income.addr),
+        }
+    }
+}
+
+// aynı makinedeki node'lar da aynı portu dinleyebilsin diye SO_REUSEADDR ile açılır
+fn bind_group(group: SocketAddr) -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::for_address(group), Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    match group.ip() {
+        IpAddr::V4(group_ip) => {
+            socket.bind(&SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), group.port()).into())?;
+            socket.join_multicast_v4(&group_ip, &Ipv4Addr::UNSPECIFIED)?;
+            socket.set_multicast_loop_v4(true)?;
+            socket.set_multicast_ttl_v4(1)?;
+        }
+        IpAddr::V6(group_ip) => {
+            socket.set_only_v6(true)?;
+            socket.bind(&SocketAddr::new(IpAddr::V6(0.into()), group.port()).into())?;
+            socket.join_multicast_v6(&group_ip, 0)?;
+            socket.set_multicast_loop_v6(true)?;
+        }
+    }
+    let socket: UdpSocket = socket.into();
+    socket.set_read_timeout(Some(RECV_TIMEOUT))?;
+    Ok(socket)
+}
+
+// duyuruları gönderir ve dinler, bulunan adresler found_addrs'a eklenir
+pub fn start(
+    discovery: Discovery,
+    clock: Arc<dyn Clock>,
+    running: Arc<AtomicBool>,
+    found_addrs: Arc<Mutex<Vec<String>>>,
+    span: Span,
+) -> bool {
+    if !discovery.group.ip().is_multicast() {
+        error!(group = %discovery.group, "discovery group is not a multicast address");
+        return false;
+    }
+    let socket = match bind_group(discovery.group) {
+        Ok(socket) => socket,
+        Err(err) => {
+            error!(group = %discovery.group, error = %err, "discovery could not be started");
+            return false;
+        }
+    };
+    let announcement = serde_json::to_vec(&discovery.announcement()).unwrap_or_default();
+    thread::spawn(move || {
+        let _span_guard = span.enter();
+        let mut next_announce = 0;
+        let mut recv_buf = [0u8; 2048];
+        while running.load(Ordering::SeqCst) {
+            let now_millis = clock.now_millis();
+            if now_millis >= next_announce {
+                next_announce = now_millis + discovery.interval_millis;
+                if let Err(err) = socket.send_to(&announcement, discovery.group) {
+                    debug!(error = %err, "discovery announce failed");
+                }
+            }
+            let (n, source) = match socket.recv_from(&mut recv_buf) {
+                Ok(received) => received,
+                Err(_) => continue,
+            };
+            if let Some(node_addr) = discovery.accept(&recv_buf[0..n], source) {
+                trace!(peer = %node_addr, "peer discovered");
+                found_addrs.lock().unwrap().push(node_addr);
+            }
+        }
+    });
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn discovery(node_id: &str, cluster_id: &str, cluster_token: &str) -> Discovery {
+        Discovery {
+            group: "239.255.77.77:7777".parse().unwrap(),
+            interval_millis: 1000,
+            cluster_id: cluster_id.to_string(),
+            cluster_token: cluster_token.to_string(),
+            node_id: node_id.to_string(),
+            my_addr: "10.0.0.7:1111".to_string(),
+        }
+    }
+
+    // duyuru 192.168.1.20'den gelmiş gibi işlenir
+    fn receive(local: &Discovery, announcement: &Announcement) -> Option<String> {
+        let source = "192.168.1.20:7777".parse().unwrap();
+        local.accept(&serde_json::to_vec(announcement).unwrap(), source)
+    }
+
+    #[test]
+    fn accepts_nodes_of_the_same_cluster() {
+        let local = discovery("local", "prod", "token");
+        let remote = discovery("remote", "prod", "token");
+        assert_eq!(
+            receive(&local, &remote.announcement()),
+            Some(remote.my_addr)
+        );
+    }
+
+    #[test]
+    fn skips_other_clusters() {
+        let local = discovery("local", "prod", "token");
+        let remote = discovery("remote", "staging", "token");
+        assert_eq!(receive(&local, &remote.announcement()), None);
+    }
+
+    #[test]
+    fn skips_announcements_with_a_bad_proof() {
+        let local = discovery("local", "prod", "token");
+        // cluster adını bilen ama secret'ı bilmeyen node
+        let remote = discovery("remote", "prod", "guessed");
+        assert_eq!(receive(&local, &remote.announcement()), None);
+
+        // başka bir node'un proof'u kopyalanamaz
+        let mut forged = discovery("remote", "prod", "token").announcement();
+        forged.node_id = "other".to_string();
+        assert_eq!(receive(&local, &forged), None);
+    }
+
+    #[test]
+    fn skips_our_own_announcement() {
+        let local = discovery("local", "prod", "token");
+        assert_eq!(receive(&local, &local.announcement()), None);
+    }
+
+    #[test]
+    fn skips_foreign_packets() {
+        let local = discovery("local", "prod", "token");
+        let mut other_app = discovery("remote", "prod", "token").announcement();
+        other_app.magic = "other-app".to_string();
+        assert_eq!(receive(&local, &other_app), None);
+        let source = "192.168.1.20:7777".parse().unwrap();
+        assert_eq!(local.accept(b"not json", source), None);
+    }
+
+    #[test]
+    fn unspecified_addrs_take_the_source_ip() {
+        let local = discovery("local", "prod", "token");
+        let mut remote = discovery("remote", "prod", "token");
+        remote.my_addr = "0.0.0.0:2222".to_string();
+        assert_eq!(
+            receive(&local, &remote.announcement()),
+            Some("192.168.1.20:2222".to_string())
+        );
+
+        remote.my_addr = "[::]:2222".to_string();
+        let data = serde_json::to_vec(&remote.announcement()).unwrap();
+        let found = local.accept(&data, "[fe80::1]:7777".parse().unwrap());
+        assert_eq!(found, Some("[fe80::1]:2222".to_string()));
+    }
+}
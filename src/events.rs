@@ -0,0 +1,35 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::EventType;
+
+// ping ve bağlantı thread'lerinde oluşup on_event ile okunan olaylar
+#[derive(Default)]
+pub struct EventQueue {
+    queue: VecDeque<EventType>,
+    reported: HashSet<(String, String)>,
+}
+
+impl EventQueue {
+    pub fn push(&mut self, event: EventType) {
+        self.queue.push_back(event);
+    }
+
+    pub fn pop(&mut self) -> Option<EventType> {
+        self.queue.pop_front()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    // her ping'de tekrar bildirilmesin diye aynı node ve sebep bir kez kuyruğa girer
+    pub fn report_incompatible(&mut self, node_addr: &str, reason: String) {
+        if self
+            .reported
+            .insert((node_addr.to_string(), reason.clone()))
+        {
+            self.queue
+                .push_back(EventType::OnIncompatiblePeer(node_addr.to_string(), reason));
+        }
+    }
+}
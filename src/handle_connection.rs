@@ -1,34 +1,341 @@
-use crate::structs::{ConvertVecToStruct, Message, MessageKind};
+use crate::{
+    admission::{Admission, ERR_REJECTED},
+    codec::{self, ERR_UNDECODABLE},
+    dht::{self, Dht, ERR_DHT_DISABLED},
+    events::EventQueue,
+    handshake::{Handshake, ERR_INCOMPATIBLE},
+    message_id::{DedupWindow, MessageIds},
+    metrics::Metrics,
+    nat::{self, NatState, ERR_NAT_DISABLED},
+    relay::{self, RelayEnvelope, ERR_NO_ROUTE},
+    stream::{self, StreamState},
+    structs::{ConvertVecToStruct, Message, MessageKind, NodeDetails, NodeDetailsToHelper},
+    transport::{self, Transport, TransportConnection},
+};
 use std::{
-    io::{Read, Write},
-    net::TcpStream,
+    net::SocketAddr,
     sync::{Arc, Mutex},
 };
+use tracing::{debug, trace, trace_span};
 
-pub fn handle_connection(
-    my_addr: String,
-    mut stream: TcpStream,
-    msg_list: Arc<Mutex<Vec<Message>>>,
-) {
-    let mut read_buf = [0u8; 4096];
-    match stream.read(&mut read_buf) {
-        Ok(n) => {
+// gelen bağlantıyı işlemek için gereken node durumu, her bağlantı için kopyalanır
+#[derive(Clone)]
+pub struct Inbound {
+    pub my_addr: String,
+    pub msg_list: Arc<Mutex<Vec<Message>>>,
+    pub node_list: Arc<Mutex<Vec<NodeDetails>>>,
+    pub admission: Arc<Admission>,
+    pub metrics: Arc<Metrics>,
+    pub dedup: Arc<Mutex<DedupWindow>>,
+    pub streams: Arc<Mutex<StreamState>>,
+    pub handshake: Arc<Mutex<Handshake>>,
+    pub events: Arc<Mutex<EventQueue>>,
+    pub dht: Arc<Mutex<Dht>>,
+    // relay mesajlarını bir sonraki node'a iletmek için
+    pub transport: Arc<dyn Transport>,
+    pub ids: Arc<MessageIds>,
+    pub nat: Arc<Mutex<NatState>>,
+}
+
+impl Inbound {
+    pub fn handle(&self, stream: Box<dyn TransportConnection>) {
+        handle_connection(self, stream);
+    }
+}
+
+pub fn handle_connection(inbound: &Inbound, mut stream: Box<dyn TransportConnection>) {
+    let admission = &inbound.admission;
+    let metrics = &inbound.metrics;
+    match stream.read_request() {
+        Ok(read_buf) => {
+            let n = read_buf.len();
             if n > 0 {
-                let income_data = read_buf[0..n].to_vec().to_message_struct();
-                msg_list.lock().unwrap().push(income_data);
-                _ = stream.write(
-                    &Message {
-                        id: 0,
-                        sender: my_addr.clone(),
-                        kind: MessageKind::Ok,
-                        payload: Vec::new(),
+                let mut income_data = read_buf.to_message_struct();
+                let _span_guard = trace_span!(
+                    "connection",
+                    peer = %income_data.sender,
+                    msg_id = income_data.id,
+                    kind = %income_data.kind
+                )
+                .entered();
+                if !is_admitted(stream.as_ref(), &income_data, &inbound.node_list, admission) {
+                    debug!("connection rejected");
+                    _ = stream
+                        .write_response(&error_reply(inbound, ERR_REJECTED, None).to_byte_array());
+                    return;
+                }
+                metrics.record_received(&income_data.kind, n);
+                record_observed_addr(stream.as_ref(), &income_data.sender, &inbound.node_list);
+                let local_handshake = inbound.handshake.lock().unwrap().clone();
+                if let Some(peer_handshake) = &income_data.handshake {
+                    if let Some(reason) = local_handshake.incompatibility(peer_handshake) {
+                        debug!(reason = %reason, "incompatible peer");
+                        inbound
+                            .events
+                            .lock()
+                            .unwrap()
+                            .report_incompatible(&income_data.sender, reason);
+                        _ = stream.write_response(
+                            &error_reply(
+                                inbound,
+                                ERR_INCOMPATIBLE,
+                                Some(Box::new(local_handshake)),
+                            )
+                            .to_byte_array(),
+                        );
+                        return;
+                    }
+                }
+                if !codec::decode(&mut income_data) {
+                    debug!(codec = %income_data.payload_codec, "payload could not be decoded");
+                    _ = stream.write_response(
+                        &error_reply(inbound, ERR_UNDECODABLE, Some(Box::new(local_handshake)))
+                            .to_byte_array(),
+                    );
+                    return;
+                }
+                if income_data.kind == MessageKind::Stream {
+                    // stream parçaları offset ile sıralanır, ack alıcının durumunu taşır
+                    let mut reply_payload = Vec::new();
+                    if let Some(frame) = stream::frame_from_bytes(&income_data.payload) {
+                        let ack = inbound
+                            .streams
+                            .lock()
+                            .unwrap()
+                            .receive(&income_data.sender, frame);
+                        reply_payload = stream::ack_to_bytes(&ack);
                     }
-                    .to_byte_array(),
+                    _ = stream.write_response(
+                        &ok_reply(inbound, reply_payload, None).to_compact_byte_array(),
+                    );
+                    return;
+                }
+                if income_data.kind == MessageKind::Dht {
+                    let reply = match dht::query_from_bytes(&income_data.payload) {
+                        Some(query) => {
+                            let mut local_dht = inbound.dht.lock().unwrap();
+                            match local_dht.enabled {
+                                true => Some(local_dht.handle(&income_data.sender, query)),
+                                false => None,
+                            }
+                        }
+                        None => None,
+                    };
+                    let reply = match reply {
+                        Some(reply) => ok_reply(inbound, dht::reply_to_bytes(&reply), None),
+                        None => error_reply(inbound, ERR_DHT_DISABLED, None),
+                    };
+                    _ = stream.write_response(&reply.to_compact_byte_array());
+                    return;
+                }
+                if income_data.kind == MessageKind::Nat {
+                    let reply = match nat::request_from_bytes(&income_data.payload) {
+                        Some(request) => nat::handle(inbound, &income_data.sender, request),
+                        None => None,
+                    };
+                    let reply = match reply {
+                        Some(reply) => ok_reply(inbound, nat::reply_to_bytes(&reply), None),
+                        None => error_reply(inbound, ERR_NAT_DISABLED, None),
+                    };
+                    _ = stream.write_response(&reply.to_byte_array());
+                    return;
+                }
+                if income_data.kind == MessageKind::Relay {
+                    let result_id = match relay::envelope_from_bytes(&income_data.payload) {
+                        Some(envelope) => relay_message(inbound, &income_data.sender, envelope),
+                        None => ERR_NO_ROUTE,
+                    };
+                    let reply = match result_id {
+                        0 => ok_reply(inbound, Vec::new(), None),
+                        _ => error_reply(inbound, result_id, None),
+                    };
+                    _ = stream.write_response(&reply.to_byte_array());
+                    return;
+                }
+                // ping gibi state mesajlarının cevabı handshake'imizi ve gönderenin
+                // görüldüğü adresi taşır, NAT arkasındaki node dış adresini buradan öğrenir
+                let reply_handshake = match income_data.kind {
+                    MessageKind::State => {
+                        let mut reply_handshake = local_handshake;
+                        reply_handshake.observed_addr =
+                            observed_addr_of(stream.as_ref(), &income_data.sender)
+                                .unwrap_or_default();
+                        Some(Box::new(reply_handshake))
+                    }
+                    _ => None,
+                };
+                // tekrar gelen mesaja yine Ok dönülür, gönderen tekrar denemesin
+                if income_data.id != 0 && !inbound.dedup.lock().unwrap().insert(income_data.id) {
+                    debug!("duplicate message dropped");
+                    metrics.record_duplicate();
+                } else if matches!(
+                    income_data.kind,
+                    MessageKind::State | MessageKind::Distribute
+                ) {
+                    inbound.msg_list.lock().unwrap().push(income_data);
+                } else {
+                    // select() sadece State ve Distribute'u kuyruktan alır, diğerleri kuyruğu tıkar
+                    debug!("unexpected message kind dropped");
+                }
+                _ = stream.write_response(
+                    &ok_reply(inbound, Vec::new(), reply_handshake).to_byte_array(),
                 );
             }
         }
         Err(_err) => {
-            _ = stream.write_all("ERR".as_bytes());
+            _ = stream.write_response("ERR".as_bytes());
+        }
+    }
+}
+
+// cevaplar her zaman kendi token'ımızı taşır, gönderen bizi kendi cluster'ından biri olarak tanır
+fn ok_reply(inbound: &Inbound, payload: Vec<u8>, handshake: Option<Box<Handshake>>) -> Message {
+    reply_message(inbound, 0, MessageKind::Ok, payload, handshake)
+}
+
+// id hata kodudur: ERR_REJECTED, ERR_INCOMPATIBLE, ERR_UNDECODABLE, ERR_DHT_DISABLED, ...
+fn error_reply(inbound: &Inbound, id: u128, handshake: Option<Box<Handshake>>) -> Message {
+    reply_message(inbound, id, MessageKind::Error, Vec::new(), handshake)
+}
+
+fn reply_message(
+    inbound: &Inbound,
+    id: u128,
+    kind: MessageKind,
+    payload: Vec<u8>,
+    handshake: Option<Box<Handshake>>,
+) -> Message {
+    Message {
+        id,
+        sender: inbound.my_addr.clone(),
+        kind,
+        payload,
+        cluster_token: inbound.admission.cluster_token.clone(),
+        payload_codec: String::new(),
+        handshake,
+        node_metadata: Vec::new(),
+    }
+}
+
+fn is_admitted(
+    stream: &dyn TransportConnection,
+    income_data: &Message,
+    node_list: &Arc<Mutex<Vec<NodeDetails>>>,
+    admission: &Admission,
+) -> bool {
+    // okunamayan istek Error olarak çözülür, token'ı olmadığı gibi kuyruğa da girmemeli
+    if income_data.kind == MessageKind::Error {
+        return false;
+    }
+    if !admission.is_token_valid(&income_data.cluster_token) {
+        return false;
+    }
+    if let Some(peer_ip) = stream.peer_ip() {
+        if !admission.is_ip_allowed(&peer_ip) {
+            return false;
         }
     }
+    if !admission.is_addr_allowed(&income_data.sender) {
+        return false;
+    }
+    let node_list = node_list.lock().unwrap();
+    let node_exist = node_list
+        .iter()
+        .any(|n_info| n_info.addr.eq(&income_data.sender));
+    node_exist || admission.has_room(node_list.len())
+}
+
+// gönderenin bağlandığı IP ile kimliğindeki port, port koruyan NAT'ın arkasındaki node'un dış adresi
+fn observed_addr_of(stream: &dyn TransportConnection, sender: &str) -> Option<String> {
+    let peer_ip = stream.peer_ip()?;
+    let sender_addr = sender.parse::<SocketAddr>().ok()?;
+    Some(SocketAddr::new(peer_ip, sender_addr.port()).to_string())
+}
+
+// NAT arkasındaki node'lara ulaşmak için
+fn record_observed_addr(
+    stream: &dyn TransportConnection,
+    sender: &str,
+    node_list: &Arc<Mutex<Vec<NodeDetails>>>,
+) {
+    let Some(observed_addr) = observed_addr_of(stream, sender) else {
+        return;
+    };
+    for n_info in node_list.lock().unwrap().iter_mut() {
+        if n_info.addr.eq(sender) {
+            if !n_info.observed_addr.eq(&observed_addr) {
+                trace!(observed_addr = %observed_addr, "observed address changed");
+                n_info.observed_addr = observed_addr;
+            }
+            break;
+        }
+    }
+}
+
+// mesaj bize geldiyse kuyruğa girer, değilse bir sonraki node'a iletilir; rota varsa
+// hemen Ok dönülür ve iletim arka planda yapılır, gönderen kısa cevap süresini aşmasın.
+// Gönderen sadece ilk adımın mesajı kabul ettiğini öğrenir, sonraki adımlardaki kayıplar bildirilmez
+fn relay_message(inbound: &Inbound, came_from: &str, envelope: RelayEnvelope) -> u128 {
+    let my_addr = &inbound.my_addr;
+    let is_for_me = envelope.receiver.eq(my_addr)
+        || inbound
+            .handshake
+            .lock()
+            .unwrap()
+            .addrs
+            .contains(&envelope.receiver);
+    if is_for_me {
+        let mut inner = envelope.inner;
+        // sadece uygulama mesajları relay edilir, state mesajları doğrudan gelmeli
+        if inner.kind != MessageKind::Distribute
+            || !inbound.admission.is_token_valid(&inner.cluster_token)
+        {
+            debug!(origin = %inner.sender, "relayed message rejected");
+            return ERR_REJECTED;
+        }
+        if !codec::decode(&mut inner) {
+            debug!(origin = %inner.sender, codec = %inner.payload_codec, "relayed payload could not be decoded");
+            return ERR_UNDECODABLE;
+        }
+        trace!(origin = %inner.sender, "relayed message arrived");
+        if inner.id != 0 && !inbound.dedup.lock().unwrap().insert(inner.id) {
+            debug!("duplicate message dropped");
+            inbound.metrics.record_duplicate();
+        } else {
+            inbound.msg_list.lock().unwrap().push(inner);
+        }
+        return 0;
+    }
+    if envelope.hops_left == 0 {
+        debug!(receiver = %envelope.receiver, "relay hop limit reached");
+        return ERR_NO_ROUTE;
+    }
+    let node_list = inbound.node_list.lock().unwrap().clone();
+    let Some((next_addr, _)) = relay::next_hop(&node_list, my_addr, &envelope.receiver, came_from)
+    else {
+        debug!(receiver = %envelope.receiver, "no route to receiver");
+        return ERR_NO_ROUTE;
+    };
+    let forward = RelayEnvelope {
+        hops_left: envelope.hops_left - 1,
+        ..envelope
+    };
+    let msg_data = relay::wrap(
+        &forward,
+        my_addr,
+        &inbound.admission.cluster_token,
+        &inbound.ids,
+    )
+    .to_compact_byte_array();
+    trace!(receiver = %forward.receiver, next = %next_addr, "relaying message");
+    inbound
+        .metrics
+        .record_sent(&MessageKind::Relay, msg_data.len());
+    transport::request_async(
+        inbound.transport.clone(),
+        inbound.node_list.dial_addr_of(&next_addr),
+        msg_data,
+        inbound.metrics.clone(),
+    );
+    0
 }
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{
+    codec,
+    structs::{NodeDetails, Reachability},
+};
+
+/// Wire protocol version of this build. Version 1 is the original format
+/// without a handshake.
+pub const PROTOCOL_VERSION: u32 = 2;
+// konuşabildiğimiz en eski sürüm, handshake göndermeyen node'lar 1 sayılır
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+// sürümü veya özellikleri uyumsuz node'a dönülen hata, cevap kendi handshake'imizi taşır
+pub const ERR_INCOMPATIBLE: u128 = 14;
+
+pub const FEATURE_STREAM: &str = "stream";
+pub const FEATURE_DEDUP: &str = "dedup";
+// sadece config ile DHT açılan node'lar bildirir
+pub const FEATURE_DHT: &str = "dht";
+// sadece config ile NAT geçişi açılan node'lar bildirir
+pub const FEATURE_NAT: &str = "nat";
+
+/// What a node tells its peers about itself, sent with every ping and with
+/// the reply to it.
+///
+/// Older nodes ignore the field, so new fields can be added here as long as
+/// they have a serde default.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Handshake {
+    pub protocol_version: u32,
+    pub min_protocol_version: u32,
+    pub node_id: String,
+    // açabildiği payload kodlamaları
+    #[serde(default)]
+    pub codecs: Vec<String>,
+    #[serde(default)]
+    pub features: Vec<String>,
+    // uygulamanın set_metadata ile eklediği bilgiler
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    // metadata her değiştiğinde büyür, eski bilgi yenisinin üzerine yazılmaz
+    #[serde(default)]
+    pub metadata_version: u64,
+    // node'un bildirdiği tüm adresler, ilki kimliği
+    #[serde(default)]
+    pub addrs: Vec<String>,
+    // ulaşabildiği node'lar ve kaç adımda ulaştığı, relay rotaları buradan öğrenilir
+    #[serde(default)]
+    pub routes: BTreeMap<String, u8>,
+    #[serde(default)]
+    pub reachability: Reachability,
+    // sadece cevaplarda: isteği gönderenin bağlantısının geldiği adres
+    #[serde(default)]
+    pub observed_addr: String,
+}
+
+impl Handshake {
+    pub fn local(node_id: String, metadata: BTreeMap<String, String>) -> Self {
+        Handshake {
+            protocol_version: PROTOCOL_VERSION,
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+            node_id,
+            codecs: codec::supported_codecs(),
+            features: vec![FEATURE_STREAM.to_string(), FEATURE_DEDUP.to_string()],
+            metadata,
+            metadata_version: 0,
+            addrs: Vec::new(),
+            routes: BTreeMap::new(),
+            reachability: Reachability::Unknown,
+            observed_addr: String::new(),
+        }
+    }
+
+    // iki taraf birbirinin en düşük sürümünü karşılamıyorsa sebebini döner
+    pub fn incompatibility(&self, peer: &Handshake) -> Option<String> {
+        if peer.protocol_version < self.min_protocol_version {
+            return Some(format!(
+                "peer speaks protocol {}, we need at least {}",
+                peer.protocol_version, self.min_protocol_version
+            ));
+        }
+        if self.protocol_version < peer.min_protocol_version {
+            return Some(format!(
+                "peer needs protocol {} or newer, we speak {}",
+                peer.min_protocol_version, self.protocol_version
+            ));
+        }
+        None
+    }
+
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f.eq(feature))
+    }
+
+    pub fn apply_to(&self, n_info: &mut NodeDetails) {
+        if !n_info.node_id.is_empty() && !n_info.node_id.eq(&self.node_id) {
+            // adreste artık başka bir node var, eskisinin metadata'sı geçersiz
+            n_info.metadata_version = 0;
+        }
+        n_info.protocol_version = self.protocol_version;
+        n_info.node_id = self.node_id.clone();
+        n_info.codecs = self.codecs.clone();
+        n_info.features = self.features.clone();
+        n_info.advertised_addrs = self.addrs.clone();
+        n_info.routes = self.routes.clone();
+        n_info.reachability = self.reachability.clone();
+        if self.metadata_version >= n_info.metadata_version {
+            n_info.metadata = self.metadata.clone();
+            n_info.metadata_version = self.metadata_version;
+        }
+    }
+}
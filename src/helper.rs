@@ -1,9 +1,7 @@
-use crate::structs::{ConvertVecToStruct, Message, MessageKind, NodeDetails, NodeStatus};
+use crate::structs::{NodeDetails, NodeStatus};
 use std::{
-    io::{Read, Write},
-    net::TcpStream,
-    thread,
-    time::{Duration, SystemTime},
+    net::SocketAddr,
+    path::{Path, PathBuf},
 };
 
 pub fn control_nodes_hash(node_list: Vec<NodeDetails>) -> bool {
@@ -11,10 +9,10 @@ pub fn control_nodes_hash(node_list: Vec<NodeDetails>) -> bool {
     let mut current_hash = String::new();
     for n_info in node_list.iter() {
         if n_info.status == NodeStatus::Online {
-            if current_hash.len() == 0 {
+            if current_hash.is_empty() {
                 current_hash = n_info.node_hash.clone();
             }
-            if current_hash.eq(&n_info.node_hash) == false {
+            if !current_hash.eq(&n_info.node_hash) {
                 all_equal = false;
             }
         }
@@ -22,84 +20,105 @@ pub fn control_nodes_hash(node_list: Vec<NodeDetails>) -> bool {
     all_equal
 }
 
-pub fn client_async(node_addr: String, msg_data: Vec<u8>) {
-    thread::spawn(move || {
-        _ = client(node_addr, msg_data);
-    });
+// node listesinin saklandığı dosya, örn: <storage_dir>/<node_id>.json
+// eski sürümler dosyayı adrese göre adlandırırdı, örn: 127_0_0_1_1111.json
+pub fn node_list_file(storage_dir: &str, file_key: &str) -> PathBuf {
+    Path::new(storage_dir).join(format!(
+        "{}.json",
+        // unix:/run/p2p/a.sock gibi adresler klasör içermesin
+        file_key.replace(['.', ':', '/', '\\'], "_")
+    ))
 }
 
-pub fn client(node_addr: String, msg_data: Vec<u8>) -> Message {
-    #[allow(unused_assignments)]
-    let mut result_no = 0;
-    match TcpStream::connect(node_addr.clone()) {
-        Ok(mut stream) => {
-            match stream.set_read_timeout(Some(Duration::from_millis(10))) {
-                Ok(_) => {}
-                Err(_) => {}
-            }
+// node id'nin saklandığı dosya, adres değişse de aynı kalır; storage_dir boş ise çalışma klasörü
+pub fn node_id_file(storage_dir: &str) -> PathBuf {
+    Path::new(storage_dir).join("node_id")
+}
+
+// eski sürümlerin storage_dir boşken adrese göre adlandırdığı id dosyası
+pub fn legacy_node_id_file(storage_dir: &str, node_addr: &str) -> PathBuf {
+    node_list_file(storage_dir, node_addr).with_extension("node_id")
+}
 
-            match stream.write(&msg_data) {
-                Ok(_) => {
-                    let mut data = [0u8; 2048];
-                    match stream.read(&mut data) {
-                        Ok(data_size) => {
-                            if data_size == 0 {
-                                result_no = 77;
-                                _ = stream.flush();
-                                _ = stream.shutdown(std::net::Shutdown::Both);
-                            } else {
-                                let convert_buf = data[0..data_size].to_vec();
-                                let c_data = convert_buf.to_message_struct();
-                                return c_data;
-                            }
-                        }
-                        Err(_e) => {
-                            match stream.flush() {
-                                Ok(_a) => {}
-                                Err(_e) => { /* println!("from stream.flush() => {}",e) */ }
-                            }
-                            match stream.shutdown(std::net::Shutdown::Read) {
-                                Ok(_n) => {}
-                                Err(_e) => { /* println!("from stream.shutdown => {}",e) */ }
-                            }
-                            result_no = 9;
-                        }
-                    }
-                }
-                Err(_) => {
-                    result_no = 8;
-                }
+// bildirilecek adreslerin listesi, port 0 olanlar dinlenen port ile doldurulur
+pub fn advertised_addrs(
+    addr: &str,
+    bind_addr: &str,
+    advertise_addr: &[String],
+    bound_addr: &str,
+) -> Vec<String> {
+    let mut addr_list = Vec::new();
+    let configured = match (advertise_addr.is_empty(), bind_addr.is_empty()) {
+        (false, _) => advertise_addr.to_vec(),
+        // bind_addr ayrı verilmişse addr node'un kimliği olarak kalır
+        (true, false) if !addr.is_empty() => vec![addr.to_string()],
+        _ => vec![bound_addr.to_string()],
+    };
+    let bound_port = bound_addr.parse::<SocketAddr>().ok().map(|a| a.port());
+    for item in configured.iter() {
+        let item = match (item.parse::<SocketAddr>(), bound_port) {
+            (Ok(mut socket_addr), Some(port)) if socket_addr.port() == 0 => {
+                socket_addr.set_port(port);
+                socket_addr.to_string()
             }
+            _ => item.clone(),
+        };
+        if !item.is_empty() && !addr_list.contains(&item) {
+            addr_list.push(item);
         }
-        Err(_e) => {
-            result_no = 5;
-        }
-    }
-    Message {
-        id: result_no,
-        sender: String::new(),
-        kind: MessageKind::Error,
-        payload: Vec::new(),
     }
+    addr_list
 }
 
-pub fn get_sys_time_in_secs() -> u128 {
-    match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
-        Ok(n) => n.as_secs() as u128,
-        Err(_) => 0,
-    }
+// 0.0.0.0 veya [::] gibi başka bir makineden bağlanılamayacak adres
+pub fn is_unspecified_addr(addr: &str) -> bool {
+    addr.parse::<SocketAddr>()
+        .is_ok_and(|socket_addr| socket_addr.ip().is_unspecified())
 }
 
-pub fn get_sys_time_in_millis() -> u128 {
-    match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
-        Ok(n) => n.as_millis(),
-        Err(_) => 0,
-    }
+// xorshift64*, aynı seed ile hep aynı sayı dizisi
+pub fn next_random(rng_state: &mut u64) -> u64 {
+    let mut x = (*rng_state).max(1);
+    x ^= x >> 12;
+    x ^= x << 25;
+    x ^= x >> 27;
+    *rng_state = x;
+    x.wrapping_mul(0x2545_f491_4f6c_dd1d)
 }
 
-pub fn get_sys_time_in_nano() -> u128 {
-    match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
-        Ok(n) => n.as_nanos(),
-        Err(_) => 0,
+// Vec<u8> alanları JSON'da sayı dizisi yerine base64 yazılır, okurken iki biçim de kabul edilir
+pub mod base64_bytes {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{de, Deserializer, Serializer};
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        deserializer.deserialize_any(BytesVisitor)
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> de::Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a base64 string or a byte array")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Vec<u8>, E> {
+            STANDARD.decode(value).map_err(E::custom)
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+            let mut data = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+            while let Some(byte) = seq.next_element::<u8>()? {
+                data.push(byte);
+            }
+            Ok(data)
+        }
     }
 }
@@ -1,33 +1,110 @@
-use colored::Colorize;
-use config::Config;
-use helper::client_async;
-use log::{debug, error, info, trace};
+use admission::Admission;
+pub use clock::{Clock, MockClock, MonotonicClock};
+pub use config::Config;
+use dht::{Dht, DhtClient, DhtValue};
+use discovery::Discovery;
+use events::EventQueue;
+use handle_connection::Inbound;
+pub use handshake::{Handshake, PROTOCOL_VERSION};
+use message_id::{DedupWindow, MessageIds};
+use metrics::{Metrics, MetricsServer};
+use nat::NatState;
+pub use metrics::MetricsSnapshot;
+use ping::PingLoop;
+use relay::{RelayEnvelope, MAX_RELAY_HOPS};
+pub use resolver::{Resolver, StubResolver, SystemResolver};
 use serde::{Deserialize, Serialize};
+pub use sim::{SimNetwork, SimTransport, Simulation};
 use std::{
+    collections::BTreeMap,
     fs,
-    net::TcpListener,
-    sync::{Arc, Mutex},
+    io::{self, Read, Seek},
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     thread,
+    time::Duration,
 };
-use structs::{
-    ConvertVecToStruct, Message, MessageKind, NodeDetails, NodeDetailsToHelper, NodeStatus,
-    StateType,
+use stream::{OutgoingStream, StreamState};
+use structs::{ConvertVecToStruct, NodeDetails, NodeDetailsToHelper, StateType};
+pub use structs::{
+    LinkQuality, Message, MessageKind, NodeMetadata, NodeStatus, PeerInfo, Reachability,
 };
+use tracing::{debug, error, info, info_span, trace, warn, Span};
+pub use transport::{
+    MemoryNetwork, MemoryTransport, TcpTransport, Transport, TransportConnection, TransportListener,
+};
+#[cfg(feature = "quic")]
+pub use transport::QuicTransport;
+pub use worker::PoolStats;
 use worker::ThreadPool;
 
+mod admission;
+mod clock;
+mod codec;
 mod config;
+mod dht;
+mod discovery;
+mod events;
 mod handle_connection;
+mod handshake;
 mod helper;
+mod message_id;
+mod metrics;
+mod nat;
+mod ping;
+mod relay;
+mod resolver;
+mod sim;
+mod stream;
 mod structs;
+mod transport;
 mod worker;
 
 pub struct MessageConfig {
     ping_time: u128,
+    worker_count: usize,
+    job_timeout: u64,
+    stream_chunk_size: usize,
+    compression_threshold: usize,
+    dht_replication: usize,
 }
 pub struct MessagePool {
     pub my_addr: String,
+    // dinleyicinin gerçekten bağlandığı adres, my_addr'den farklı olabilir
+    listen_addr: String,
     hard_config: MessageConfig,
     store_node_list_active: bool,
+    storage_dir: String,
+    running: Arc<AtomicBool>,
+    transport: Arc<dyn Transport>,
+    // new() ile oluşturulduysa transport Config.transport'a göre seçilir
+    transport_from_config: bool,
+    admission: Arc<Admission>,
+    pool: Option<Arc<ThreadPool>>,
+    metrics: Arc<Metrics>,
+    metrics_server: Option<MetricsServer>,
+    span: Span,
+    clock: Arc<dyn Clock>,
+    ids: Arc<MessageIds>,
+    dedup: Arc<Mutex<DedupWindow>>,
+    streams: Arc<Mutex<StreamState>>,
+    handshake: Arc<Mutex<Handshake>>,
+    events: Arc<Mutex<EventQueue>>,
+    dht: Arc<Mutex<Dht>>,
+    nat: Arc<Mutex<NatState>>,
+    resolver: Arc<dyn Resolver>,
+    // bootstrap içindeki, periyodik olarak tekrar çözülen DNS isimleri
+    dns_names: Vec<String>,
+    dns_refresh_millis: u128,
+    next_dns_refresh: u128,
+    // DNS'ten çözülmüş veya yerel ağda bulunmuş, henüz node listesine eklenmemiş adresler
+    resolved_addrs: Arc<Mutex<Vec<String>>>,
+    // simülasyonda thread yerine tick() ile sürülen ping döngüsü
+    stepped_ping: Option<PingLoop>,
     node_hash: Arc<Mutex<String>>,
     node_list_synced: Arc<Mutex<String>>,
     node_hash_updated: Arc<Mutex<bool>>,
@@ -36,18 +113,73 @@ pub struct MessagePool {
     msg_list: Arc<Mutex<Vec<Message>>>,
 }
 
+impl Default for MessagePool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Installs a colored console subscriber for the demo and tests.
+///
+/// The library itself never installs a subscriber; applications that already
+/// have one should not call this. Returns `false` if one was already set.
+#[cfg(feature = "console-log")]
+pub fn init_console_log() -> bool {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .with_target(false)
+        .without_time()
+        .try_init()
+        .is_ok()
+}
+
 impl MessagePool {
     pub fn new() -> Self {
-        env_logger::builder()
-            .filter_level(log::LevelFilter::Info)
-            .format_target(false)
-            .format_timestamp(None)
-            .init();
+        let mut msg_pool = Self::with_transport(Arc::new(TcpTransport::new()));
+        msg_pool.transport_from_config = true;
+        msg_pool
+    }
 
+    /// Creates a pool that sends and listens through `transport` instead of TCP.
+    pub fn with_transport(transport: Arc<dyn Transport>) -> Self {
         MessagePool {
             my_addr: String::new(),
-            hard_config: MessageConfig { ping_time: 250 },
+            listen_addr: String::new(),
+            hard_config: MessageConfig {
+                ping_time: 250,
+                worker_count: 4,
+                job_timeout: 5000,
+                stream_chunk_size: 16 * 1024,
+                compression_threshold: 1024,
+                dht_replication: 3,
+            },
             store_node_list_active: true,
+            storage_dir: String::new(),
+            running: Arc::new(AtomicBool::new(false)),
+            transport,
+            transport_from_config: false,
+            admission: Arc::new(Admission::default()),
+            pool: None,
+            metrics: Arc::new(Metrics::default()),
+            metrics_server: None,
+            span: Span::none(),
+            clock: Arc::new(MonotonicClock::new()),
+            ids: Arc::new(MessageIds::new("", 0)),
+            dedup: Arc::new(Mutex::new(DedupWindow::new(0))),
+            streams: Arc::new(Mutex::new(StreamState::new(
+                1024 * 1024,
+                Arc::new(MonotonicClock::new()),
+            ))),
+            handshake: Arc::new(Mutex::new(Handshake::local(String::new(), BTreeMap::new()))),
+            events: Arc::new(Mutex::new(EventQueue::default())),
+            dht: Arc::new(Mutex::new(Dht::new(false, ""))),
+            nat: Arc::new(Mutex::new(NatState::new(false))),
+            resolver: Arc::new(SystemResolver::new()),
+            dns_names: Vec::new(),
+            dns_refresh_millis: 0,
+            next_dns_refresh: 0,
+            resolved_addrs: Arc::new(Mutex::new(Vec::new())),
+            stepped_ping: None,
             node_list_synced: Arc::new(Mutex::new(String::new())),
             node_hash_updated: Arc::new(Mutex::new(true)),
             node_hash: Arc::new(Mutex::new(String::new())),
@@ -57,27 +189,372 @@ impl MessagePool {
         }
     }
 
-    pub fn start(&mut self, config_file_name: Option<String>) {
+    pub fn start(&mut self, config_file_name: Option<String>) -> bool {
         let conf = Config::new(config_file_name);
+        self.start_with_config(conf)
+    }
+
+    /// Starts the node without reading or writing `p2p_config.json`.
+    ///
+    /// Returns `false` if the listener could not be bound. The bound address,
+    /// e.g. the real port for `127.0.0.1:0`, is available from `local_addr`.
+    pub fn start_with_config(&mut self, conf: Config) -> bool {
+        let listener = match self.prepare(&conf) {
+            Some(listener) => listener,
+            None => return false,
+        };
+        let _span_guard = self.span.clone().entered();
+        let mut listeners = vec![listener];
+        match self.transport.listen_datagram(&self.listen_addr) {
+            Ok(Some(datagram_listener)) => listeners.push(datagram_listener),
+            Ok(None) => {}
+            // ping'ler TCP ile gelmeye devam eder
+            Err(_) => warn!(addr = %self.listen_addr, "udp listener could not be started"),
+        }
+        self.thread_socket(listeners);
+        if !conf.metrics_addr.is_empty() {
+            self.thread_metrics(conf.metrics_addr.clone());
+        }
+        self.thread_ping();
+        self.thread_resolve();
+        if !conf.discovery_group.is_empty() {
+            self.thread_discovery(&conf);
+        }
+        true
+    }
+
+    // thread açmadan başlatır, ping döngüsü tick() ile ilerler
+    pub(crate) fn start_stepped(&mut self, conf: Config) -> bool {
+        if self.prepare(&conf).is_none() {
+            return false;
+        }
+        self.stepped_ping = Some(self.ping_loop());
+        true
+    }
+
+    pub(crate) fn tick(&mut self) {
+        let _span_guard = self.span.clone().entered();
+        let now_millis = self.clock.now_millis();
+        if let Some(ping_loop) = self.stepped_ping.as_mut() {
+            ping_loop.round(now_millis);
+        }
+        if self.dns_refresh_millis > 0 && now_millis >= self.next_dns_refresh {
+            self.next_dns_refresh = now_millis + self.dns_refresh_millis;
+            for name in self.dns_names.iter() {
+                let resolved = resolver::resolve_entry(self.resolver.as_ref(), name, &self.my_addr);
+                self.resolved_addrs.lock().unwrap().extend(resolved);
+            }
+        }
+    }
+
+    /// Replaces the DNS resolver used for bootstrap names, call before
+    /// `start`. Defaults to `SystemResolver`.
+    pub fn set_resolver(&mut self, resolver: Arc<dyn Resolver>) {
+        self.resolver = resolver;
+    }
+
+    /// Replaces the time source, call before `start`. Defaults to `MonotonicClock`.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    pub(crate) fn inbound(&self) -> Inbound {
+        Inbound {
+            my_addr: self.my_addr.clone(),
+            msg_list: self.msg_list.clone(),
+            node_list: self.node_list.clone(),
+            admission: self.admission.clone(),
+            metrics: self.metrics.clone(),
+            dedup: self.dedup.clone(),
+            streams: self.streams.clone(),
+            handshake: self.handshake.clone(),
+            events: self.events.clone(),
+            dht: self.dht.clone(),
+            transport: self.transport.clone(),
+            ids: self.ids.clone(),
+            nat: self.nat.clone(),
+        }
+    }
+
+    pub(crate) fn node_hash(&self) -> String {
+        self.node_hash.lock().unwrap().clone()
+    }
+
+    pub(crate) fn node_list_snapshot(&self) -> Vec<NodeDetails> {
+        self.node_list.lock().unwrap().clone()
+    }
+
+    pub(crate) fn has_pending_events(&self) -> bool {
+        !self.msg_list.lock().unwrap().is_empty()
+            || !self.node_status_change.lock().unwrap().is_empty()
+            || *self.node_hash_updated.lock().unwrap()
+            || !self.streams.lock().unwrap().events.is_empty()
+            || !self.events.lock().unwrap().is_empty()
+            || !self.resolved_addrs.lock().unwrap().is_empty()
+    }
+
+    // ayarları uygular, dinleyiciyi açar ve node listesini hazırlar
+    fn prepare(&mut self, conf: &Config) -> Option<Box<dyn TransportListener>> {
         self.store_node_list_active = conf.store_node_list;
+        self.storage_dir = conf.storage_dir.clone();
+        self.admission = Arc::new(Admission::from_config(conf));
+        self.hard_config.worker_count = conf.worker_count.max(1);
+        self.hard_config.job_timeout = conf.job_timeout;
+        self.hard_config.stream_chunk_size = conf.stream_chunk_size.max(1);
+        self.hard_config.compression_threshold = conf.compression_threshold;
+        self.hard_config.dht_replication = conf.dht_replication.max(1);
 
-        self.my_addr = conf.addr.clone();
-        self.add_node_to_list(conf.addr.clone());
-        self.add_node_to_lists(conf.bootstrap.clone());
+        let bind_addr = match conf.bind_addr.is_empty() {
+            true => conf.addr.clone(),
+            false => conf.bind_addr.clone(),
+        };
+        if self.transport_from_config {
+            self.transport = match transport::from_name(&conf.transport) {
+                Ok(transport) => transport,
+                Err(err) => {
+                    error!(transport = %conf.transport, error = %err, "transport could not be created");
+                    return None;
+                }
+            };
+        }
+        *self.nat.lock().unwrap() = NatState::new(conf.nat_traversal);
+        self.transport.set_port_reuse(conf.nat_traversal);
+        self.transport.set_datagrams(match conf.udp {
+            true => conf.udp_datagram_size,
+            false => 0,
+        });
+        self.transport
+            .set_datagram_key(admission::datagram_key(&conf.cluster_id, &conf.cluster_secret));
+        let listener = match self.transport.listen(&bind_addr) {
+            Ok(listener) => listener,
+            Err(_) => {
+                error!(addr = %bind_addr, "listener could not be started");
+                return None;
+            }
+        };
+        self.listen_addr = listener.local_addr();
+        let advertised = helper::advertised_addrs(
+            &conf.addr,
+            &conf.bind_addr,
+            &conf.advertise_addr,
+            &self.listen_addr,
+        );
+        self.my_addr = advertised
+            .first()
+            .cloned()
+            .unwrap_or(self.listen_addr.clone());
+        if helper::is_unspecified_addr(&self.my_addr) {
+            warn!(addr = %self.my_addr, "advertised address is not reachable by peers, set advertise_addr");
+        }
+        let node_id = self.load_node_id(conf);
+        self.ids = Arc::new(MessageIds::new(&node_id, self.clock.now_micros() as u64));
+        *self.dht.lock().unwrap() = Dht::new(conf.dht, &node_id);
+        self.dedup = Arc::new(Mutex::new(DedupWindow::new(conf.dedup_window)));
+        self.streams = Arc::new(Mutex::new(StreamState::new(
+            conf.stream_window,
+            self.clock.clone(),
+        )));
+        // set_metadata ile start'tan önce eklenenler korunur
+        {
+            let mut local_handshake = self.handshake.lock().unwrap();
+            local_handshake.node_id = node_id;
+            local_handshake.addrs = advertised;
+            if conf.dht && !local_handshake.has_feature(handshake::FEATURE_DHT) {
+                local_handshake
+                    .features
+                    .push(handshake::FEATURE_DHT.to_string());
+            }
+            if conf.nat_traversal && !local_handshake.has_feature(handshake::FEATURE_NAT) {
+                local_handshake
+                    .features
+                    .push(handshake::FEATURE_NAT.to_string());
+            }
+            for (key, value) in conf.metadata.iter() {
+                local_handshake
+                    .metadata
+                    .entry(key.clone())
+                    .or_insert(value.clone());
+            }
+            // yeniden başlayan node'un metadata'sı eski kopyaların üzerine yazılsın
+            local_handshake.metadata_version = self.clock.now_millis() as u64;
+        }
+        self.span = info_span!("node", addr = %self.my_addr);
+        let _span_guard = self.span.clone().entered();
+        self.add_node_to_list(self.my_addr.clone());
+        self.apply_local_handshake();
+        let bootstrap = self.resolve_bootstrap(conf);
+        self.add_node_to_lists(bootstrap);
         self.add_node_to_lists(self.load_from_disk());
 
-        info!(
-            "{}     => {}",
-            "STARTING NODE".bright_cyan(),
-            self.my_addr.clone().to_string().bright_cyan()
-        );
+        info!(addr = %self.my_addr, listen_addr = %self.listen_addr, node_id = %self.node_id(), "starting node");
 
         self.update_node_hash();
-        if self.thread_socket() == true {
-            self.thread_ping();
-        } else {
-            error!("TCP Could Not started");
-            std::process::exit(9);
+        self.running.store(true, Ordering::SeqCst);
+        Some(listener)
+    }
+
+    // ip:port girdileri olduğu gibi, DNS isimleri çözülerek eklenir
+    fn resolve_bootstrap(&mut self, conf: &Config) -> Vec<String> {
+        let mut bootstrap = Vec::new();
+        self.dns_names = Vec::new();
+        self.dns_refresh_millis = conf.dns_refresh_secs as u128 * 1000;
+        self.next_dns_refresh = self.clock.now_millis() + self.dns_refresh_millis;
+        for entry in conf.bootstrap.iter() {
+            let entry = resolver::normalize_addr(entry);
+            if !resolver::is_dns_name(&entry) {
+                bootstrap.push(entry);
+                continue;
+            }
+            let resolved = resolver::resolve_entry(self.resolver.as_ref(), &entry, &self.my_addr);
+            if resolved.is_empty() && !entry.starts_with(resolver::SRV_PREFIX) {
+                // çözülemeyen isim olduğu gibi eklenir, bağlanırken tekrar çözülür
+                bootstrap.push(entry.clone());
+            }
+            bootstrap.extend(resolved);
+            if !self.dns_names.contains(&entry) {
+                self.dns_names.push(entry);
+            }
+        }
+        bootstrap
+    }
+
+    fn thread_resolve(&self) {
+        if self.dns_names.is_empty() || self.dns_refresh_millis == 0 {
+            return;
+        }
+        let resolver = self.resolver.clone();
+        let dns_names = self.dns_names.clone();
+        let my_addr = self.my_addr.clone();
+        let resolved_addrs = self.resolved_addrs.clone();
+        let running = self.running.clone();
+        let clock = self.clock.clone();
+        let refresh_millis = self.dns_refresh_millis;
+        let span = self.span.clone();
+        thread::spawn(move || {
+            let _span_guard = span.enter();
+            let mut next_refresh = clock.now_millis() + refresh_millis;
+            while running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(100));
+                if clock.now_millis() < next_refresh {
+                    continue;
+                }
+                next_refresh = clock.now_millis() + refresh_millis;
+                for name in dns_names.iter() {
+                    let resolved = resolver::resolve_entry(resolver.as_ref(), name, &my_addr);
+                    resolved_addrs.lock().unwrap().extend(resolved);
+                }
+            }
+        });
+    }
+
+    // aynı cluster'dan duyuru yapan node'lar resolved_addrs üzerinden eklenir
+    fn thread_discovery(&self, conf: &Config) {
+        let group = match conf.discovery_group.parse::<SocketAddr>() {
+            Ok(group) => group,
+            Err(_) => {
+                error!(group = %conf.discovery_group, "invalid discovery group");
+                return;
+            }
+        };
+        let discovery = Discovery {
+            group,
+            interval_millis: conf.discovery_interval.max(100) as u128,
+            cluster_id: conf.cluster_id.clone(),
+            cluster_token: self.admission.cluster_token.clone(),
+            node_id: self.node_id(),
+            my_addr: self.my_addr.clone(),
+        };
+        if discovery::start(
+            discovery,
+            self.clock.clone(),
+            self.running.clone(),
+            self.resolved_addrs.clone(),
+            self.span.clone(),
+        ) {
+            debug!(group = %group, "discovery started");
+        }
+    }
+
+    // DNS'ten veya discovery'den yeni gelen adresler node listesine eklenir, var olanlar değişmez
+    fn add_resolved_addrs(&mut self) {
+        let resolved: Vec<String> = self.resolved_addrs.lock().unwrap().drain(..).collect();
+        if !resolved.is_empty() && self.add_node_to_lists(resolved) {
+            *self.node_hash.lock().unwrap() = self.node_list.calculate_hash();
+            trace!("resolved addresses added");
+        }
+    }
+
+    /// The address this node is known by, the first advertised address.
+    pub fn local_addr(&self) -> String {
+        self.my_addr.clone()
+    }
+
+    /// The address the listener is actually bound to.
+    pub fn listen_addr(&self) -> String {
+        self.listen_addr.clone()
+    }
+
+    /// Every address this node advertises to its peers.
+    pub fn advertised_addrs(&self) -> Vec<String> {
+        self.handshake.lock().unwrap().addrs.clone()
+    }
+
+    /// Whether peers can connect to this node's advertised addresses, as
+    /// measured by asking peers to dial back. Stays `Unknown` unless
+    /// `nat_traversal` is on.
+    pub fn reachability(&self) -> Reachability {
+        self.nat.lock().unwrap().reachability.clone()
+    }
+
+    /// The address most peers see this node's connections coming from,
+    /// e.g. the public address of the NAT in front of it.
+    pub fn public_addr(&self) -> Option<String> {
+        self.nat.lock().unwrap().public_addr()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Id of this node. It is created on the first `start` and stored in
+    /// `storage_dir`, so it survives restarts and address changes.
+    pub fn node_id(&self) -> String {
+        self.handshake.lock().unwrap().node_id.clone()
+    }
+
+    /// Sets an application defined key on this node, e.g. `role` or
+    /// `region`. Peers see it in `PeerInfo::metadata` after their next ping
+    /// or node list sync. Keys from `Config::metadata` can be overridden.
+    pub fn set_metadata(&mut self, key: &str, value: &str) {
+        self.handshake
+            .lock()
+            .unwrap()
+            .metadata
+            .insert(key.to_string(), value.to_string());
+        self.bump_metadata_version();
+    }
+
+    pub fn remove_metadata(&mut self, key: &str) {
+        self.handshake.lock().unwrap().metadata.remove(key);
+        self.bump_metadata_version();
+    }
+
+    fn bump_metadata_version(&mut self) {
+        {
+            let mut local_handshake = self.handshake.lock().unwrap();
+            local_handshake.metadata_version =
+                (local_handshake.metadata_version + 1).max(self.clock.now_millis() as u64);
+        }
+        self.apply_local_handshake();
+    }
+
+    // kendi node kaydımız handshake ile aynı bilgiyi gösterir
+    fn apply_local_handshake(&mut self) {
+        let local_handshake = self.handshake.lock().unwrap().clone();
+        for n_info in self.node_list.lock().unwrap().iter_mut() {
+            if n_info.addr.eq(&self.my_addr) {
+                local_handshake.apply_to(n_info);
+            }
         }
     }
 
@@ -93,7 +570,10 @@ impl MessagePool {
                 self.process_state(income_msg);
             }
             MessageKind::Distribute => {
-                debug!("distribute message");
+                debug!(msg_id = income_msg.id, peer = %income_msg.sender, "distribute message");
+            }
+            MessageKind::Stream | MessageKind::Dht | MessageKind::Relay | MessageKind::Nat => {
+                // stream parçaları, DHT, relay ve NAT mesajları handle_connection içinde işlenir
             }
         }
     }
@@ -101,26 +581,53 @@ impl MessagePool {
     fn process_state(&mut self, income_msg: Message) {
         match income_msg.payload.to_state_struct() {
             StateType::Unknown() => {
-                println!("hatali state geldi");
+                let newer_peer = income_msg
+                    .handshake
+                    .as_ref()
+                    .is_some_and(|hs| hs.protocol_version > PROTOCOL_VERSION);
+                if newer_peer {
+                    debug!(msg_id = income_msg.id, peer = %income_msg.sender, "state from newer protocol ignored");
+                } else {
+                    warn!(msg_id = income_msg.id, peer = %income_msg.sender, "unknown state message");
+                }
             }
             StateType::Ping(income_node_list_hash) => {
-                trace!("Ping Msg Arrived");
+                trace!(peer = %income_msg.sender, "ping arrived");
                 self.add_node_to_list(income_msg.sender.clone());
-                if self.store_node_list_active == true {
-                    self.node_list.store_to_disk(self.my_addr.clone());
+                if self.store_node_list_active {
+                    self.node_list
+                        .store_to_disk(self.node_list_file(), &self.my_addr);
                 }
 
                 let new_node_hash = self.node_list.calculate_hash();
-                if self.node_hash.lock().unwrap().eq(&new_node_hash) == false {
+                if !self.node_hash.lock().unwrap().eq(&new_node_hash) {
                     *self.node_hash.lock().unwrap() = new_node_hash.clone();
                 }
 
                 for item in self.node_list.lock().unwrap().iter_mut() {
                     if item.addr.eq(&income_msg.sender.clone()) {
                         item.node_hash = income_node_list_hash.clone();
+                        if let Some(peer_handshake) = &income_msg.handshake {
+                            peer_handshake.apply_to(item);
+                        }
+                        // NAT arkasındaki node'a her zaman bağlanamayız, ping atması online olduğunu gösterir
+                        if item.reachability == Reachability::Private {
+                            item.last_access_time = self.clock.now_millis();
+                            if item.status != NodeStatus::Online {
+                                item.status = NodeStatus::Online;
+                                self.node_status_change
+                                    .lock()
+                                    .unwrap()
+                                    .push((item.addr.clone(), NodeStatus::Online));
+                            }
+                        }
                         break;
                     }
                 }
+                if self.merge_moved_node(&income_msg.sender) && self.store_node_list_active {
+                    self.node_list
+                        .store_to_disk(self.node_list_file(), &self.my_addr);
+                }
                 *self.node_hash.lock().unwrap() = self.node_list.calculate_hash();
             }
             StateType::ControlNodeStatus(new_status_node_addr) => {
@@ -133,20 +640,86 @@ impl MessagePool {
                 }
             }
             StateType::NodeList(income_node_list) => {
-                trace!("Node List Arrived");
-                if self.add_node_to_lists(income_node_list.clone()) == true {
+                trace!(peer = %income_msg.sender, "node list arrived");
+                // adresi değişmiş node'ların eski adresleri tekrar eklenmez
+                let income_node_list: Vec<String> = income_node_list
+                    .into_iter()
+                    .filter(|node_addr| !self.is_stale_addr(node_addr, &income_msg.node_metadata))
+                    .collect();
+                if self.add_node_to_lists(income_node_list.clone()) {
                     *self.node_hash.lock().unwrap() = self.node_list.calculate_hash();
                     *self.node_hash_updated.lock().unwrap() = false;
-                    if self.store_node_list_active == true {
-                        self.node_list.store_to_disk(self.my_addr.clone());
+                    if self.store_node_list_active {
+                        self.node_list
+                            .store_to_disk(self.node_list_file(), &self.my_addr);
                     }
-                    trace!("Send Node List To =>{}", income_msg.sender.clone());
+                    trace!(peer = %income_msg.sender, "node list merged");
                 } else {
-                    trace!("Node List Equal With {}", income_msg.sender.clone());
+                    trace!(peer = %income_msg.sender, "node list equal");
+                }
+                if self
+                    .node_list
+                    .merge_node_metadata(&income_msg.node_metadata, &self.my_addr)
+                {
+                    trace!(peer = %income_msg.sender, "node metadata merged");
+                }
+                let mut moved = false;
+                for node_addr in income_node_list.iter() {
+                    moved |= self.merge_moved_node(node_addr);
                 }
+                if moved {
+                    *self.node_hash.lock().unwrap() = self.node_list.calculate_hash();
+                    if self.store_node_list_active {
+                        self.node_list
+                            .store_to_disk(self.node_list_file(), &self.my_addr);
+                    }
+                }
+            }
+        }
+    }
+    // node_addr'de bilinen bir node_id varsa eski kaydı bu adrese taşır
+    fn merge_moved_node(&mut self, node_addr: &str) -> bool {
+        if node_addr.eq(&self.my_addr) {
+            return false;
+        }
+        match self.node_list.merge_moved_node(node_addr) {
+            Some((node_id, old_addr)) => {
+                debug!(node_id = %node_id, old_addr = %old_addr, new_addr = %node_addr, "node address changed");
+                self.metrics.forget_peer(&old_addr);
+                self.nat.lock().unwrap().forget_peer(&old_addr);
+                self.events
+                    .lock()
+                    .unwrap()
+                    .push(EventType::OnNodeAddressChanged(
+                        node_id,
+                        old_addr,
+                        node_addr.to_string(),
+                    ));
+                true
             }
+            None => false,
         }
     }
+
+    // node listesinde gelen adres, aynı node'un bizde bulunan daha yeni adresinden eskiyse
+    fn is_stale_addr(&self, node_addr: &str, node_metadata: &[NodeMetadata]) -> bool {
+        let Some(income) = node_metadata
+            .iter()
+            .find(|income| income.addr.eq(node_addr) && !income.node_id.is_empty())
+        else {
+            return false;
+        };
+        if income.node_id.eq(&self.node_id()) {
+            // yeniden başlamadan önceki kendi adresimiz
+            return !node_addr.eq(&self.my_addr);
+        }
+        self.node_list.lock().unwrap().iter().any(|n_info| {
+            n_info.node_id.eq(&income.node_id)
+                && !n_info.addr.eq(node_addr)
+                && n_info.metadata_version >= income.version
+        })
+    }
+
     pub fn get_message(&mut self) -> Message {
         let income_msg = self.msg_list.lock().unwrap()[0].clone();
         self.dispose_message();
@@ -159,66 +732,318 @@ impl MessagePool {
     }
 
     pub fn send_to(&mut self, receiver: String, payload: Vec<u8>) {
-        _ = helper::client_async(
-            receiver.clone(),
-            Message {
-                id: helper::get_sys_time_in_nano(),
-                sender: self.my_addr.clone(),
-                kind: MessageKind::Distribute,
-                payload: payload,
-            }
-            .to_byte_array(),
+        let msg = Message {
+            id: self.ids.next(),
+            sender: self.my_addr.clone(),
+            kind: MessageKind::Distribute,
+            payload,
+            cluster_token: self.admission.cluster_token.clone(),
+            payload_codec: String::new(),
+            handshake: None,
+            node_metadata: Vec::new(),
+        };
+        let msg = codec::compress(&msg, self.peer_compression_threshold(&receiver)).unwrap_or(msg);
+        self.send_routed(&receiver, &msg);
+    }
+
+    /// The node a message to `node_addr` goes through and the number of
+    /// hops to it. `node_addr` itself with 1 hop if it is reachable directly.
+    ///
+    /// Routes are learned from the peers' pings, every node tells its
+    /// neighbours which nodes it reaches and in how many hops.
+    pub fn next_hop(&self, node_addr: &str) -> Option<(String, u8)> {
+        let node_list = self.node_list.lock().unwrap().clone();
+        relay::next_hop(&node_list, &self.my_addr, node_addr, "")
+    }
+
+    // doğrudan ulaşılamayan node'a mesaj, rotası biliniyorsa bir komşu üzerinden gider
+    fn send_routed(&self, receiver: &str, msg: &Message) {
+        let (kind, dial_addr, msg_payload) = match self.next_hop(receiver) {
+            Some((relay_addr, hops)) if !relay_addr.eq(receiver) => {
+                trace!(peer = %receiver, relay = %relay_addr, hops, "sending through relay");
+                let envelope = RelayEnvelope {
+                    receiver: receiver.to_string(),
+                    hops_left: MAX_RELAY_HOPS - 1,
+                    inner: msg.clone(),
+                };
+                let relay_msg = relay::wrap(
+                    &envelope,
+                    &self.my_addr,
+                    &self.admission.cluster_token,
+                    &self.ids,
+                );
+                (
+                    MessageKind::Relay,
+                    self.node_list.dial_addr_of(&relay_addr),
+                    relay_msg.to_compact_byte_array(),
+                )
+            }
+            _ => (
+                msg.kind.clone(),
+                self.node_list.dial_addr_of(receiver),
+                msg.to_byte_array(),
+            ),
+        };
+        self.metrics.record_sent(&kind, msg_payload.len());
+        transport::request_async(
+            self.transport.clone(),
+            dial_addr,
+            msg_payload,
+            self.metrics.clone(),
         );
     }
     pub fn distribute(&mut self, payload: Vec<u8>) {
-        trace!("Distributing To Nodes");
-        let msg_payload = Message {
-            id: helper::get_sys_time_in_nano(),
+        trace!("distributing to nodes");
+        self.distribute_filtered(payload, |_| true);
+    }
+
+    /// Sends `payload` only to the nodes whose metadata has `key` set to
+    /// `value`, e.g. `distribute_to_tagged("role", "indexer", payload)`.
+    pub fn distribute_to_tagged(&mut self, key: &str, value: &str, payload: Vec<u8>) {
+        trace!(key, value, "distributing to tagged nodes");
+        self.distribute_filtered(payload, |n_info| {
+            n_info.metadata.get(key).is_some_and(|v| v.eq(value))
+        });
+    }
+
+    fn distribute_filtered<F>(&mut self, payload: Vec<u8>, filter: F)
+    where
+        F: Fn(&NodeDetails) -> bool,
+    {
+        let msg = Message {
+            id: self.ids.next(),
             sender: self.my_addr.clone(),
             kind: MessageKind::Distribute,
-            payload: payload,
+            payload,
+            cluster_token: self.admission.cluster_token.clone(),
+            payload_codec: String::new(),
+            handshake: None,
+            node_metadata: Vec::new(),
+        };
+        let raw_payload = msg.to_byte_array();
+        let compressed_payload = codec::compress(&msg, self.hard_config.compression_threshold)
+            .map(|encoded| encoded.to_byte_array());
+
+        // sağlıklı bağlantılar önce
+        let mut tmp_node_list = self.node_list.lock().unwrap().clone();
+        tmp_node_list.sort_by_key(|n_info| n_info.link_quality.score());
+        for n_info in tmp_node_list.iter() {
+            if !n_info.addr.eq(&self.my_addr.clone()) && filter(n_info) {
+                if n_info.status != NodeStatus::Online {
+                    self.send_routed(&n_info.addr, &msg);
+                    continue;
+                }
+                let msg_payload = match &compressed_payload {
+                    Some(compressed) if codec::accepts_compressed(&n_info.codecs) => compressed,
+                    _ => &raw_payload,
+                };
+                self.metrics
+                    .record_sent(&MessageKind::Distribute, msg_payload.len());
+                transport::request_async(
+                    self.transport.clone(),
+                    n_info.dial_addr().to_string(),
+                    msg_payload.clone(),
+                    self.metrics.clone(),
+                );
+            }
+        }
+    }
+
+    /// Sends everything `source` yields to `receiver` in chunks, on a
+    /// background thread, and returns the stream id.
+    ///
+    /// The receiver gets `OnStreamData` events in order followed by
+    /// `OnStreamCompleted` once the checksum matches. This node gets
+    /// `OnStreamProgress` after every acknowledged chunk, then
+    /// `OnStreamCompleted` or `OnStreamFailed`. A dropped connection is
+    /// retried from the last offset the receiver acknowledged.
+    pub fn send_stream<R>(&mut self, receiver: String, source: R) -> u128
+    where
+        R: Read + Seek + Send + 'static,
+    {
+        let stream_id = self.ids.next();
+        let compression_threshold = self.peer_compression_threshold(&receiver);
+        OutgoingStream {
+            stream_id,
+            my_addr: self.my_addr.clone(),
+            dial_addr: self.node_list.dial_addr_of(&receiver),
+            receiver,
+            cluster_token: self.admission.cluster_token.clone(),
+            chunk_size: self.hard_config.stream_chunk_size,
+            compression_threshold,
+            source,
+            transport: self.transport.clone(),
+            ids: self.ids.clone(),
+            metrics: self.metrics.clone(),
+            state: self.streams.clone(),
+            running: self.running.clone(),
+        }
+        .spawn();
+        stream_id
+    }
+
+    // karşı taraf sıkıştırmayı desteklemiyorsa 0, yani kapalı
+    fn peer_compression_threshold(&self, node_addr: &str) -> usize {
+        let peer_accepts =
+            self.node_list.lock().unwrap().iter().any(|n_info| {
+                n_info.addr.eq(node_addr) && codec::accepts_compressed(&n_info.codecs)
+            });
+        match peer_accepts {
+            true => self.hard_config.compression_threshold,
+            false => 0,
+        }
+    }
+
+    /// Finds the address of `node_id` through the DHT, asking nodes closer
+    /// to it each round, so the node does not have to be in our node list.
+    /// Returns `None` if the DHT is off or no node knows it.
+    pub fn find_node(&self, node_id: &str) -> Option<String> {
+        let dht_client = self.dht_client()?;
+        dht_client.find_node(node_id).map(|contact| contact.addr)
+    }
+
+    /// Stores `value` on the `dht_replication` nodes closest to `key` and
+    /// returns how many of them accepted it. A later put of the same key
+    /// replaces the value. Values larger than 64 KiB are rejected.
+    pub fn dht_put(&mut self, key: &str, value: Vec<u8>) -> usize {
+        let Some(dht_client) = self.dht_client() else {
+            return 0;
+        };
+        if value.len() > dht::MAX_VALUE_SIZE {
+            warn!(key, size = value.len(), "dht value too large");
+            return 0;
+        }
+        let value = DhtValue {
+            data: value,
+            version: self.clock.now_micros() as u64,
+        };
+        dht_client.put(key, value)
+    }
+
+    /// Reads the newest value stored under `key` from the nodes closest to it.
+    pub fn dht_get(&self, key: &str) -> Option<Vec<u8>> {
+        let dht_client = self.dht_client()?;
+        dht_client.get(key).map(|value| value.data)
+    }
+
+    fn dht_client(&self) -> Option<DhtClient> {
+        if !self.dht.lock().unwrap().enabled {
+            return None;
         }
-        .to_byte_array();
+        Some(DhtClient {
+            my_addr: self.my_addr.clone(),
+            cluster_token: self.admission.cluster_token.clone(),
+            replication: self.hard_config.dht_replication,
+            dht: self.dht.clone(),
+            node_list: self.node_list.clone(),
+            transport: self.transport.clone(),
+            ids: self.ids.clone(),
+            metrics: self.metrics.clone(),
+        })
+    }
 
+    pub fn peers(&self) -> Vec<PeerInfo> {
+        let mut peer_list = Vec::new();
         for n_info in self.node_list.lock().unwrap().iter() {
-            if n_info.addr.eq(&self.my_addr.clone()) == false {
-                _ = helper::client_async(n_info.addr.to_string(), msg_payload.clone());
+            if !n_info.addr.eq(&self.my_addr) {
+                peer_list.push(PeerInfo::from(n_info));
             }
         }
+        peer_list
+    }
+
+    pub fn peer(&self, node_addr: &str) -> Option<PeerInfo> {
+        self.node_list
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|n_info| n_info.addr.eq(node_addr))
+            .map(PeerInfo::from)
+    }
+
+    pub fn peer_by_id(&self, node_id: &str) -> Option<PeerInfo> {
+        self.node_list
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|n_info| !node_id.is_empty() && n_info.node_id.eq(node_id))
+            .map(PeerInfo::from)
+    }
+
+    pub fn peers_by_status(&self, status: NodeStatus) -> Vec<PeerInfo> {
+        self.peers()
+            .into_iter()
+            .filter(|peer| peer.status == status)
+            .collect()
+    }
+
+    pub fn peers_with_metadata(&self, key: &str, value: &str) -> Vec<PeerInfo> {
+        self.peers()
+            .into_iter()
+            .filter(|peer| peer.metadata.get(key).is_some_and(|v| v.eq(value)))
+            .collect()
+    }
+
+    pub fn local_node(&self) -> Option<PeerInfo> {
+        self.peer(&self.my_addr.clone())
+    }
+
+    // en düşük gecikmeli ve en az kayıplı online node
+    pub fn nearest_peer(&self) -> Option<PeerInfo> {
+        self.peers()
+            .into_iter()
+            .filter(|peer| peer.status == NodeStatus::Online)
+            .min_by_key(|peer| peer.link_quality.score())
     }
 
     pub fn on_event(&mut self) -> EventType {
+        let _span_guard = self.span.clone().entered();
+        self.add_resolved_addrs();
         let (node_addr, node_status) = self.status_changed();
         match node_status {
             NodeStatus::Online => {
-                info!(
-                    "{}            => {}",
-                    "Online".bright_green(),
-                    node_addr.clone()
-                );
+                info!(peer = %node_addr, status = %NodeStatus::Online, "node status changed");
                 return EventType::OnNodeStatusChanged(node_addr, NodeStatus::Online);
             }
             NodeStatus::Offline => {
-                info!(
-                    "{}           => {}",
-                    "Offline".bright_red(),
-                    node_addr.clone()
-                );
+                info!(peer = %node_addr, status = %NodeStatus::Offline, "node status changed");
                 return EventType::OnNodeStatusChanged(node_addr, NodeStatus::Offline);
             }
             _ => {}
         }
 
-        if self.node_hash_updated.lock().unwrap().clone() == true {
+        let stream_event = {
+            let mut streams = self.streams.lock().unwrap();
+            streams.expire_idle();
+            streams.pop_event()
+        };
+        if let Some(stream_event) = stream_event {
+            return stream_event;
+        }
+
+        let queued_event = self.events.lock().unwrap().pop();
+        if let Some(queued_event) = queued_event {
+            match &queued_event {
+                EventType::OnIncompatiblePeer(node_addr, reason) => {
+                    warn!(peer = %node_addr, reason = %reason, "incompatible peer");
+                }
+                EventType::OnNodeAddressChanged(node_id, old_addr, new_addr) => {
+                    info!(node_id = %node_id, old_addr = %old_addr, new_addr = %new_addr, "node address changed");
+                }
+                _ => {}
+            }
+            return queued_event;
+        }
+
+        if *self.node_hash_updated.lock().unwrap() {
             *self.node_hash_updated.lock().unwrap() = false;
             let current_node_list_hash = self.node_list_synced.lock().unwrap().clone();
-            if current_node_list_hash.len() > 0 {
+            if !current_node_list_hash.is_empty() {
                 let n_list_hash = self.node_list_synced.lock().unwrap().clone();
                 info!(
-                    "{}  => {} [ {} ]",
-                    "Node List Synced".bright_green(),
-                    n_list_hash.clone(),
-                    self.node_list.online_node_count()
+                    node_hash = %n_list_hash,
+                    online = self.node_list.online_node_count(),
+                    "node list synced"
                 );
                 return EventType::OnNodesSynced(n_list_hash.clone());
             }
@@ -228,13 +1053,11 @@ impl MessagePool {
         if msg_kind == MessageKind::Distribute {
             let income_msg = self.get_message();
             info!(
-                "{}  => {} >> {}",
-                "Message Received".bright_green(),
-                income_msg.sender.clone().bright_blue(),
-                match String::from_utf8(income_msg.payload.clone()) {
-                    Ok(income) => income,
-                    Err(_) => "".to_string(),
-                }
+                msg_id = income_msg.id,
+                peer = %income_msg.sender,
+                kind = %income_msg.kind,
+                size = income_msg.payload.len(),
+                "message received"
             );
             return EventType::OnMessage(income_msg);
         }
@@ -243,10 +1066,10 @@ impl MessagePool {
             let _income_msg = self.get_message();
         }
 
-        return EventType::OnWait();
+        EventType::OnWait()
     }
     pub fn status_changed(&mut self) -> (String, NodeStatus) {
-        if self.node_status_change.lock().unwrap().len() == 0 {
+        if self.node_status_change.lock().unwrap().is_empty() {
             return (String::new(), NodeStatus::Unknown);
         }
 
@@ -268,7 +1091,7 @@ impl MessagePool {
     }
 
     pub fn select(&mut self) -> MessageKind {
-        if self.msg_list.lock().unwrap().len() > 0 {
+        if !self.msg_list.lock().unwrap().is_empty() {
             let income_msg = self.msg_list.lock().unwrap()[0].clone();
             if income_msg.kind == MessageKind::Distribute || income_msg.kind == MessageKind::State {
                 return income_msg.kind;
@@ -278,265 +1101,140 @@ impl MessagePool {
     }
 
     fn thread_ping(&self) {
-        let my_node_addr = self.my_addr.clone();
-        let node_list = self.node_list.clone();
-        let node_status_change = self.node_status_change.clone();
-        let current_node_hash = self.node_hash.clone();
-        let my_node_hash = self.node_hash.clone();
-        let node_hash_updated = self.node_hash_updated.clone();
-        let node_list_synced = self.node_list_synced.clone();
-        let hard_config_ping_time = self.hard_config.ping_time;
+        let mut ping_loop = self.ping_loop();
+        let span = self.span.clone();
+        let running = self.running.clone();
+        let clock = self.clock.clone();
         thread::spawn(move || {
-            let mut all_node_list_changed = true;
-            let mut ping_time_diff = 0;
-            loop {
-                let mut next_ping_time_diff = hard_config_ping_time;
-                let mut update_node_hash_value = false;
-                let mut update_node_status = Vec::new();
-                let mut update_time = Vec::new();
-                let mut move_to_offline_node = usize::MAX;
-                let sending_data = Message {
-                    id: helper::get_sys_time_in_nano(),
-                    sender: my_node_addr.clone(),
-                    kind: MessageKind::State,
-                    payload: StateType::Ping(current_node_hash.lock().unwrap().clone())
-                        .to_byte_array(),
-                }
-                .to_byte_array();
-                let tmp_node_list = node_list.lock().unwrap().clone();
-                for (n_index, n_info) in tmp_node_list.iter().enumerate() {
-                    if move_to_offline_node == usize::MAX
-                        && my_node_addr.eq(&n_info.addr.clone()) == false
-                    {
-                        let send_ping_to_node = match n_info.status {
-                            NodeStatus::Online => {
-                                let curr_millis = helper::get_sys_time_in_millis();
-                                let time_diff = n_info.last_access_time.abs_diff(curr_millis);
-                                if time_diff > ping_time_diff {
-                                    true
-                                } else {
-                                    false
-                                }
-                            }
-                            NodeStatus::Offline => {
-                                let current_time = helper::get_sys_time_in_secs();
-                                if current_time.abs_diff(n_info.last_access_time) > 10 {
-                                    // TO-DO
-                                    // eğer kontrol edildiği zaman yine offline ise,
-                                    // bu listeden çıkartıp offline listesine al
-                                    debug!("{}   => {}", n_info.addr.clone(), "testing offline");
-                                    next_ping_time_diff = 0;
-                                    true
-                                } else {
-                                    false
-                                }
-                            }
-                            NodeStatus::Unknown => {
-                                next_ping_time_diff = 0;
-                                debug!("{}   => {}", n_info.addr.clone(), "testing unknown");
-                                true
-                            }
-                        };
-
-                        if send_ping_to_node == true {
-                            let result =
-                                helper::client(n_info.addr.to_string(), sending_data.clone());
-                            if result.kind == MessageKind::Ok {
-                                update_time
-                                    .push((n_info.addr.clone(), helper::get_sys_time_in_millis()));
-                                if n_info.status != NodeStatus::Online {
-                                    update_node_status
-                                        .push((n_info.addr.clone(), NodeStatus::Online));
-                                    node_status_change
-                                        .lock()
-                                        .unwrap()
-                                        .push((n_info.addr.clone(), NodeStatus::Online));
-                                    update_node_hash_value = true;
-                                }
-                            } else {
-                                if result.id == 5 {
-                                    update_node_hash_value = true;
-                                    match n_info.status {
-                                        NodeStatus::Online => {
-                                            let offline_node_addr = n_info.addr.clone();
-                                            // println!("offline_node_addr: {}", offline_node_addr);
-
-                                            let state_msg_vec = Message {
-                                                id: helper::get_sys_time_in_nano(),
-                                                sender: my_node_addr.clone(),
-                                                kind: MessageKind::State,
-                                                payload: StateType::ControlNodeStatus(
-                                                    offline_node_addr.clone(),
-                                                )
-                                                .to_byte_array(),
-                                            }
-                                            .to_byte_array();
-                                            // omergoksoy
-                                            // burada online olan bir nodu'un offline olduğunu öğrendik
-                                            // bu durumu diğer nodelara state olarak iletiyoruz
-                                            for node_addr in node_list.to_node_list().iter() {
-                                                if node_addr.eq(&offline_node_addr.clone()) == false
-                                                    && node_addr.eq(&my_node_addr.clone()) == false
-                                                {
-                                                    // println!("node'u ilet => {}", helper::get_sys_time_in_nano() );
-                                                    client_async(
-                                                        node_addr.clone(),
-                                                        state_msg_vec.clone(),
-                                                    );
-                                                }
-                                            }
-                                            update_node_status.push((
-                                                offline_node_addr.clone(),
-                                                NodeStatus::Offline,
-                                            ));
-                                            node_status_change.lock().unwrap().push((
-                                                offline_node_addr.clone(),
-                                                NodeStatus::Offline,
-                                            ));
-                                        }
-                                        NodeStatus::Offline => {
-                                            move_to_offline_node = n_index.clone();
-                                        }
-                                        NodeStatus::Unknown => {
-                                            update_node_status
-                                                .push((n_info.addr.clone(), NodeStatus::Offline));
-                                            node_status_change
-                                                .lock()
-                                                .unwrap()
-                                                .push((n_info.addr.clone(), NodeStatus::Unknown));
-                                        }
-                                    }
-                                } else {
-                                    if result.id != 9 {
-                                        debug!("result: {:?}", result);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
-                if move_to_offline_node != usize::MAX {
-                    node_list.lock().unwrap().remove(move_to_offline_node);
-                    all_node_list_changed = true;
-                } else {
-                    for (n_addr, n_time) in update_time.iter() {
-                        for n_info in node_list.lock().unwrap().iter_mut() {
-                            if n_info.addr.eq(n_addr) {
-                                n_info.last_access_time = n_time.clone();
-                            }
-                        }
-                    }
-
-                    for (o_node, n_status) in update_node_status.iter() {
-                        for n_info in node_list.lock().unwrap().iter_mut() {
-                            if n_info.addr.eq(o_node) {
-                                n_info.status = n_status.clone();
-                            }
-                        }
-                    }
+            let _span_guard = span.enter();
+            while running.load(Ordering::SeqCst) {
+                ping_loop.round(clock.now_millis());
+            }
+        });
+    }
 
-                    // asenkron olduğu için aynı işlemi tekrar tekrar yapıyor
-                    let mut update_sync_time = Vec::new();
-                    let tmp_node_list = node_list.lock().unwrap().clone();
-                    for n_info in tmp_node_list.iter() {
-                        if my_node_addr.eq(&n_info.addr.clone()) == false {
-                            if my_node_hash.lock().unwrap().eq(&n_info.node_hash) == false {
-                                if n_info.status == NodeStatus::Online {
-                                    let time_diff = helper::get_sys_time_in_millis()
-                                        - n_info.synced_time_as_secs;
-                                    if time_diff > 100 {
-                                        update_sync_time.push(n_info.addr.clone());
-                                        trace!(
-                                            "Sync With  [ {} ]  => {}",
-                                            time_diff,
-                                            n_info.addr.clone()
-                                        );
-
-                                        node_list.send_state_to_all(
-                                            my_node_addr.clone(),
-                                            StateType::NodeList(node_list.to_node_list()),
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                    }
+    fn ping_loop(&self) -> PingLoop {
+        PingLoop::new(
+            self.my_addr.clone(),
+            self.node_list.clone(),
+            self.node_status_change.clone(),
+            self.node_hash.clone(),
+            self.node_hash_updated.clone(),
+            self.node_list_synced.clone(),
+            self.hard_config.ping_time,
+            self.admission.cluster_token.clone(),
+            self.metrics.clone(),
+            self.transport.clone(),
+            self.clock.clone(),
+            self.ids.clone(),
+            self.hard_config.compression_threshold,
+            self.handshake.clone(),
+            self.events.clone(),
+            self.nat.clone(),
+        )
+    }
 
-                    node_list
-                        .clone()
-                        .set_sync_time(update_sync_time, helper::get_sys_time_in_millis());
-
-                    if helper::control_nodes_hash(node_list.lock().unwrap().clone()) {
-                        if all_node_list_changed == true {
-                            all_node_list_changed = false;
-                            let node_hash_cloned = my_node_hash.lock().unwrap().clone();
-                            let current_list_hash = node_list_synced.lock().unwrap().clone();
-                            if current_list_hash.eq(&node_hash_cloned.clone()) == false {
-                                *node_list_synced.lock().unwrap() = node_hash_cloned.clone();
-                                *node_hash_updated.lock().unwrap() = true;
-                            }
-                        }
-                    } else {
-                        all_node_list_changed = true;
-                    }
-                }
+    pub fn pool_stats(&self) -> PoolStats {
+        match &self.pool {
+            Some(pool) => pool.stats(),
+            None => PoolStats::default(),
+        }
+    }
 
-                if update_node_hash_value == true {
-                    let new_node_list_hash = node_list.calculate_hash();
-                    if current_node_hash
-                        .lock()
-                        .unwrap()
-                        .clone()
-                        .eq(&new_node_list_hash)
-                        == false
-                    {
-                        all_node_list_changed = true;
-                    }
-                    *current_node_hash.lock().unwrap() = new_node_list_hash.clone();
-                    for n_info in node_list.lock().unwrap().iter_mut() {
-                        if my_node_addr.eq(&n_info.addr.clone()) {
-                            if n_info.node_hash.eq(&new_node_list_hash.clone()) == false {
-                                all_node_list_changed = true;
-                                n_info.node_hash = new_node_list_hash.clone();
-                            }
-                        }
-                    }
-                }
+    pub fn resize_workers(&mut self, worker_count: usize) {
+        self.hard_config.worker_count = worker_count.max(1);
+        if let Some(pool) = &self.pool {
+            pool.resize(self.hard_config.worker_count);
+        }
+    }
 
-                ping_time_diff = next_ping_time_diff;
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.metrics.snapshot(
+            self.node_list.online_node_count(),
+            self.msg_list.lock().unwrap().len(),
+            self.pool_stats(),
+        )
+    }
 
-                if ping_time_diff > 0 {
-                    //thread::sleep(Duration::from_millis(50));
-                }
-            }
+    fn thread_metrics(&mut self, metrics_addr: String) {
+        let metrics = self.metrics.clone();
+        let node_list = self.node_list.clone();
+        let msg_list = self.msg_list.clone();
+        let pool = self.pool.clone();
+        self.metrics_server = metrics::serve(metrics_addr, self.running.clone(), move || {
+            metrics.snapshot(
+                node_list.online_node_count(),
+                msg_list.lock().unwrap().len(),
+                match &pool {
+                    Some(pool) => pool.stats(),
+                    None => PoolStats::default(),
+                },
+            )
         });
     }
 
-    fn thread_socket(&self) -> bool {
-        let listener = TcpListener::bind(self.my_addr.clone());
-        if listener.is_err() {
-            return false;
+    /// Stops the ping loop and the listeners, then joins the metrics thread and the
+    /// connection workers.
+    pub fn shutdown(&mut self) {
+        if self.running.swap(false, Ordering::SeqCst) {
+            // accept() üzerinde bekleyen dinleyiciyi uyandır
+            self.transport.close(&self.listen_addr);
         }
-        let listener = listener.unwrap();
-        let pool = ThreadPool::new(4);
-        let msg_list_cloned = self.msg_list.clone();
-        let my_addr = self.my_addr.clone();
+        if let Some(mut metrics_server) = self.metrics_server.take() {
+            metrics_server.stop();
+        }
+        if let Some(pool) = self.pool.take() {
+            pool.shutdown();
+        }
+    }
+
+    // her dinleyici için ayrı bir thread, bağlantıları aynı worker'lar işler
+    fn thread_socket(&mut self, listeners: Vec<Box<dyn TransportListener>>) {
+        let job_timeout = Duration::from_millis(self.hard_config.job_timeout);
+        let pool = Arc::new(ThreadPool::new(self.hard_config.worker_count, job_timeout));
+        self.pool = Some(pool.clone());
+        for listener in listeners {
+            self.thread_accept(listener, pool.clone(), job_timeout);
+        }
+    }
+
+    fn thread_accept(
+        &self,
+        listener: Box<dyn TransportListener>,
+        pool: Arc<ThreadPool>,
+        job_timeout: Duration,
+    ) {
+        let inbound = self.inbound();
+        let span = self.span.clone();
+        let running = self.running.clone();
         thread::spawn(move || loop {
-            for stream in listener.incoming() {
-                let stream = stream.unwrap();
-                let msg_list_cloned_inner = msg_list_cloned.clone();
-                let my_addr_cloned = my_addr.clone();
-                pool.execute(move || {
-                    handle_connection::handle_connection(
-                        my_addr_cloned,
-                        stream,
-                        msg_list_cloned_inner.clone(),
-                    );
-                });
+            let stream = listener.accept();
+            if !running.load(Ordering::SeqCst) {
+                trace!("listener stopped");
+                return;
+            }
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(err) if err.kind() == io::ErrorKind::NotConnected => {
+                    trace!("listener closed");
+                    return;
+                }
+                Err(_) => continue,
+            };
+            if !job_timeout.is_zero() {
+                stream.set_timeout(job_timeout);
+            }
+            let inbound_inner = inbound.clone();
+            let span_inner = span.clone();
+            let queued = pool.execute(move || {
+                let _span_guard = span_inner.enter();
+                inbound_inner.handle(stream);
+            });
+            if !queued {
+                trace!("worker pool shut down, listener stopped");
+                return;
             }
         });
-        return true;
     }
 
     fn update_node_hash(&mut self) {
@@ -552,15 +1250,20 @@ impl MessagePool {
     pub fn add_node_to_lists(&mut self, node_list: Vec<String>) -> bool {
         let mut updated = false;
         for node_addr in node_list.iter() {
-            if self.add_node_to_list(node_addr.to_string()) == true {
+            if self.add_node_to_list(node_addr.to_string()) {
                 updated = true;
             }
         }
-        return updated;
+        updated
     }
 
     pub fn add_node_to_list(&mut self, node_addr: String) -> bool {
-        if node_addr.len() == 0 {
+        let node_addr = resolver::normalize_addr(&node_addr);
+        if node_addr.is_empty() {
+            return false;
+        }
+        if !node_addr.eq(&self.my_addr) && self.advertised_addrs().contains(&node_addr) {
+            // kendi diğer adresimiz, listeye ayrı node olarak girmez
             return false;
         }
         let mut updated = false;
@@ -570,7 +1273,20 @@ impl MessagePool {
                 node_exist = true;
             }
         }
-        if node_exist == false {
+        if !node_exist && !node_addr.eq(&self.my_addr) {
+            if !self.admission.is_addr_allowed(&node_addr) {
+                debug!(peer = %node_addr, "address not allowed");
+                return false;
+            }
+            if !self
+                .admission
+                .has_room(self.node_list.lock().unwrap().len())
+            {
+                debug!(peer = %node_addr, "cluster is full");
+                return false;
+            }
+        }
+        if !node_exist {
             updated = true;
             self.node_list.lock().unwrap().push(NodeDetails {
                 addr: node_addr.clone(),
@@ -579,28 +1295,79 @@ impl MessagePool {
                 } else {
                     NodeStatus::Unknown
                 },
-                synced_time_as_secs: 0,
+                synced_time: 0,
                 last_access_time: 0,
+                offline_since: 0,
                 node_hash: String::new(),
+                link_quality: LinkQuality::default(),
+                metadata: BTreeMap::new(),
+                metadata_version: 0,
+                protocol_version: 0,
+                node_id: String::new(),
+                codecs: Vec::new(),
+                features: Vec::new(),
+                advertised_addrs: Vec::new(),
+                observed_addr: String::new(),
+                reach_addr: String::new(),
+                routes: BTreeMap::new(),
+                reachability: Reachability::Unknown,
             });
             self.node_list.clone().set_sync_time(Vec::new(), 0);
         }
         updated
     }
 
+    fn node_list_file(&self) -> PathBuf {
+        helper::node_list_file(&self.storage_dir, &self.node_id())
+    }
+
+    // node id config'de yoksa saklanan kullanılır, hiç yoksa üretilip saklanır
+    fn load_node_id(&self, conf: &Config) -> String {
+        if !conf.node_id.is_empty() {
+            return conf.node_id.clone();
+        }
+        let generated = format!(
+            "{:x}",
+            md5::compute(format!("{}{}", self.my_addr, self.clock.now_micros()))
+        );
+        if !self.store_node_list_active {
+            return generated;
+        }
+        let id_file = helper::node_id_file(&self.storage_dir);
+        let stored = fs::read_to_string(&id_file).or_else(|_| {
+            fs::read_to_string(helper::legacy_node_id_file(
+                &self.storage_dir,
+                &self.my_addr,
+            ))
+        });
+        match stored {
+            Ok(stored) if !stored.trim().is_empty() => {
+                if !id_file.exists() && fs::write(&id_file, stored.trim()).is_err() {
+                    warn!(file = %id_file.display(), "node id could not be stored");
+                }
+                stored.trim().to_string()
+            }
+            _ => {
+                if fs::write(&id_file, &generated).is_err() {
+                    warn!(file = %id_file.display(), "node id could not be stored");
+                }
+                generated
+            }
+        }
+    }
+
     fn load_from_disk(&self) -> Vec<String> {
-        if self.store_node_list_active == true {
-            match fs::read_to_string(format!(
-                "{}.json",
-                self.my_addr.clone().replace(".", "_").replace(":", "_")
-            )) {
+        if self.store_node_list_active {
+            let mut list_file = self.node_list_file();
+            if !list_file.exists() {
+                // eski sürümlerin adrese göre adlandırdığı dosya
+                list_file = helper::node_list_file(&self.storage_dir, &self.my_addr);
+            }
+            match fs::read_to_string(list_file) {
                 Ok(data) => {
                     let payload_result: serde_json::Result<Vec<String>> =
                         serde_json::from_str(&data);
-                    match payload_result {
-                        Ok(node_list) => node_list,
-                        Err(_) => Vec::new(),
-                    }
+                    payload_result.unwrap_or_default()
                 }
                 Err(_) => Vec::new(),
             }
@@ -613,10 +1380,26 @@ impl MessagePool {
     }
 }
 
+impl Drop for MessagePool {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum EventType {
     OnNodesSynced(String),
     OnNodeStatusChanged(String, NodeStatus),
     OnMessage(Message),
+    // gönderen adresi, stream id, offset, veri
+    OnStreamData(String, u128, u64, Vec<u8>),
+    // alıcı adresi, stream id, onaylanan byte, toplam byte
+    OnStreamProgress(String, u128, u64, u64),
+    OnStreamCompleted(String, u128),
+    OnStreamFailed(String, u128, String),
+    // node adresi, sebep; node listeden çıkartılır
+    OnIncompatiblePeer(String, String),
+    // node id, eski adres, yeni adres
+    OnNodeAddressChanged(String, String, String),
     OnWait(),
 }
@@ -0,0 +1,72 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use crate::helper;
+
+// mesaj id'si: | 48 bit node | 48 bit sayaç | 32 bit rastgele |
+// aynı node'dan art arda gönderilenler sayaçla, farklı node'lar node kısmıyla ayrılır
+pub struct MessageIds {
+    node_part: u128,
+    counter: AtomicU64,
+    rng_state: Mutex<u64>,
+}
+
+impl MessageIds {
+    pub fn new(node_key: &str, seed: u64) -> Self {
+        let digest = md5::compute(format!("{}:{}", node_key, seed));
+        let mut node_part = 0u128;
+        for byte in digest.0[..6].iter() {
+            node_part = (node_part << 8) | *byte as u128;
+        }
+        MessageIds {
+            node_part,
+            counter: AtomicU64::new(0),
+            rng_state: Mutex::new(seed ^ node_part as u64),
+        }
+    }
+
+    pub fn next(&self) -> u128 {
+        let counter = self.counter.fetch_add(1, Ordering::SeqCst) & 0xffff_ffff_ffff;
+        let random_part = helper::next_random(&mut self.rng_state.lock().unwrap()) & 0xffff_ffff;
+        (self.node_part << 80) | ((counter as u128) << 32) | random_part as u128
+    }
+}
+
+// son görülen mesaj id'leri, dolunca en eskisi unutulur
+pub struct DedupWindow {
+    seen: HashSet<u128>,
+    order: VecDeque<u128>,
+    capacity: usize,
+}
+
+impl DedupWindow {
+    pub fn new(capacity: usize) -> Self {
+        DedupWindow {
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Returns `false` if `msg_id` was already seen inside the window.
+    pub fn insert(&mut self, msg_id: u128) -> bool {
+        if self.capacity == 0 {
+            return true;
+        }
+        if !self.seen.insert(msg_id) {
+            return false;
+        }
+        self.order.push_back(msg_id);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use tracing::{error, trace};
+
+use crate::{structs::MessageKind, worker::PoolStats};
+
+#[derive(Default)]
+pub struct Metrics {
+    messages_sent: Mutex<BTreeMap<String, u64>>,
+    messages_received: Mutex<BTreeMap<String, u64>>,
+    ping_rtt_ms: Mutex<BTreeMap<String, f64>>,
+    bytes_out: AtomicU64,
+    bytes_in: AtomicU64,
+    sync_rounds: AtomicU64,
+    connect_failures: AtomicU64,
+    duplicates: AtomicU64,
+}
+
+impl Metrics {
+    pub fn record_sent(&self, kind: &MessageKind, byte_count: usize) {
+        *self
+            .messages_sent
+            .lock()
+            .unwrap()
+            .entry(kind.to_string())
+            .or_insert(0) += 1;
+        self.bytes_out
+            .fetch_add(byte_count as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_received(&self, kind: &MessageKind, byte_count: usize) {
+        *self
+            .messages_received
+            .lock()
+            .unwrap()
+            .entry(kind.to_string())
+            .or_insert(0) += 1;
+        self.bytes_in
+            .fetch_add(byte_count as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_ping_rtt(&self, node_addr: &str, rtt_ms: f64) {
+        self.ping_rtt_ms
+            .lock()
+            .unwrap()
+            .insert(node_addr.to_string(), rtt_ms);
+    }
+
+    pub fn forget_peer(&self, node_addr: &str) {
+        self.ping_rtt_ms.lock().unwrap().remove(node_addr);
+    }
+
+    pub fn record_sync_round(&self) {
+        self.sync_rounds.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_connect_failure(&self) {
+        self.connect_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_duplicate(&self) {
+        self.duplicates.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(
+        &self,
+        online_node_count: usize,
+        queue_depth: usize,
+        pool: PoolStats,
+    ) -> MetricsSnapshot {
+        MetricsSnapshot {
+            messages_sent: self.messages_sent.lock().unwrap().clone(),
+            messages_received: self.messages_received.lock().unwrap().clone(),
+            ping_rtt_ms: self.ping_rtt_ms.lock().unwrap().clone(),
+            bytes_out: self.bytes_out.load(Ordering::Relaxed),
+            bytes_in: self.bytes_in.load(Ordering::Relaxed),
+            sync_rounds: self.sync_rounds.load(Ordering::Relaxed),
+            connect_failures: self.connect_failures.load(Ordering::Relaxed),
+            duplicates: self.duplicates.load(Ordering::Relaxed),
+            online_node_count,
+            queue_depth,
+            pool,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    pub messages_sent: BTreeMap<String, u64>,
+    pub messages_received: BTreeMap<String, u64>,
+    pub ping_rtt_ms: BTreeMap<String, f64>,
+    pub bytes_out: u64,
+    pub bytes_in: u64,
+    pub sync_rounds: u64,
+    pub connect_failures: u64,
+    pub duplicates: u64,
+    pub online_node_count: usize,
+    pub queue_depth: usize,
+    pub pool: PoolStats,
+}
+
+impl MetricsSnapshot {
+    /// Renders the snapshot in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        write_family(
+            &mut out,
+            "litep2p_messages_sent_total",
+            "counter",
+            "Messages sent per kind.",
+        );
+        for (kind, count) in self.messages_sent.iter() {
+            _ = writeln!(
+                out,
+                "litep2p_messages_sent_total{{kind=\"{}\"}} {}",
+                kind, count
+            );
+        }
+        write_family(
+            &mut out,
+            "litep2p_messages_received_total",
+            "counter",
+            "Messages received per kind.",
+        );
+        for (kind, count) in self.messages_received.iter() {
+            _ = writeln!(
+                out,
+                "litep2p_messages_received_total{{kind=\"{}\"}} {}",
+                kind, count
+            );
+        }
+        write_family(
+            &mut out,
+            "litep2p_ping_rtt_milliseconds",
+            "gauge",
+            "Last ping round-trip time per peer.",
+        );
+        for (node_addr, rtt_ms) in self.ping_rtt_ms.iter() {
+            _ = writeln!(
+                out,
+                "litep2p_ping_rtt_milliseconds{{peer=\"{}\"}} {}",
+                escape_label(node_addr),
+                rtt_ms
+            );
+        }
+        write_single(
+            &mut out,
+            "litep2p_bytes_out_total",
+            "counter",
+            "Bytes written to peers.",
+            self.bytes_out,
+        );
+        write_single(
+            &mut out,
+            "litep2p_bytes_in_total",
+            "counter",
+            "Bytes read from peers.",
+            self.bytes_in,
+        );
+        write_single(
+            &mut out,
+            "litep2p_sync_rounds_total",
+            "counter",
+            "Node list sync rounds started.",
+            self.sync_rounds,
+        );
+        write_single(
+            &mut out,
+            "litep2p_connect_failures_total",
+            "counter",
+            "Failed outbound connections.",
+            self.connect_failures,
+        );
+        write_single(
+            &mut out,
+            "litep2p_duplicate_messages_total",
+            "counter",
+            "Messages dropped by the dedup window.",
+            self.duplicates,
+        );
+        write_single(
+            &mut out,
+            "litep2p_online_node_count",
+            "gauge",
+            "Nodes currently marked Online.",
+            self.online_node_count,
+        );
+        write_single(
+            &mut out,
+            "litep2p_queue_depth",
+            "gauge",
+            "Received messages waiting for on_event.",
+            self.queue_depth,
+        );
+        write_single(
+            &mut out,
+            "litep2p_worker_count",
+            "gauge",
+            "Connection worker threads.",
+            self.pool.size,
+        );
+        write_single(
+            &mut out,
+            "litep2p_worker_busy",
+            "gauge",
+            "Connection workers running a job.",
+            self.pool.busy,
+        );
+        write_single(
+            &mut out,
+            "litep2p_worker_queue_depth",
+            "gauge",
+            "Connections waiting for a worker.",
+            self.pool.queued,
+        );
+        write_single(
+            &mut out,
+            "litep2p_worker_panics_total",
+            "counter",
+            "Connection jobs that panicked.",
+            self.pool.panicked,
+        );
+        write_single(
+            &mut out,
+            "litep2p_worker_timeouts_total",
+            "counter",
+            "Connection jobs that timed out.",
+            self.pool.timed_out,
+        );
+        out
+    }
+}
+
+fn write_family(out: &mut String, name: &str, kind: &str, help: &str) {
+    _ = writeln!(out, "# HELP {} {}", name, help);
+    _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn write_single<T: std::fmt::Display>(
+    out: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    value: T,
+) {
+    write_family(out, name, kind, help);
+    _ = writeln!(out, "{} {}", name, value);
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// isteklerin tek tek işlendiği thread, isteğini göndermeyen bir bağlantı diğerlerini en fazla bu kadar bekletir
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+// serve ile başlatılan thread, node kapanırken stop ile durdurulur
+pub struct MetricsServer {
+    local_addr: SocketAddr,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    // running false olduktan sonra accept() üzerinde bekleyen thread bir bağlantı açılarak uyandırılır
+    pub fn stop(&mut self) {
+        let Some(thread) = self.thread.take() else {
+            return;
+        };
+        self.running.store(false, Ordering::SeqCst);
+        let mut wake_addr = self.local_addr;
+        if wake_addr.ip().is_unspecified() {
+            wake_addr.set_ip(match wake_addr.ip() {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            });
+        }
+        if TcpStream::connect_timeout(&wake_addr, REQUEST_TIMEOUT).is_ok() {
+            _ = thread.join();
+        }
+    }
+}
+
+// metrics_addr üzerinde GET /metrics isteklerine cevap veren basit http sunucusu
+pub fn serve<F>(
+    metrics_addr: String,
+    running: Arc<AtomicBool>,
+    snapshot: F,
+) -> Option<MetricsServer>
+where
+    F: Fn() -> MetricsSnapshot + Send + 'static,
+{
+    let listener = match TcpListener::bind(metrics_addr.clone()) {
+        Ok(listener) => listener,
+        Err(_) => {
+            error!(addr = %metrics_addr, "metrics endpoint could not be started");
+            return None;
+        }
+    };
+    let local_addr = listener.local_addr().ok()?;
+    let running_inner = running.clone();
+    let thread = thread::spawn(move || {
+        for stream in listener.incoming() {
+            if !running_inner.load(Ordering::SeqCst) {
+                trace!("metrics endpoint stopped");
+                return;
+            }
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT));
+            _ = stream.set_write_timeout(Some(REQUEST_TIMEOUT));
+            let mut read_buf = [0u8; 1024];
+            let n = match stream.read(&mut read_buf) {
+                Ok(n) => n,
+                Err(_) => continue,
+            };
+            let request = String::from_utf8_lossy(&read_buf[0..n]);
+            trace!(
+                request = request.lines().next().unwrap_or(""),
+                "metrics request"
+            );
+            let response = if request.starts_with("GET /metrics") {
+                let body = snapshot().to_prometheus();
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string()
+            };
+            _ = stream.write_all(response.as_bytes());
+        }
+    });
+    Some(MetricsServer {
+        local_addr,
+        running,
+        thread: Some(thread),
+    })
+}
@@ -0,0 +1,346 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{debug, trace};
+
+use crate::{
+    handle_connection::Inbound,
+    handshake::FEATURE_NAT,
+    helper,
+    message_id::MessageIds,
+    metrics::Metrics,
+    structs::{Message, MessageKind, NodeDetails, NodeStatus, Reachability},
+    transport::Transport,
+};
+
+// dışarıdan ulaşılabilirlik bu aralıkla tekrar ölçülür
+const REACHABILITY_CHECK_MILLIS: u128 = 30_000;
+// hiçbir peer cevap vermediyse ölçüm bu kadar sonra tekrar denenir
+const REACHABILITY_RETRY_MILLIS: u128 = 1_000;
+// ulaşılabilirlik sorulan en fazla peer
+const DIAL_BACK_PEERS: usize = 3;
+// bir isteğe göre denenen en fazla adres
+const MAX_DIAL_ADDRS: usize = 4;
+// Probe cevabı için beklenen süre
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+// DialBack'e cevap veren node adresleri bu süre içinde dener, cevap NAT_REPLY_TIMEOUT'a yetişir
+const DIAL_BACK_BUDGET: Duration = Duration::from_millis(2000);
+// cevap vermeden önce başka node'lara bağlanan DialBack ve PunchRequest için beklenen süre
+const NAT_REPLY_TIMEOUT: Duration = Duration::from_millis(3000);
+// aynı node için hole punching en sık bu aralıkla denenir
+const PUNCH_RETRY_MILLIS: u128 = 5_000;
+// özel ağdaki node'dan bu süre boyunca ping geliyorsa ona ulaşamasak da online sayılır
+pub const PRIVATE_PEER_TIMEOUT_MILLIS: u128 = 5_000;
+// NAT desteği kapalı node'un Nat mesajlarına döndüğü hata
+pub const ERR_NAT_DISABLED: u128 = 18;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum NatRequest {
+    // gönderenin adreslerine bağlanmayı dene, bağlanılabilenleri dön
+    DialBack { node_id: String, addrs: Vec<String> },
+    // dial-back ve hole punching bağlantısı, cevapta node id döner
+    Probe,
+    // koordinatöre: target ile aramızda delik aç
+    PunchRequest { target: String },
+    // koordinatörden hedefe: peer'in adreslerine hemen bağlan
+    Punch { peer: String, candidates: Vec<String> },
+}
+
+// Ok cevabının payload'ı
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct NatReply {
+    pub node_id: String,
+    // DialBack'te bağlanılabilen adresler, PunchRequest'te hedefin denenecek adresleri
+    pub addrs: Vec<String>,
+}
+
+// bu node'un NAT durumu, ping döngüsü ve gelen bağlantılar tarafından güncellenir
+pub struct NatState {
+    pub enabled: bool,
+    pub reachability: Reachability,
+    // peer adresi -> peer'in bizi gördüğü adres
+    observed: BTreeMap<String, String>,
+    next_check: u128,
+    // ölçüm arka planda sürüyor
+    checking: bool,
+    last_punch: HashMap<String, u128>,
+}
+
+impl NatState {
+    pub fn new(enabled: bool) -> Self {
+        NatState {
+            enabled,
+            reachability: Reachability::Unknown,
+            observed: BTreeMap::new(),
+            next_check: 0,
+            checking: false,
+            last_punch: HashMap::new(),
+        }
+    }
+
+    pub fn record_observed(&mut self, node_addr: &str, observed_addr: &str) {
+        if observed_addr.is_empty() {
+            return;
+        }
+        let previous = self
+            .observed
+            .insert(node_addr.to_string(), observed_addr.to_string());
+        if previous.is_none_or(|previous| !previous.eq(observed_addr)) {
+            trace!(peer = %node_addr, observed_addr = %observed_addr, "observed address reported");
+        }
+    }
+
+    pub fn forget_peer(&mut self, node_addr: &str) {
+        self.observed.remove(node_addr);
+        self.last_punch.remove(node_addr);
+    }
+
+    // en çok peer'in bildirdiği adres
+    pub fn public_addr(&self) -> Option<String> {
+        let mut counts: BTreeMap<&String, usize> = BTreeMap::new();
+        for observed_addr in self.observed.values() {
+            *counts.entry(observed_addr).or_insert(0) += 1;
+        }
+        counts
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .map(|(observed_addr, _)| observed_addr.clone())
+    }
+
+    // ölçüm zamanı geldiyse ve başka bir ölçüm sürmüyorsa true döner, ölçüm schedule_check ile biter
+    pub fn begin_check(&mut self, now_millis: u128) -> bool {
+        if !self.enabled || self.checking || now_millis < self.next_check {
+            return false;
+        }
+        self.checking = true;
+        true
+    }
+
+    pub fn schedule_check(&mut self, now_millis: u128, answered: bool) {
+        self.checking = false;
+        self.next_check = now_millis
+            + match answered {
+                true => REACHABILITY_CHECK_MILLIS,
+                false => REACHABILITY_RETRY_MILLIS,
+            };
+    }
+
+    pub fn is_punch_due(&mut self, node_addr: &str, now_millis: u128) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        match self.last_punch.get(node_addr) {
+            Some(last) if now_millis.saturating_sub(*last) < PUNCH_RETRY_MILLIS => false,
+            _ => {
+                self.last_punch.insert(node_addr.to_string(), now_millis);
+                true
+            }
+        }
+    }
+}
+
+pub fn request_from_bytes(data: &[u8]) -> Option<NatRequest> {
+    serde_json::from_slice(data).ok()
+}
+
+pub fn reply_to_bytes(reply: &NatReply) -> Vec<u8> {
+    serde_json::to_vec(reply).unwrap_or_default()
+}
+
+// Nat isteklerini gönderen taraf, uzun süren ölçüm ve denemeler için thread'lere kopyalanır
+#[derive(Clone)]
+pub struct NatClient {
+    pub my_addr: String,
+    pub node_id: String,
+    pub cluster_token: String,
+    pub transport: Arc<dyn Transport>,
+    pub ids: Arc<MessageIds>,
+    pub metrics: Arc<Metrics>,
+}
+
+impl NatClient {
+    fn call(
+        &self,
+        dial_addr: &str,
+        request: &NatRequest,
+        reply_timeout: Duration,
+    ) -> Option<NatReply> {
+        let msg_data = Message {
+            id: self.ids.next(),
+            sender: self.my_addr.to_string(),
+            kind: MessageKind::Nat,
+            payload: serde_json::to_vec(request).unwrap_or_default(),
+            cluster_token: self.cluster_token.to_string(),
+            payload_codec: String::new(),
+            handshake: None,
+            node_metadata: Vec::new(),
+        }
+        .to_byte_array();
+        self.metrics.record_sent(&MessageKind::Nat, msg_data.len());
+        let result = self
+            .transport
+            .request_with_timeout(dial_addr, &msg_data, reply_timeout);
+        match result.kind {
+            MessageKind::Ok => serde_json::from_slice(&result.payload).ok(),
+            _ => None,
+        }
+    }
+
+    // adreste node_id'li node cevap veriyor mu
+    pub fn probe(&self, dial_addr: &str, node_id: &str) -> bool {
+        self.call(dial_addr, &NatRequest::Probe, PROBE_TIMEOUT)
+            .is_some_and(|reply| reply.node_id.eq(node_id))
+    }
+
+    // bildirdiğimiz adreslere bağlanılabiliyor mu, NAT destekleyen online peer'lere sorulur;
+    // hiçbir peer cevap vermezse None
+    pub fn check_reachability(
+        &self,
+        node_list: &[NodeDetails],
+        addrs: &[String],
+    ) -> Option<Reachability> {
+        let addrs: Vec<String> = addrs
+            .iter()
+            .filter(|addr| !helper::is_unspecified_addr(addr))
+            .take(MAX_DIAL_ADDRS)
+            .cloned()
+            .collect();
+        let request = NatRequest::DialBack {
+            node_id: self.node_id.to_string(),
+            addrs,
+        };
+        let mut reachability = None;
+        let peers = node_list.iter().filter(|n_info| {
+            !n_info.addr.eq(&self.my_addr)
+                && n_info.status == NodeStatus::Online
+                && n_info.features.iter().any(|f| f.eq(FEATURE_NAT))
+        });
+        for n_info in peers.take(DIAL_BACK_PEERS) {
+            let Some(reply) = self.call(n_info.dial_addr(), &request, NAT_REPLY_TIMEOUT) else {
+                continue;
+            };
+            if !reply.addrs.is_empty() {
+                debug!(peer = %n_info.addr, addrs = ?reply.addrs, "reachable from peer");
+                return Some(Reachability::Public);
+            }
+            reachability = Some(Reachability::Private);
+        }
+        reachability
+    }
+
+    // target'a doğrudan ulaşan bir node üzerinden hole punching ister,
+    // target'ın denenmesi gereken adresleri döner
+    pub fn request_punch(&self, node_list: &[NodeDetails], target: &str) -> Vec<String> {
+        let coordinators = node_list.iter().filter(|n_info| {
+            !n_info.addr.eq(&self.my_addr)
+                && !n_info.addr.eq(target)
+                && n_info.status == NodeStatus::Online
+                && n_info.features.iter().any(|f| f.eq(FEATURE_NAT))
+                && n_info.routes.get(target).is_some_and(|hops| *hops == 1)
+        });
+        for coordinator in coordinators {
+            let request = NatRequest::PunchRequest {
+                target: target.to_string(),
+            };
+            if let Some(reply) = self.call(coordinator.dial_addr(), &request, NAT_REPLY_TIMEOUT) {
+                debug!(peer = %target, coordinator = %coordinator.addr, "hole punching coordinated");
+                return reply.addrs;
+            }
+        }
+        Vec::new()
+    }
+}
+
+// handle_connection'da gelen Nat isteğini cevaplar, None ise istek reddedilir
+pub fn handle(inbound: &Inbound, sender: &str, request: NatRequest) -> Option<NatReply> {
+    let node_id = inbound.handshake.lock().unwrap().node_id.clone();
+    if request != NatRequest::Probe && !inbound.nat.lock().unwrap().enabled {
+        return None;
+    }
+    let client = NatClient {
+        my_addr: inbound.my_addr.clone(),
+        node_id: node_id.clone(),
+        cluster_token: inbound.admission.cluster_token.clone(),
+        transport: inbound.transport.clone(),
+        ids: inbound.ids.clone(),
+        metrics: inbound.metrics.clone(),
+    };
+    let addrs = match request {
+        NatRequest::Probe => Vec::new(),
+        NatRequest::DialBack {
+            node_id: peer_id,
+            addrs,
+        } => {
+            let started = Instant::now();
+            addrs
+                .into_iter()
+                .take(MAX_DIAL_ADDRS)
+                .take_while(|_| started.elapsed() < DIAL_BACK_BUDGET)
+                .filter(|addr| client.probe(addr, &peer_id))
+                .collect()
+        }
+        NatRequest::PunchRequest { target } => {
+            let node_list = inbound.node_list.lock().unwrap().clone();
+            let target_info = node_list
+                .iter()
+                .find(|n_info| n_info.addr.eq(&target) && n_info.status == NodeStatus::Online)?;
+            let sender_info = node_list.iter().find(|n_info| n_info.addr.eq(sender))?;
+            let punch = NatRequest::Punch {
+                peer: sender.to_string(),
+                candidates: punch_candidates(sender_info),
+            };
+            client.call(target_info.dial_addr(), &punch, NAT_REPLY_TIMEOUT)?;
+            punch_candidates(target_info)
+        }
+        NatRequest::Punch { peer, candidates } => {
+            // dinlenen porttan peer'in adreslerine paket gönderilir; karşı tarafın NAT'ı
+            // bunları reddeder, ama bizim NAT'ımızda peer'in gelecek paketleri için eşleme açılır
+            let candidates: Vec<String> = candidates.into_iter().take(MAX_DIAL_ADDRS).collect();
+            client.transport.punch(&candidates);
+            let peer_id = inbound
+                .node_list
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|n_info| n_info.addr.eq(&peer))
+                .map(|n_info| n_info.node_id.clone())
+                .unwrap_or_default();
+            // TCP için de aynı porttan bağlantı denenir, koordinatör bunu beklemez
+            let probe_client = client.clone();
+            let probe = move || {
+                for candidate in candidates.iter() {
+                    if probe_client.probe(candidate, &peer_id) {
+                        trace!(peer = %peer, candidate = %candidate, "punched peer answered");
+                    }
+                }
+            };
+            match client.transport.is_inline() {
+                true => probe(),
+                false => {
+                    thread::spawn(probe);
+                }
+            }
+            Vec::new()
+        }
+    };
+    Some(NatReply { node_id, addrs })
+}
+
+// NAT arkasındaki node'un önce bizim gördüğümüz adresi denenir
+fn punch_candidates(n_info: &NodeDetails) -> Vec<String> {
+    let mut candidates = Vec::new();
+    if !n_info.observed_addr.is_empty() {
+        candidates.push(n_info.observed_addr.clone());
+    }
+    for candidate in n_info.dial_candidates() {
+        if !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    }
+    candidates.truncate(MAX_DIAL_ADDRS);
+    candidates
+}
@@ -0,0 +1,537 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use tracing::{debug, trace};
+
+use crate::{
+    admission::ERR_REJECTED,
+    clock::Clock,
+    events::EventQueue,
+    handshake::{Handshake, ERR_INCOMPATIBLE},
+    helper,
+    message_id::MessageIds,
+    metrics::Metrics,
+    nat::{self, NatClient, NatState},
+    relay,
+    structs::{
+        Message, MessageKind, NodeDetails, NodeDetailsToHelper, NodeStatus, Reachability,
+        StateType,
+    },
+    transport::{self, Transport},
+    EventType,
+};
+
+// offline node'un tekrar denenmeden önce beklediği süre
+const OFFLINE_RETEST_MILLIS: u128 = 10_000;
+
+// ping ve node listesi senkron döngüsünün tek bir turu, zaman dışarıdan verilir
+// böylece thread_ping gerçek saatle, simülasyon ise sanal saatle çalıştırabilir
+pub struct PingLoop {
+    pub my_node_addr: String,
+    pub node_list: Arc<Mutex<Vec<NodeDetails>>>,
+    pub node_status_change: Arc<Mutex<Vec<(String, NodeStatus)>>>,
+    pub node_hash: Arc<Mutex<String>>,
+    pub node_hash_updated: Arc<Mutex<bool>>,
+    pub node_list_synced: Arc<Mutex<String>>,
+    pub ping_time: u128,
+    pub cluster_token: String,
+    pub metrics: Arc<Metrics>,
+    pub transport: Arc<dyn Transport>,
+    pub clock: Arc<dyn Clock>,
+    pub ids: Arc<MessageIds>,
+    pub compression_threshold: usize,
+    pub handshake: Arc<Mutex<Handshake>>,
+    pub events: Arc<Mutex<EventQueue>>,
+    pub nat: Arc<Mutex<NatState>>,
+    all_node_list_changed: bool,
+    ping_time_diff: u128,
+}
+
+impl PingLoop {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        my_node_addr: String,
+        node_list: Arc<Mutex<Vec<NodeDetails>>>,
+        node_status_change: Arc<Mutex<Vec<(String, NodeStatus)>>>,
+        node_hash: Arc<Mutex<String>>,
+        node_hash_updated: Arc<Mutex<bool>>,
+        node_list_synced: Arc<Mutex<String>>,
+        ping_time: u128,
+        cluster_token: String,
+        metrics: Arc<Metrics>,
+        transport: Arc<dyn Transport>,
+        clock: Arc<dyn Clock>,
+        ids: Arc<MessageIds>,
+        compression_threshold: usize,
+        handshake: Arc<Mutex<Handshake>>,
+        events: Arc<Mutex<EventQueue>>,
+        nat: Arc<Mutex<NatState>>,
+    ) -> Self {
+        PingLoop {
+            my_node_addr,
+            node_list,
+            node_status_change,
+            node_hash,
+            node_hash_updated,
+            node_list_synced,
+            ping_time,
+            cluster_token,
+            metrics,
+            transport,
+            clock,
+            ids,
+            compression_threshold,
+            handshake,
+            events,
+            nat,
+            all_node_list_changed: true,
+            ping_time_diff: 0,
+        }
+    }
+
+    pub fn round(&mut self, now_millis: u128) {
+        let my_node_addr = &self.my_node_addr;
+        let node_list = &self.node_list;
+        let node_status_change = &self.node_status_change;
+        let current_node_hash = &self.node_hash;
+        let my_node_hash = &self.node_hash;
+        let node_hash_updated = &self.node_hash_updated;
+        let node_list_synced = &self.node_list_synced;
+        let hard_config_ping_time = self.ping_time;
+        let cluster_token = &self.cluster_token;
+        let metrics = &self.metrics;
+        let transport = &self.transport;
+        let clock = &self.clock;
+        let ids = &self.ids;
+        let local_handshake = self.handshake.lock().unwrap().clone();
+        let nat_client = NatClient {
+            my_addr: self.my_node_addr.clone(),
+            node_id: local_handshake.node_id.clone(),
+            cluster_token: self.cluster_token.clone(),
+            transport: self.transport.clone(),
+            ids: self.ids.clone(),
+            metrics: self.metrics.clone(),
+        };
+        let mut all_node_list_changed = self.all_node_list_changed;
+        let ping_time_diff = self.ping_time_diff;
+
+        let mut next_ping_time_diff = hard_config_ping_time;
+        let mut update_node_hash_value = false;
+        let mut update_node_status = Vec::new();
+        let mut update_time = Vec::new();
+        let mut update_handshake = Vec::new();
+        let mut update_reach: Vec<(String, String)> = Vec::new();
+        let mut update_quality = Vec::new();
+        let mut move_to_offline_node = usize::MAX;
+        let mut rejected_nodes = Vec::new();
+        let sending_data = Message {
+            id: ids.next(),
+            sender: my_node_addr.clone(),
+            kind: MessageKind::State,
+            payload: StateType::Ping(current_node_hash.lock().unwrap().clone()).to_byte_array(),
+            cluster_token: cluster_token.clone(),
+            payload_codec: String::new(),
+            handshake: Some(Box::new(local_handshake.clone())),
+            node_metadata: Vec::new(),
+        }
+        .to_byte_array();
+        let tmp_node_list = node_list.lock().unwrap().clone();
+        for (n_index, n_info) in tmp_node_list.iter().enumerate() {
+            if move_to_offline_node == usize::MAX && !my_node_addr.eq(&n_info.addr.clone()) {
+                let send_ping_to_node = match n_info.status {
+                    NodeStatus::Online => {
+                        let time_diff = now_millis.saturating_sub(n_info.last_access_time);
+                        time_diff > ping_time_diff
+                    }
+                    NodeStatus::Offline => {
+                        let time_diff = now_millis.saturating_sub(n_info.offline_since);
+                        if time_diff > OFFLINE_RETEST_MILLIS {
+                            // TO-DO
+                            // eğer kontrol edildiği zaman yine offline ise,
+                            // bu listeden çıkartıp offline listesine al
+                            debug!(peer = %n_info.addr, "testing offline");
+                            next_ping_time_diff = 0;
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                    NodeStatus::Unknown => {
+                        next_ping_time_diff = 0;
+                        debug!(peer = %n_info.addr, "testing unknown");
+                        true
+                    }
+                };
+
+                if send_ping_to_node {
+                    metrics.record_sent(&MessageKind::State, sending_data.len());
+                    let ping_started = clock.now_micros();
+                    let mut result = transport.request_datagram(n_info.dial_addr(), &sending_data);
+                    if transport::is_transport_error(&result) {
+                        // node'un bildirdiği diğer adresler sırayla denenir
+                        for candidate in n_info.dial_candidates().iter().skip(1) {
+                            let candidate_result = transport.request_datagram(candidate, &sending_data);
+                            if candidate_result.kind == MessageKind::Ok {
+                                debug!(peer = %n_info.addr, reach_addr = %candidate, "reached through another address");
+                                update_reach.push((n_info.addr.clone(), candidate.clone()));
+                                result = candidate_result;
+                                break;
+                            }
+                        }
+                    }
+                    if transport::is_transport_error(&result)
+                        && self.nat.lock().unwrap().is_punch_due(&n_info.addr, now_millis)
+                    {
+                        // node'a ulaşabilen bir node aracılığıyla iki taraf da birbirine bağlanır
+                        let candidates = nat_client.request_punch(&tmp_node_list, &n_info.addr);
+                        transport.punch(&candidates);
+                        for candidate in candidates {
+                            let candidate_result = transport.request_datagram(&candidate, &sending_data);
+                            if candidate_result.kind == MessageKind::Ok {
+                                debug!(peer = %n_info.addr, reach_addr = %candidate, "reached after hole punching");
+                                update_reach.push((n_info.addr.clone(), candidate.clone()));
+                                result = candidate_result;
+                                break;
+                            }
+                        }
+                    }
+                    let ping_rtt_us = clock.now_micros().saturating_sub(ping_started) as u64;
+                    if result.kind == MessageKind::Ok {
+                        metrics.record_ping_rtt(&n_info.addr, ping_rtt_us as f64 / 1000.0);
+                        update_quality.push((n_info.addr.clone(), Some(ping_rtt_us)));
+                    } else {
+                        if result.id == transport::ERR_CONNECT {
+                            metrics.record_connect_failure();
+                        }
+                        update_quality.push((n_info.addr.clone(), None));
+                    }
+                    let incompatibility = match &result.handshake {
+                        Some(peer_handshake) => local_handshake.incompatibility(peer_handshake),
+                        None if result.id == ERR_INCOMPATIBLE => Some("rejected by peer".to_string()),
+                        None => None,
+                    };
+                    if result
+                        .handshake
+                        .as_ref()
+                        .is_some_and(|hs| hs.node_id.eq(&local_handshake.node_id))
+                    {
+                        // kendi diğer adresimiz, örn. yeniden başlamadan önceki adres
+                        debug!(peer = %n_info.addr, "own address removed");
+                        rejected_nodes.push(n_info.addr.clone());
+                        update_node_hash_value = true;
+                    } else if let Some(reason) = incompatibility {
+                        // sürümü uyumsuz node listeden çıkartılıyor, sebebi olay olarak bildirilir
+                        debug!(peer = %n_info.addr, reason = %reason, "incompatible peer");
+                        self.events
+                            .lock()
+                            .unwrap()
+                            .report_incompatible(&n_info.addr, reason);
+                        rejected_nodes.push(n_info.addr.clone());
+                        update_node_hash_value = true;
+                    } else if result.id == ERR_REJECTED
+                        || (result.kind == MessageKind::Ok
+                            && !result.cluster_token.eq(cluster_token))
+                    {
+                        // farklı cluster'a ait veya izin verilmeyen node
+                        // listeden çıkartılıyor, diğer node'lara yayılmıyor
+                        debug!(peer = %n_info.addr, "rejected by peer");
+                        rejected_nodes.push(n_info.addr.clone());
+                        update_node_hash_value = true;
+                    } else if result.kind == MessageKind::Ok {
+                        update_time.push((n_info.addr.clone(), now_millis));
+                        if let Some(peer_handshake) = &result.handshake {
+                            self.nat
+                                .lock()
+                                .unwrap()
+                                .record_observed(&n_info.addr, &peer_handshake.observed_addr);
+                            update_handshake.push((n_info.addr.clone(), peer_handshake.clone()));
+                        }
+                        if n_info.status != NodeStatus::Online {
+                            update_node_status.push((n_info.addr.clone(), NodeStatus::Online));
+                            node_status_change
+                                .lock()
+                                .unwrap()
+                                .push((n_info.addr.clone(), NodeStatus::Online));
+                            update_node_hash_value = true;
+                        }
+                    } else if n_info.reachability == Reachability::Private
+                        && n_info.status == NodeStatus::Online
+                        && now_millis.saturating_sub(n_info.last_access_time)
+                            <= nat::PRIVATE_PEER_TIMEOUT_MILLIS
+                    {
+                        // NAT arkasındaki node bize ping atmaya devam ediyor, ona bağlanamamamız
+                        // offline olduğunu göstermez
+                        trace!(peer = %n_info.addr, "private peer not dialable");
+                    } else {
+                        if result.id == transport::ERR_CONNECT {
+                            update_node_hash_value = true;
+                            match n_info.status {
+                                NodeStatus::Online => {
+                                    let offline_node_addr = n_info.addr.clone();
+                                    // println!("offline_node_addr: {}", offline_node_addr);
+
+                                    let state_msg_vec = Message {
+                                        id: ids.next(),
+                                        sender: my_node_addr.clone(),
+                                        kind: MessageKind::State,
+                                        payload: StateType::ControlNodeStatus(
+                                            offline_node_addr.clone(),
+                                        )
+                                        .to_byte_array(),
+                                        cluster_token: cluster_token.clone(),
+                                        payload_codec: String::new(),
+                                        handshake: None,
+                                        node_metadata: Vec::new(),
+                                    }
+                                    .to_byte_array();
+                                    // omergoksoy
+                                    // burada online olan bir nodu'un offline olduğunu öğrendik
+                                    // bu durumu diğer nodelara state olarak iletiyoruz
+                                    for other_info in tmp_node_list.iter() {
+                                        if !other_info.addr.eq(&offline_node_addr.clone())
+                                            && !other_info.addr.eq(&my_node_addr.clone())
+                                        {
+                                            // println!("node'u ilet => {}", helper::get_sys_time_in_nano() );
+                                            metrics.record_sent(
+                                                &MessageKind::State,
+                                                state_msg_vec.len(),
+                                            );
+                                            transport::request_datagram_async(
+                                                transport.clone(),
+                                                other_info.dial_addr().to_string(),
+                                                state_msg_vec.clone(),
+                                                metrics.clone(),
+                                            );
+                                        }
+                                    }
+                                    update_node_status
+                                        .push((offline_node_addr.clone(), NodeStatus::Offline));
+                                    node_status_change
+                                        .lock()
+                                        .unwrap()
+                                        .push((offline_node_addr.clone(), NodeStatus::Offline));
+                                }
+                                NodeStatus::Offline => {
+                                    move_to_offline_node = n_index;
+                                }
+                                NodeStatus::Unknown => {
+                                    update_node_status
+                                        .push((n_info.addr.clone(), NodeStatus::Offline));
+                                    node_status_change
+                                        .lock()
+                                        .unwrap()
+                                        .push((n_info.addr.clone(), NodeStatus::Unknown));
+                                }
+                            }
+                        } else {
+                            if result.id != transport::ERR_READ {
+                                debug!(peer = %n_info.addr, code = result.id, "ping failed");
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if move_to_offline_node != usize::MAX {
+            let removed_node = node_list.lock().unwrap().remove(move_to_offline_node);
+            metrics.forget_peer(&removed_node.addr);
+            self.nat.lock().unwrap().forget_peer(&removed_node.addr);
+            all_node_list_changed = true;
+        } else {
+            for (n_addr, n_rtt) in update_quality.iter() {
+                for n_info in node_list.lock().unwrap().iter_mut() {
+                    if n_info.addr.eq(n_addr) {
+                        match n_rtt {
+                            Some(rtt_us) => n_info.link_quality.record_success(*rtt_us),
+                            None => n_info.link_quality.record_failure(),
+                        }
+                    }
+                }
+            }
+
+            for (n_addr, reach_addr) in update_reach.iter() {
+                for n_info in node_list.lock().unwrap().iter_mut() {
+                    if n_info.addr.eq(n_addr) {
+                        let reach_addr = match reach_addr.eq(n_addr) {
+                            true => String::new(),
+                            false => reach_addr.clone(),
+                        };
+                        if !n_info.reach_addr.eq(&reach_addr) {
+                            // eski adrese gönderilen node listesi ulaşmamış olabilir
+                            n_info.synced_time = 0;
+                        }
+                        n_info.reach_addr = reach_addr;
+                    }
+                }
+            }
+
+            for (n_addr, peer_handshake) in update_handshake.iter() {
+                for n_info in node_list.lock().unwrap().iter_mut() {
+                    if n_info.addr.eq(n_addr) {
+                        peer_handshake.apply_to(n_info);
+                    }
+                }
+            }
+
+            for (n_addr, _) in update_handshake.iter() {
+                if let Some((node_id, old_addr)) = node_list.merge_moved_node(n_addr) {
+                    debug!(node_id = %node_id, old_addr = %old_addr, new_addr = %n_addr, "node address changed");
+                    metrics.forget_peer(&old_addr);
+                    self.nat.lock().unwrap().forget_peer(&old_addr);
+                    self.events
+                        .lock()
+                        .unwrap()
+                        .push(EventType::OnNodeAddressChanged(
+                            node_id,
+                            old_addr,
+                            n_addr.clone(),
+                        ));
+                    update_node_hash_value = true;
+                }
+            }
+
+            for (n_addr, n_time) in update_time.iter() {
+                for n_info in node_list.lock().unwrap().iter_mut() {
+                    if n_info.addr.eq(n_addr) {
+                        n_info.last_access_time = *n_time;
+                    }
+                }
+            }
+
+            for (o_node, n_status) in update_node_status.iter() {
+                for n_info in node_list.lock().unwrap().iter_mut() {
+                    if n_info.addr.eq(o_node) {
+                        if *n_status == NodeStatus::Offline {
+                            n_info.offline_since = now_millis;
+                        }
+                        n_info.status = n_status.clone();
+                    }
+                }
+            }
+
+            // asenkron olduğu için aynı işlemi tekrar tekrar yapıyor
+            let mut update_sync_time = Vec::new();
+            let tmp_node_list = node_list.lock().unwrap().clone();
+            for n_info in tmp_node_list.iter() {
+                if !my_node_addr.eq(&n_info.addr.clone())
+                    && !my_node_hash.lock().unwrap().eq(&n_info.node_hash)
+                    && n_info.status == NodeStatus::Online
+                {
+                    let time_diff = now_millis.saturating_sub(n_info.synced_time);
+                    if time_diff > 100 {
+                        update_sync_time.push(n_info.addr.clone());
+                        trace!(peer = %n_info.addr, time_diff, "sync with");
+
+                        metrics.record_sync_round();
+                        let state_msg = Message {
+                            id: ids.next(),
+                            sender: my_node_addr.clone(),
+                            kind: MessageKind::State,
+                            payload: StateType::NodeList(node_list.to_node_list()).to_byte_array(),
+                            cluster_token: cluster_token.clone(),
+                            payload_codec: String::new(),
+                            handshake: None,
+                            node_metadata: node_list.to_node_metadata(),
+                        };
+                        node_list.send_state_to_all(
+                            &state_msg,
+                            self.compression_threshold,
+                            transport.as_ref(),
+                            metrics,
+                        );
+                    }
+                }
+            }
+
+            // boş liste tüm node'ları işaretler, o zaman fark süren node'lara hiç tekrar gönderilmez
+            if !update_sync_time.is_empty() {
+                node_list
+                    .clone()
+                    .set_sync_time(update_sync_time, now_millis);
+            }
+
+            if helper::control_nodes_hash(node_list.lock().unwrap().clone()) {
+                if all_node_list_changed {
+                    all_node_list_changed = false;
+                    let node_hash_cloned = my_node_hash.lock().unwrap().clone();
+                    let current_list_hash = node_list_synced.lock().unwrap().clone();
+                    if !current_list_hash.eq(&node_hash_cloned.clone()) {
+                        *node_list_synced.lock().unwrap() = node_hash_cloned.clone();
+                        *node_hash_updated.lock().unwrap() = true;
+                    }
+                }
+            } else {
+                all_node_list_changed = true;
+            }
+        }
+
+        if !rejected_nodes.is_empty() {
+            node_list
+                .lock()
+                .unwrap()
+                .retain(|n_info| !rejected_nodes.contains(&n_info.addr));
+            for rejected_addr in rejected_nodes.iter() {
+                metrics.forget_peer(rejected_addr);
+                self.nat.lock().unwrap().forget_peer(rejected_addr);
+            }
+            all_node_list_changed = true;
+        }
+
+        if update_node_hash_value {
+            let new_node_list_hash = node_list.calculate_hash();
+            if !current_node_hash
+                .lock()
+                .unwrap()
+                .clone()
+                .eq(&new_node_list_hash)
+            {
+                all_node_list_changed = true;
+            }
+            *current_node_hash.lock().unwrap() = new_node_list_hash.clone();
+            for n_info in node_list.lock().unwrap().iter_mut() {
+                if my_node_addr.eq(&n_info.addr.clone())
+                    && !n_info.node_hash.eq(&new_node_list_hash.clone())
+                {
+                    all_node_list_changed = true;
+                    n_info.node_hash = new_node_list_hash.clone();
+                }
+            }
+        }
+
+        if self.nat.lock().unwrap().begin_check(now_millis) {
+            // peer'ler adreslerimizi denerken cevap saniyeler sürebilir, ping'ler beklemesin
+            let tmp_node_list = node_list.lock().unwrap().clone();
+            let addrs = local_handshake.addrs.clone();
+            let nat = self.nat.clone();
+            let handshake = self.handshake.clone();
+            let check = move || {
+                let checked = nat_client.check_reachability(&tmp_node_list, &addrs);
+                let mut nat_state = nat.lock().unwrap();
+                nat_state.schedule_check(now_millis, checked.is_some());
+                if let Some(reachability) = checked {
+                    if nat_state.reachability != reachability {
+                        debug!(reachability = %reachability, "reachability changed");
+                    }
+                    nat_state.reachability = reachability.clone();
+                    handshake.lock().unwrap().reachability = reachability;
+                }
+            };
+            match transport.is_inline() {
+                true => check(),
+                false => {
+                    thread::spawn(check);
+                }
+            }
+        }
+
+        // bir sonraki ping'lerle komşulara bildirilir
+        let routes = relay::local_routes(&node_list.lock().unwrap(), my_node_addr);
+        self.handshake.lock().unwrap().routes = routes;
+
+        self.all_node_list_changed = all_node_list_changed;
+        self.ping_time_diff = next_ping_time_diff;
+    }
+}
@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{
+    message_id::MessageIds,
+    structs::{Message, MessageKind, NodeDetails, NodeStatus},
+};
+
+// bir mesajın hedefe varana kadar geçebileceği en fazla node sayısı
+pub const MAX_RELAY_HOPS: u8 = 4;
+// hop limiti dolan veya rotası olmayan relay mesajına dönülen hata
+pub const ERR_NO_ROUTE: u128 = 17;
+
+// Relay mesajının payload'ı, inner hedefe vardığında doğrudan gelmiş gibi işlenir
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayEnvelope {
+    pub receiver: String,
+    // bu node'dan sonra kalan adım sayısı
+    pub hops_left: u8,
+    pub inner: Message,
+}
+
+// envelope'un JSON başlığı, inner mesaj başlıktan sonra kendi baytlarıyla eklenir
+#[derive(Serialize, Deserialize)]
+struct RelayHeader {
+    receiver: String,
+    hops_left: u8,
+}
+
+// JSON başlık, '\n' ve inner mesajın kısa biçimi; inner tekrar sayı dizisine çevrilmez
+pub fn envelope_to_bytes(envelope: &RelayEnvelope) -> Vec<u8> {
+    let header = RelayHeader {
+        receiver: envelope.receiver.clone(),
+        hops_left: envelope.hops_left,
+    };
+    let mut envelope_data = serde_json::to_vec(&header).unwrap_or_default();
+    envelope_data.push(b'\n');
+    envelope_data.extend_from_slice(&envelope.inner.to_compact_byte_array());
+    envelope_data
+}
+
+pub fn envelope_from_bytes(envelope_data: &[u8]) -> Option<RelayEnvelope> {
+    let split_at = envelope_data.iter().position(|byte| *byte == b'\n')?;
+    let header: RelayHeader = serde_json::from_slice(&envelope_data[..split_at]).ok()?;
+    Some(RelayEnvelope {
+        receiver: header.receiver,
+        hops_left: header.hops_left,
+        inner: serde_json::from_slice(&envelope_data[split_at + 1..]).ok()?,
+    })
+}
+
+pub fn wrap(
+    envelope: &RelayEnvelope,
+    my_addr: &str,
+    cluster_token: &str,
+    ids: &MessageIds,
+) -> Message {
+    Message {
+        id: ids.next(),
+        sender: my_addr.to_string(),
+        kind: MessageKind::Relay,
+        payload: envelope_to_bytes(envelope),
+        cluster_token: cluster_token.to_string(),
+        payload_codec: String::new(),
+        handshake: None,
+        node_metadata: Vec::new(),
+    }
+}
+
+// handshake ile komşulara bildirilen rotalar: doğrudan ulaşılan node'lar 1,
+// komşular üzerinden ulaşılanlar komşunun bildirdiği adım + 1
+pub fn local_routes(node_list: &[NodeDetails], my_addr: &str) -> BTreeMap<String, u8> {
+    let mut routes = BTreeMap::new();
+    for n_info in node_list.iter() {
+        if n_info.addr.eq(my_addr) || n_info.status != NodeStatus::Online {
+            continue;
+        }
+        routes.insert(n_info.addr.clone(), 1);
+        for (target, hops) in n_info.routes.iter() {
+            if target.eq(my_addr) || *hops >= MAX_RELAY_HOPS {
+                continue;
+            }
+            let entry = routes.entry(target.clone()).or_insert(u8::MAX);
+            *entry = (*entry).min(hops + 1);
+        }
+    }
+    routes
+}
+
+// receiver'a doğrudan ulaşılıyorsa kendisi, değilse en az adımlı ve en sağlıklı
+// online komşu ile toplam adım sayısı döner, mesajın geldiği node tekrar seçilmez
+pub fn next_hop(
+    node_list: &[NodeDetails],
+    my_addr: &str,
+    receiver: &str,
+    came_from: &str,
+) -> Option<(String, u8)> {
+    if node_list
+        .iter()
+        .any(|n_info| n_info.addr.eq(receiver) && n_info.status == NodeStatus::Online)
+    {
+        return Some((receiver.to_string(), 1));
+    }
+    node_list
+        .iter()
+        .filter(|n_info| {
+            n_info.status == NodeStatus::Online
+                && !n_info.addr.eq(my_addr)
+                && !n_info.addr.eq(receiver)
+                && !n_info.addr.eq(came_from)
+        })
+        .filter_map(|n_info| {
+            let hops = *n_info.routes.get(receiver)?;
+            Some((hops, n_info.link_quality.score(), n_info.addr.clone()))
+        })
+        .filter(|(hops, _, _)| *hops < MAX_RELAY_HOPS)
+        .min()
+        .map(|(hops, _, relay_addr)| (relay_addr, hops + 1))
+}
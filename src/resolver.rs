@@ -0,0 +1,282 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, ErrorKind},
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+use tracing::{debug, trace};
+
+// SRV kaydı ile çözülecek bootstrap girdisi, ör. srv:_litep2p._tcp.example.com
+pub const SRV_PREFIX: &str = "srv:";
+
+const DNS_PORT: u16 = 53;
+const DNS_TIMEOUT: Duration = Duration::from_secs(2);
+const DNS_TYPE_SRV: u16 = 33;
+const DNS_CLASS_IN: u16 = 1;
+
+/// Turns a bootstrap entry into the socket addresses it currently points to.
+///
+/// An entry is either `host:port`, where every A/AAAA record becomes an
+/// address, or `srv:_service._proto.domain`, where every SRV target is
+/// resolved with its port. Entries that are already `ip:port` never reach
+/// the resolver.
+pub trait Resolver: Send + Sync {
+    fn resolve(&self, name: &str) -> io::Result<Vec<String>>;
+}
+
+/// Resolves through the operating system, and for SRV entries through the
+/// first nameserver in `/etc/resolv.conf`.
+#[derive(Debug, Clone, Default)]
+pub struct SystemResolver {
+    nameserver: Option<String>,
+}
+
+impl SystemResolver {
+    pub fn new() -> Self {
+        SystemResolver { nameserver: None }
+    }
+
+    /// Sends SRV queries to `nameserver` (`ip:port`) instead of the one in
+    /// `/etc/resolv.conf`.
+    pub fn with_nameserver(nameserver: &str) -> Self {
+        SystemResolver {
+            nameserver: Some(nameserver.to_string()),
+        }
+    }
+
+    fn nameserver(&self) -> io::Result<String> {
+        if let Some(nameserver) = &self.nameserver {
+            return Ok(nameserver.clone());
+        }
+        let resolv_conf = fs::read_to_string("/etc/resolv.conf")?;
+        for line in resolv_conf.lines() {
+            let mut parts = line.split_whitespace();
+            if parts.next() == Some("nameserver") {
+                if let Some(ip) = parts.next().and_then(|ip| ip.parse().ok()) {
+                    return Ok(SocketAddr::new(ip, DNS_PORT).to_string());
+                }
+            }
+        }
+        Err(io::Error::new(ErrorKind::NotFound, "no nameserver"))
+    }
+}
+
+impl Resolver for SystemResolver {
+    fn resolve(&self, name: &str) -> io::Result<Vec<String>> {
+        let Some(srv_name) = name.strip_prefix(SRV_PREFIX) else {
+            let addr_list = name.to_socket_addrs()?;
+            return Ok(addr_list.map(|addr| addr.to_string()).collect());
+        };
+        let mut addr_list = Vec::new();
+        for (target, port) in query_srv(&self.nameserver()?, srv_name)? {
+            // hedeflerden biri çözülemezse diğerleri yine kullanılır
+            if let Ok(target_addrs) = (target.as_str(), port).to_socket_addrs() {
+                addr_list.extend(target_addrs.map(|addr| addr.to_string()));
+            }
+        }
+        Ok(addr_list)
+    }
+}
+
+/// Fixed answers for tests and the simulator. Names without a record fail
+/// with `NotFound`, the same as an unknown host.
+#[derive(Debug, Default)]
+pub struct StubResolver {
+    records: Mutex<HashMap<String, Vec<String>>>,
+}
+
+impl StubResolver {
+    pub fn new() -> Self {
+        StubResolver::default()
+    }
+
+    pub fn set(&self, name: &str, addr_list: Vec<String>) {
+        self.records
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), addr_list);
+    }
+
+    pub fn remove(&self, name: &str) {
+        self.records.lock().unwrap().remove(name);
+    }
+}
+
+impl Resolver for StubResolver {
+    fn resolve(&self, name: &str) -> io::Result<Vec<String>> {
+        match self.records.lock().unwrap().get(name) {
+            Some(addr_list) => Ok(addr_list.clone()),
+            None => Err(io::Error::new(ErrorKind::NotFound, name.to_string())),
+        }
+    }
+}
+
+// ip:port girdilerini tek bir yazıma çevirir, ör. [::0001]:80 -> [::1]:80
+pub fn normalize_addr(node_addr: &str) -> String {
+    match node_addr.trim().parse::<SocketAddr>() {
+        Ok(socket_addr) => socket_addr.to_string(),
+        Err(_) => node_addr.trim().to_string(),
+    }
+}
+
+// ip:port değilse DNS ile çözülmesi gereken bir isimdir
+pub fn is_dns_name(node_addr: &str) -> bool {
+    if node_addr.starts_with(SRV_PREFIX) {
+        return true;
+    }
+    if node_addr.parse::<SocketAddr>().is_ok() {
+        return false;
+    }
+    // host:port biçiminde ve port sayı olmalı
+    match node_addr.rsplit_once(':') {
+        Some((host, port)) => !host.is_empty() && port.parse::<u16>().is_ok(),
+        None => false,
+    }
+}
+
+// localhost gibi hem IPv4 hem IPv6 dönen isimler aynı node'u iki kez eklemesin,
+// kendi adresimizle aynı ailedekiler tercih edilir
+pub fn prefer_family(addr_list: Vec<String>, my_addr: &str) -> Vec<String> {
+    let Ok(my_socket_addr) = my_addr.parse::<SocketAddr>() else {
+        return addr_list;
+    };
+    let same_family: Vec<String> = addr_list
+        .iter()
+        .filter(|addr| {
+            addr.parse::<SocketAddr>()
+                .is_ok_and(|socket_addr| socket_addr.is_ipv4() == my_socket_addr.is_ipv4())
+        })
+        .cloned()
+        .collect();
+    match same_family.is_empty() {
+        true => addr_list,
+        false => same_family,
+    }
+}
+
+// tek bir SRV sorgusu, (hedef, port) listesi öncelik sırasıyla döner
+fn query_srv(nameserver: &str, name: &str) -> io::Result<Vec<(String, u16)>> {
+    let query_id = match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(n) => (n.subsec_nanos() & 0xffff) as u16,
+        Err(_) => 0,
+    };
+    let mut query = Vec::new();
+    query.extend_from_slice(&query_id.to_be_bytes());
+    // recursion desired, tek soru
+    query.extend_from_slice(&[0x01, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(io::Error::new(ErrorKind::InvalidInput, name.to_string()));
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&DNS_TYPE_SRV.to_be_bytes());
+    query.extend_from_slice(&DNS_CLASS_IN.to_be_bytes());
+
+    let bind_addr = match nameserver.parse::<SocketAddr>() {
+        Ok(addr) if addr.is_ipv6() => "[::]:0",
+        _ => "0.0.0.0:0",
+    };
+    let socket = UdpSocket::bind(bind_addr)?;
+    socket.set_read_timeout(Some(DNS_TIMEOUT))?;
+    socket.connect(nameserver)?;
+    socket.send(&query)?;
+    let mut response = [0u8; 4096];
+    loop {
+        let n = socket.recv(&mut response)?;
+        // başka bir sorgunun geç gelen cevabı
+        if n >= 2 && response[0..2] == query_id.to_be_bytes() {
+            return parse_srv_response(&response[..n])
+                .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "bad dns response"));
+        }
+    }
+}
+
+fn parse_srv_response(data: &[u8]) -> Option<Vec<(String, u16)>> {
+    let read_u16 = |pos: usize| -> Option<u16> {
+        Some(u16::from_be_bytes([*data.get(pos)?, *data.get(pos + 1)?]))
+    };
+    let rcode = read_u16(2)? & 0x000f;
+    if rcode != 0 {
+        return Some(Vec::new());
+    }
+    let question_count = read_u16(4)?;
+    let answer_count = read_u16(6)?;
+    let mut pos = 12;
+    for _ in 0..question_count {
+        pos = read_name(data, pos)?.1 + 4;
+    }
+    let mut records = Vec::new();
+    for _ in 0..answer_count {
+        pos = read_name(data, pos)?.1;
+        let record_type = read_u16(pos)?;
+        let rdata_len = read_u16(pos + 8)? as usize;
+        let rdata_pos = pos + 10;
+        if record_type == DNS_TYPE_SRV {
+            let priority = read_u16(rdata_pos)?;
+            let weight = read_u16(rdata_pos + 2)?;
+            let port = read_u16(rdata_pos + 4)?;
+            let (target, _) = read_name(data, rdata_pos + 6)?;
+            if !target.is_empty() {
+                records.push((priority, u16::MAX - weight, target, port));
+            }
+        }
+        pos = rdata_pos + rdata_len;
+    }
+    records.sort();
+    Some(
+        records
+            .into_iter()
+            .map(|(_, _, target, port)| (target, port))
+            .collect(),
+    )
+}
+
+// sıkıştırılmış isimleri de okur, ismi ve isimden sonraki konumu döner
+fn read_name(data: &[u8], start: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut pos = start;
+    let mut end = None;
+    // döngüye giren pointer'lara karşı sınır
+    for _ in 0..128 {
+        let len = *data.get(pos)? as usize;
+        if len == 0 {
+            return Some((labels.join("."), end.unwrap_or(pos + 1)));
+        }
+        if len & 0xc0 == 0xc0 {
+            let pointer = ((len & 0x3f) << 8) | *data.get(pos + 1)? as usize;
+            end.get_or_insert(pos + 2);
+            pos = pointer;
+            continue;
+        }
+        let label = data.get(pos + 1..pos + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).to_string());
+        pos += 1 + len;
+    }
+    None
+}
+
+// bir bootstrap ismini çözer, hata durumunda boş liste döner
+pub fn resolve_entry(resolver: &dyn Resolver, name: &str, my_addr: &str) -> Vec<String> {
+    match resolver.resolve(name) {
+        Ok(addr_list) => {
+            let mut unique = Vec::new();
+            for node_addr in prefer_family(addr_list, my_addr) {
+                let node_addr = normalize_addr(&node_addr);
+                if !unique.contains(&node_addr) {
+                    unique.push(node_addr);
+                }
+            }
+            trace!(name, resolved = ?unique, "bootstrap name resolved");
+            unique
+        }
+        Err(err) => {
+            debug!(name, error = %err, "bootstrap name could not be resolved");
+            Vec::new()
+        }
+    }
+}
@@ -0,0 +1,460 @@
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tracing::debug;
+
+use crate::{
+    clock::{Clock, MockClock},
+    handle_connection::Inbound,
+    helper,
+    resolver::StubResolver,
+    structs::{ConvertVecToStruct, Message},
+    transport::{
+        error_message, Faults, Transport, TransportConnection, TransportListener, ERR_CONNECT,
+        ERR_READ,
+    },
+    Config, EventType, MessagePool,
+};
+
+// sanal saatin başlangıcı, 0 "hiç görülmedi" anlamına geldiği için gerçekçi bir zaman
+const SIM_EPOCH_MILLIS: u64 = 1_700_000_000_000;
+
+struct SimState {
+    // None: adres ayrıldı ama node henüz kaydolmadı
+    nodes: HashMap<String, Option<Inbound>>,
+    next_port: u16,
+    faults: Faults,
+}
+
+/// Single-threaded network for `Simulation`.
+///
+/// A request runs the receiver's connection handler on the caller's thread
+/// and returns its reply, so a run depends only on the seed and the order
+/// the simulation ticks the nodes in.
+#[derive(Clone)]
+pub struct SimNetwork {
+    state: Arc<Mutex<SimState>>,
+}
+
+impl SimNetwork {
+    pub fn new(seed: u64) -> Self {
+        SimNetwork {
+            state: Arc::new(Mutex::new(SimState {
+                nodes: HashMap::new(),
+                next_port: 10000,
+                faults: Faults::new(seed),
+            })),
+        }
+    }
+
+    pub fn transport(&self) -> SimTransport {
+        SimTransport {
+            network: self.clone(),
+            local_addr: Arc::new(Mutex::new(String::new())),
+        }
+    }
+
+    /// Drops this fraction (0.0 - 1.0) of requests.
+    pub fn set_loss(&self, loss_ratio: f64) {
+        self.state.lock().unwrap().faults.set_loss(loss_ratio);
+    }
+
+    /// Blocks requests from `from` to `to`, the reverse direction still works.
+    pub fn block(&self, from: &str, to: &str) {
+        self.state.lock().unwrap().faults.block(from, to);
+    }
+
+    /// Cuts every link between the two sides in both directions.
+    pub fn partition(&self, side_a: &[String], side_b: &[String]) {
+        self.state.lock().unwrap().faults.partition(side_a, side_b);
+    }
+
+    pub fn heal(&self) {
+        self.state.lock().unwrap().faults.heal();
+    }
+
+    /// Removes a node from the network as if its process crashed.
+    pub fn crash(&self, addr: &str) {
+        self.state.lock().unwrap().nodes.remove(addr);
+    }
+
+    pub fn addrs(&self) -> Vec<String> {
+        let mut addrs: Vec<String> = self.state.lock().unwrap().nodes.keys().cloned().collect();
+        addrs.sort();
+        addrs
+    }
+
+    fn register(&self, addr: &str, inbound: Inbound) {
+        self.state
+            .lock()
+            .unwrap()
+            .nodes
+            .insert(addr.to_string(), Some(inbound));
+    }
+
+    // rastgelelik tek kaynaktan gelir, aynı seed aynı sırayı üretir
+    fn next_random(&self) -> u64 {
+        self.state.lock().unwrap().faults.next_random()
+    }
+}
+
+#[derive(Clone)]
+pub struct SimTransport {
+    network: SimNetwork,
+    local_addr: Arc<Mutex<String>>,
+}
+
+impl Transport for SimTransport {
+    fn listen(&self, addr: &str) -> io::Result<Box<dyn TransportListener>> {
+        let mut state = self.network.state.lock().unwrap();
+        let bound_addr = match addr.strip_suffix(":0") {
+            Some(host) => {
+                let port = state.next_port;
+                state.next_port += 1;
+                format!("{}:{}", host, port)
+            }
+            None => addr.to_string(),
+        };
+        if state.nodes.contains_key(&bound_addr) {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, bound_addr));
+        }
+        state.nodes.insert(bound_addr.clone(), None);
+        *self.local_addr.lock().unwrap() = bound_addr.clone();
+        Ok(Box::new(SimListener { addr: bound_addr }))
+    }
+
+    fn request(&self, node_addr: &str, msg_data: &[u8]) -> Message {
+        let from = self.local_addr.lock().unwrap().clone();
+        let inbound = {
+            let mut state = self.network.state.lock().unwrap();
+            if state.faults.is_blocked(&from, node_addr) {
+                return error_message(ERR_CONNECT);
+            }
+            let inbound = match state.nodes.get(node_addr) {
+                Some(Some(inbound)) => inbound.clone(),
+                _ => return error_message(ERR_CONNECT),
+            };
+            if state.faults.is_lost() {
+                return error_message(ERR_CONNECT);
+            }
+            inbound
+        };
+
+        // alıcının kilitleri ağ kilidi bırakıldıktan sonra alınır
+        let reply = Arc::new(Mutex::new(None));
+        inbound.handle(Box::new(SimConnection {
+            from,
+            data: Some(msg_data.to_vec()),
+            reply: reply.clone(),
+        }));
+        let reply = reply.lock().unwrap().take();
+        match reply {
+            Some(reply) => reply.to_message_struct(),
+            None => error_message(ERR_READ),
+        }
+    }
+
+    fn close(&self, addr: &str) {
+        self.network.crash(addr);
+    }
+
+    fn is_inline(&self) -> bool {
+        true
+    }
+}
+
+// simülasyonda bağlantılar doğrudan iletilir, accept hiç kullanılmaz
+struct SimListener {
+    addr: String,
+}
+
+impl TransportListener for SimListener {
+    fn local_addr(&self) -> String {
+        self.addr.clone()
+    }
+
+    fn accept(&self) -> io::Result<Box<dyn TransportConnection>> {
+        Err(io::Error::new(
+            io::ErrorKind::NotConnected,
+            "simulated listener has no accept loop",
+        ))
+    }
+}
+
+struct SimConnection {
+    from: String,
+    data: Option<Vec<u8>>,
+    reply: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl TransportConnection for SimConnection {
+    fn peer_ip(&self) -> Option<IpAddr> {
+        self.from.parse::<SocketAddr>().ok().map(|addr| addr.ip())
+    }
+
+    fn set_timeout(&mut self, _timeout: Duration) {}
+
+    fn read_request(&mut self) -> io::Result<Vec<u8>> {
+        Ok(self.data.take().unwrap_or_default())
+    }
+
+    fn write_response(&mut self, data: &[u8]) -> io::Result<()> {
+        *self.reply.lock().unwrap() = Some(data.to_vec());
+        Ok(())
+    }
+}
+
+struct SimNode {
+    conf: Config,
+    pool: Option<MessagePool>,
+}
+
+/// Deterministic cluster simulator for membership and sync testing.
+///
+/// Runs many `MessagePool` nodes on one thread over a `SimNetwork`, driven
+/// by a virtual clock. Every step advances the clock by `tick_millis`,
+/// runs one ping round on each live node in a seeded random order, then
+/// drains their events. The same seed and the same sequence of calls always
+/// produce the same run.
+pub struct Simulation {
+    network: SimNetwork,
+    nodes: Vec<SimNode>,
+    clock: MockClock,
+    resolver: Arc<StubResolver>,
+    tick_millis: u64,
+    events: Vec<(u128, String, EventType)>,
+}
+
+impl Simulation {
+    /// Creates and starts `node_count` nodes, each bootstrapped from the first one.
+    pub fn new(seed: u64, node_count: usize) -> Self {
+        let mut nodes_conf = Vec::new();
+        for index in 0..node_count {
+            let mut conf = Config {
+                addr: format!("127.0.0.1:{}", 10000 + index),
+                store_node_list: false,
+                ..Config::default()
+            };
+            if index > 0 {
+                conf.bootstrap.push("127.0.0.1:10000".to_string());
+            } else if node_count > 1 {
+                conf.bootstrap.push("127.0.0.1:10001".to_string());
+            }
+            nodes_conf.push(conf);
+        }
+        Self::with_configs(seed, nodes_conf)
+    }
+
+    /// Starts one node per config, `addr` must be unique and not end in `:0`.
+    pub fn with_configs(seed: u64, nodes_conf: Vec<Config>) -> Self {
+        let mut sim = Simulation {
+            network: SimNetwork::new(seed),
+            nodes: Vec::new(),
+            clock: MockClock::new(SIM_EPOCH_MILLIS),
+            resolver: Arc::new(StubResolver::new()),
+            tick_millis: 50,
+            events: Vec::new(),
+        };
+        for conf in nodes_conf {
+            sim.nodes.push(SimNode { conf, pool: None });
+            sim.restart(sim.nodes.len() - 1);
+        }
+        sim
+    }
+
+    pub fn set_tick(&mut self, tick_millis: u64) {
+        self.tick_millis = tick_millis.max(1);
+    }
+
+    pub fn network(&self) -> &SimNetwork {
+        &self.network
+    }
+
+    /// The virtual clock every node reads, shared with the simulation.
+    pub fn clock(&self) -> &MockClock {
+        &self.clock
+    }
+
+    /// DNS records every node resolves bootstrap names from. Set them
+    /// before the nodes that use them are started or restarted.
+    pub fn resolver(&self) -> &StubResolver {
+        &self.resolver
+    }
+
+    /// Virtual time in unix millis.
+    pub fn now(&self) -> u128 {
+        self.clock.now_millis()
+    }
+
+    /// Milliseconds of virtual time since the simulation started.
+    pub fn elapsed(&self) -> u128 {
+        self.now() - SIM_EPOCH_MILLIS as u128
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn addr(&self, index: usize) -> String {
+        self.nodes[index].conf.addr.clone()
+    }
+
+    pub fn node(&self, index: usize) -> Option<&MessagePool> {
+        self.nodes[index].pool.as_ref()
+    }
+
+    pub fn node_mut(&mut self, index: usize) -> Option<&mut MessagePool> {
+        self.nodes[index].pool.as_mut()
+    }
+
+    pub fn is_alive(&self, index: usize) -> bool {
+        self.nodes[index].pool.is_some()
+    }
+
+    /// Every event the nodes returned, as (virtual time, node addr, event).
+    pub fn events(&self) -> &[(u128, String, EventType)] {
+        &self.events
+    }
+
+    pub fn step(&mut self) {
+        self.clock.advance(Duration::from_millis(self.tick_millis));
+        let now_millis = self.now();
+
+        let mut order: Vec<usize> = (0..self.nodes.len())
+            .filter(|index| self.is_alive(*index))
+            .collect();
+        for i in (1..order.len()).rev() {
+            let j = (self.network.next_random() % (i as u64 + 1)) as usize;
+            order.swap(i, j);
+        }
+
+        for index in order.iter() {
+            if let Some(pool) = self.nodes[*index].pool.as_mut() {
+                pool.tick();
+            }
+        }
+        for index in order.iter() {
+            let addr = self.nodes[*index].conf.addr.clone();
+            if let Some(pool) = self.nodes[*index].pool.as_mut() {
+                loop {
+                    let event = pool.on_event();
+                    if event != EventType::OnWait() {
+                        self.events.push((now_millis, addr.clone(), event));
+                    } else if !pool.has_pending_events() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn run_for(&mut self, millis: u128) {
+        let until = self.now() + millis;
+        while self.now() < until {
+            self.step();
+        }
+    }
+
+    /// Stops a node without a graceful goodbye, its peers only notice through failed pings.
+    pub fn crash(&mut self, index: usize) {
+        if let Some(pool) = self.nodes[index].pool.take() {
+            debug!(addr = %pool.local_addr(), "simulated crash");
+        }
+    }
+
+    /// Starts a crashed node again on the same address with an empty node list.
+    pub fn restart(&mut self, index: usize) -> bool {
+        if self.is_alive(index) {
+            return false;
+        }
+        let conf = self.nodes[index].conf.clone();
+        let mut pool = MessagePool::with_transport(Arc::new(self.network.transport()));
+        pool.set_clock(Arc::new(self.clock.clone()));
+        pool.set_resolver(self.resolver.clone());
+        if !pool.start_stepped(conf) {
+            return false;
+        }
+        self.network.register(&pool.local_addr(), pool.inbound());
+        self.nodes[index].pool = Some(pool);
+        true
+    }
+
+    /// Cuts the links between two groups of nodes, by index.
+    pub fn partition(&mut self, side_a: &[usize], side_b: &[usize]) {
+        let side_a: Vec<String> = side_a.iter().map(|index| self.addr(*index)).collect();
+        let side_b: Vec<String> = side_b.iter().map(|index| self.addr(*index)).collect();
+        self.network.partition(&side_a, &side_b);
+    }
+
+    pub fn heal(&mut self) {
+        self.network.heal();
+    }
+
+    pub fn set_loss(&mut self, loss_ratio: f64) {
+        self.network.set_loss(loss_ratio);
+    }
+
+    /// Node hash of every live node, by address.
+    pub fn node_hashes(&self) -> Vec<(String, String)> {
+        self.nodes
+            .iter()
+            .filter_map(|node| {
+                node.pool
+                    .as_ref()
+                    .map(|pool| (node.conf.addr.clone(), pool.node_hash()))
+            })
+            .collect()
+    }
+
+    /// `true` if every live node has the same node hash and sees the same
+    /// hash on all of its online peers.
+    pub fn is_converged(&self) -> bool {
+        let node_hashes = self.node_hashes();
+        let first_hash = match node_hashes.first() {
+            Some((_, node_hash)) => node_hash.clone(),
+            None => return true,
+        };
+        if node_hashes
+            .iter()
+            .any(|(_, node_hash)| !node_hash.eq(&first_hash))
+        {
+            return false;
+        }
+        self.nodes.iter().all(|node| match &node.pool {
+            Some(pool) => helper::control_nodes_hash(pool.node_list_snapshot()),
+            None => true,
+        })
+    }
+
+    /// Steps until the live nodes converge, returns the virtual millis it
+    /// took or `None` if they did not converge within `max_millis`.
+    pub fn run_until_converged(&mut self, max_millis: u128) -> Option<u128> {
+        let started = self.now();
+        loop {
+            if self.is_converged() {
+                return Some(self.now() - started);
+            }
+            if self.now() - started >= max_millis {
+                return None;
+            }
+            self.step();
+        }
+    }
+
+    /// Like `run_until_converged`, with a readable reason on failure.
+    pub fn check_converges_within(&mut self, max_millis: u128) -> Result<u128, String> {
+        match self.run_until_converged(max_millis) {
+            Some(took_millis) => Ok(took_millis),
+            None => Err(format!(
+                "nodes did not converge within {} ms: {:?}",
+                max_millis,
+                self.node_hashes()
+            )),
+        }
+    }
+}
@@ -0,0 +1,431 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{Read, Seek, SeekFrom},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use tracing::{debug, trace};
+
+use crate::{
+    clock::Clock,
+    codec,
+    message_id::MessageIds,
+    metrics::Metrics,
+    structs::{Message, MessageKind},
+    transport::Transport,
+    EventType,
+};
+
+// art arda bu kadar başarısız denemeden sonra gönderim bırakılır
+const MAX_SEND_FAILURES: u32 = 20;
+// tamamlanan stream'lerin sonucu, kaybolan son ack tekrar sorulursa diye tutulur
+const FINISHED_HISTORY: usize = 256;
+// bu süre boyunca parça gelmeyen stream yarım kalmış sayılır, gönderen çok daha önce vazgeçer
+pub const INCOMING_IDLE_MILLIS: u128 = 60_000;
+// parçanın ack'i alıcının işleme süresi ve gidiş-dönüş kadar gecikir, transport'un varsayılanı kısa kalır
+const CHUNK_REPLY_TIMEOUT: Duration = Duration::from_millis(5000);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum StreamFrame {
+    Chunk {
+        stream_id: u128,
+        offset: u64,
+        total_len: u64,
+        // JSON başlığa yazılmaz, frame_to_bytes başlıktan sonra ham olarak ekler
+        #[serde(skip)]
+        data: Vec<u8>,
+    },
+    End {
+        stream_id: u128,
+        total_len: u64,
+        checksum: String,
+    },
+}
+
+// her frame'e Ok cevabının payload'ı içinde dönülür
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct StreamAck {
+    pub stream_id: u128,
+    // alıcının sırayla aldığı byte sayısı, gönderici buradan devam eder
+    pub received: u64,
+    // false ise alıcının penceresi dolu, aynı frame biraz sonra tekrar gönderilir
+    pub accepted: bool,
+    pub completed: bool,
+    pub checksum_ok: bool,
+}
+
+struct IncomingStream {
+    received: u64,
+    hasher: md5::Context,
+    last_activity: u128,
+}
+
+// alınan stream'lerin durumu ve henüz on_event ile okunmamış stream olayları
+pub struct StreamState {
+    incoming: HashMap<(String, u128), IncomingStream>,
+    finished: HashMap<(String, u128), bool>,
+    finished_order: VecDeque<(String, u128)>,
+    pub events: VecDeque<EventType>,
+    queued_bytes: usize,
+    window: usize,
+    clock: Arc<dyn Clock>,
+}
+
+impl StreamState {
+    pub fn new(window: usize, clock: Arc<dyn Clock>) -> Self {
+        StreamState {
+            incoming: HashMap::new(),
+            finished: HashMap::new(),
+            finished_order: VecDeque::new(),
+            events: VecDeque::new(),
+            queued_bytes: 0,
+            window: window.max(1),
+            clock,
+        }
+    }
+
+    // gönderen kapandıysa veya vazgeçtiyse yarım kalan stream silinir ve alıcıya bildirilir
+    pub fn expire_idle(&mut self) {
+        let now_millis = self.clock.now_millis();
+        let expired: Vec<(String, u128)> = self
+            .incoming
+            .iter()
+            .filter(|(_, stream)| {
+                now_millis.saturating_sub(stream.last_activity) >= INCOMING_IDLE_MILLIS
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        for (sender, stream_id) in expired {
+            self.incoming.remove(&(sender.clone(), stream_id));
+            debug!(peer = %sender, stream_id, "incoming stream timed out");
+            self.events.push_back(EventType::OnStreamFailed(
+                sender,
+                stream_id,
+                "idle timeout".to_string(),
+            ));
+        }
+    }
+
+    pub fn push_event(&mut self, event: EventType) {
+        self.events.push_back(event);
+    }
+
+    pub fn pop_event(&mut self) -> Option<EventType> {
+        let event = self.events.pop_front()?;
+        if let EventType::OnStreamData(_, _, _, data) = &event {
+            self.queued_bytes = self.queued_bytes.saturating_sub(data.len());
+        }
+        Some(event)
+    }
+
+    pub fn receive(&mut self, sender: &str, frame: StreamFrame) -> StreamAck {
+        match frame {
+            StreamFrame::Chunk {
+                stream_id,
+                offset,
+                total_len,
+                data,
+            } => {
+                let key = (sender.to_string(), stream_id);
+                let now_millis = self.clock.now_millis();
+                let stream = self.incoming.entry(key).or_insert(IncomingStream {
+                    received: 0,
+                    hasher: md5::Context::new(),
+                    last_activity: now_millis,
+                });
+                stream.last_activity = now_millis;
+                let mut ack = StreamAck {
+                    stream_id,
+                    received: stream.received,
+                    accepted: true,
+                    ..StreamAck::default()
+                };
+                if offset != stream.received || offset + data.len() as u64 > total_len {
+                    // tekrar gelen veya eksik kalan parça, gönderici ack'e göre konumunu düzeltir
+                    return ack;
+                }
+                if self.queued_bytes > 0 && self.queued_bytes + data.len() > self.window {
+                    ack.accepted = false;
+                    return ack;
+                }
+                stream.hasher.consume(&data);
+                stream.received += data.len() as u64;
+                ack.received = stream.received;
+                self.queued_bytes += data.len();
+                self.events.push_back(EventType::OnStreamData(
+                    sender.to_string(),
+                    stream_id,
+                    offset,
+                    data,
+                ));
+                ack
+            }
+            StreamFrame::End {
+                stream_id,
+                total_len,
+                checksum,
+            } => {
+                let key = (sender.to_string(), stream_id);
+                if let Some(checksum_ok) = self.finished.get(&key) {
+                    return StreamAck {
+                        stream_id,
+                        received: total_len,
+                        accepted: true,
+                        completed: true,
+                        checksum_ok: *checksum_ok,
+                    };
+                }
+                let received = match self.incoming.get(&key) {
+                    Some(stream) => stream.received,
+                    None => 0,
+                };
+                if received != total_len {
+                    return StreamAck {
+                        stream_id,
+                        received,
+                        accepted: true,
+                        ..StreamAck::default()
+                    };
+                }
+                let checksum_ok = match self.incoming.remove(&key) {
+                    Some(stream) => format!("{:x}", stream.hasher.compute()).eq(&checksum),
+                    None => checksum.eq(&format!("{:x}", md5::compute([]))),
+                };
+                self.events.push_back(if checksum_ok {
+                    EventType::OnStreamCompleted(sender.to_string(), stream_id)
+                } else {
+                    EventType::OnStreamFailed(
+                        sender.to_string(),
+                        stream_id,
+                        "checksum mismatch".to_string(),
+                    )
+                });
+                self.finished.insert(key.clone(), checksum_ok);
+                self.finished_order.push_back(key);
+                while self.finished_order.len() > FINISHED_HISTORY {
+                    if let Some(oldest) = self.finished_order.pop_front() {
+                        self.finished.remove(&oldest);
+                    }
+                }
+                StreamAck {
+                    stream_id,
+                    received,
+                    accepted: true,
+                    completed: true,
+                    checksum_ok,
+                }
+            }
+        }
+    }
+}
+
+// JSON başlık, Chunk ise ardından '\n' ve ham veri; başlıkta satır sonu olmaz
+pub fn frame_to_bytes(frame: &StreamFrame) -> Vec<u8> {
+    let mut frame_data = serde_json::to_vec(frame).unwrap_or_default();
+    if let StreamFrame::Chunk { data, .. } = frame {
+        frame_data.push(b'\n');
+        frame_data.extend_from_slice(data);
+    }
+    frame_data
+}
+
+pub fn frame_from_bytes(frame_data: &[u8]) -> Option<StreamFrame> {
+    let Some(split_at) = frame_data.iter().position(|byte| *byte == b'\n') else {
+        return serde_json::from_slice(frame_data).ok();
+    };
+    match serde_json::from_slice(&frame_data[..split_at]).ok()? {
+        StreamFrame::Chunk {
+            stream_id,
+            offset,
+            total_len,
+            ..
+        } => Some(StreamFrame::Chunk {
+            stream_id,
+            offset,
+            total_len,
+            data: frame_data[split_at + 1..].to_vec(),
+        }),
+        StreamFrame::End { .. } => None,
+    }
+}
+
+pub fn ack_to_bytes(ack: &StreamAck) -> Vec<u8> {
+    serde_json::to_vec(ack).unwrap_or_default()
+}
+
+// gönderen tarafın bir stream için ihtiyaç duyduğu her şey
+pub struct OutgoingStream<R: Read + Seek> {
+    pub stream_id: u128,
+    pub my_addr: String,
+    pub receiver: String,
+    // receiver'a ulaşılan adres, node başka bir adresten cevap veriyorsa farklıdır
+    pub dial_addr: String,
+    pub cluster_token: String,
+    pub chunk_size: usize,
+    // alıcı desteklemiyorsa 0
+    pub compression_threshold: usize,
+    pub source: R,
+    pub transport: Arc<dyn Transport>,
+    pub ids: Arc<MessageIds>,
+    pub metrics: Arc<Metrics>,
+    pub state: Arc<Mutex<StreamState>>,
+    pub running: Arc<AtomicBool>,
+}
+
+impl<R: Read + Seek + Send + 'static> OutgoingStream<R> {
+    pub fn spawn(self) {
+        thread::spawn(move || self.run());
+    }
+
+    fn run(mut self) {
+        let stream_id = self.stream_id;
+        let receiver = self.receiver.clone();
+        let result = self.send_all();
+        let event = match result {
+            Ok(()) => EventType::OnStreamCompleted(receiver, stream_id),
+            Err(reason) => {
+                debug!(peer = %receiver, stream_id, reason = %reason, "stream failed");
+                EventType::OnStreamFailed(receiver, stream_id, reason)
+            }
+        };
+        self.state.lock().unwrap().push_event(event);
+    }
+
+    fn send_all(&mut self) -> Result<(), String> {
+        let total_len = self
+            .source
+            .seek(SeekFrom::End(0))
+            .map_err(|err| err.to_string())?;
+        self.source
+            .seek(SeekFrom::Start(0))
+            .map_err(|err| err.to_string())?;
+
+        // position: alıcının onayladığı ve hash'e eklenen byte sayısı
+        let mut position = 0u64;
+        let mut hasher = md5::Context::new();
+        let mut failures = 0;
+        // son gönderilen parça, onaylanınca tekrar okumadan hash'e eklenir
+        let mut last_chunk: Option<(u64, Vec<u8>)> = None;
+        loop {
+            if !self.running.load(Ordering::SeqCst) {
+                return Err("node stopped".to_string());
+            }
+            let frame = if position == total_len {
+                StreamFrame::End {
+                    stream_id: self.stream_id,
+                    total_len,
+                    checksum: format!("{:x}", hasher.clone().compute()),
+                }
+            } else {
+                self.source
+                    .seek(SeekFrom::Start(position))
+                    .map_err(|err| err.to_string())?;
+                let chunk_len = (total_len - position).min(self.chunk_size as u64) as usize;
+                let mut data = vec![0u8; chunk_len];
+                self.source
+                    .read_exact(&mut data)
+                    .map_err(|err| err.to_string())?;
+                last_chunk = Some((position, data.clone()));
+                StreamFrame::Chunk {
+                    stream_id: self.stream_id,
+                    offset: position,
+                    total_len,
+                    data,
+                }
+            };
+
+            let msg = Message {
+                id: self.ids.next(),
+                sender: self.my_addr.clone(),
+                kind: MessageKind::Stream,
+                payload: frame_to_bytes(&frame),
+                cluster_token: self.cluster_token.clone(),
+                payload_codec: String::new(),
+                handshake: None,
+                node_metadata: Vec::new(),
+            };
+            let msg_data = match codec::compress(&msg, self.compression_threshold) {
+                Some(encoded) => encoded.to_compact_byte_array(),
+                None => msg.to_compact_byte_array(),
+            };
+            self.metrics
+                .record_sent(&MessageKind::Stream, msg_data.len());
+            let result = self.transport.request_with_timeout(
+                &self.dial_addr,
+                &msg_data,
+                CHUNK_REPLY_TIMEOUT,
+            );
+            if result.kind != MessageKind::Ok {
+                failures += 1;
+                if failures >= MAX_SEND_FAILURES {
+                    return Err(format!("peer unreachable, code {}", result.id));
+                }
+                // bağlantı koparsa biraz bekleyip alıcının ack'ine göre devam et
+                thread::sleep(Duration::from_millis(50 * failures as u64));
+                continue;
+            }
+            failures = 0;
+            let ack: StreamAck = match serde_json::from_slice(&result.payload) {
+                Ok(ack) => ack,
+                Err(_) => return Err("peer does not support streams".to_string()),
+            };
+            if ack.completed {
+                return match ack.checksum_ok {
+                    true => Ok(()),
+                    false => Err("checksum mismatch".to_string()),
+                };
+            }
+            if !ack.accepted {
+                trace!(peer = %self.receiver, stream_id = self.stream_id, "receiver window full");
+                thread::sleep(Duration::from_millis(20));
+                continue;
+            }
+
+            if ack.received < position {
+                // alıcı baştan başladı, hash de baştan hesaplanır
+                position = 0;
+                hasher = md5::Context::new();
+                self.source
+                    .seek(SeekFrom::Start(0))
+                    .map_err(|err| err.to_string())?;
+            }
+            let ack_received = ack.received.min(total_len);
+            let position_before = position;
+            if let Some((chunk_offset, data)) = last_chunk.take() {
+                if chunk_offset == position && position + data.len() as u64 <= ack_received {
+                    hasher.consume(&data);
+                    position += data.len() as u64;
+                }
+            }
+            while position < ack_received {
+                let read_len = (ack_received - position).min(self.chunk_size as u64) as usize;
+                let mut data = vec![0u8; read_len];
+                self.source
+                    .seek(SeekFrom::Start(position))
+                    .map_err(|err| err.to_string())?;
+                self.source
+                    .read_exact(&mut data)
+                    .map_err(|err| err.to_string())?;
+                hasher.consume(&data);
+                position += read_len as u64;
+            }
+            if position == position_before {
+                continue;
+            }
+            self.state
+                .lock()
+                .unwrap()
+                .push_event(EventType::OnStreamProgress(
+                    self.receiver.clone(),
+                    self.stream_id,
+                    position,
+                    total_len,
+                ));
+        }
+    }
+}
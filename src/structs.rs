@@ -1,34 +1,49 @@
-use log::error;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     fmt,
     fs::File,
     io::{BufWriter, Write},
+    path::PathBuf,
     sync::{Arc, Mutex},
 };
+use tracing::error;
 
-use crate::helper;
+use crate::{
+    codec,
+    handshake::Handshake,
+    helper,
+    metrics::Metrics,
+    transport::{Transport, ERR_CONNECT},
+};
 
 pub trait NodeDetailsToHelper {
-    fn store_to_disk(&self, my_node_addr: String);
+    fn store_to_disk(&self, file_path: PathBuf, my_node_addr: &str);
     fn calculate_hash(&self) -> String;
     fn set_sync_time(&mut self, which_node: Vec<String>, new_sync_time: u128);
-    fn send_state_to_all(&self, my_addr: String, state: StateType);
+    fn send_state_to_all(
+        &self,
+        msg: &Message,
+        compression_threshold: usize,
+        transport: &dyn Transport,
+        metrics: &Metrics,
+    );
     fn to_node_list(&self) -> Vec<String>;
+    fn dial_addr_of(&self, node_addr: &str) -> String;
+    fn to_node_metadata(&self) -> Vec<NodeMetadata>;
+    fn merge_node_metadata(&self, income_metadata: &[NodeMetadata], my_node_addr: &str) -> bool;
+    fn merge_moved_node(&self, node_addr: &str) -> Option<(String, String)>;
     fn online_node_count(&self) -> usize;
 }
 impl NodeDetailsToHelper for Arc<Mutex<Vec<NodeDetails>>> {
-    fn store_to_disk(&self, my_node_addr: String) {
-        match File::create(format!(
-            "{}.json",
-            my_node_addr.replace(".", "_").replace(":", "_")
-        )) {
-            Ok(file) => {
-                let mut writer = BufWriter::new(file);
-                _ = serde_json::to_writer(&mut writer, &self.to_node_list());
-                _ = writer.flush();
-            }
-            Err(_) => {}
+    // kendi adresimiz saklanmaz, node başka bir adresle yeniden başlayabilir
+    fn store_to_disk(&self, file_path: PathBuf, my_node_addr: &str) {
+        if let Ok(file) = File::create(file_path) {
+            let mut writer = BufWriter::new(file);
+            let mut tmp_node_list = self.to_node_list();
+            tmp_node_list.retain(|node_addr| !node_addr.eq(my_node_addr));
+            _ = serde_json::to_writer(&mut writer, &tmp_node_list);
+            _ = writer.flush();
         }
     }
     fn calculate_hash(&self) -> String {
@@ -53,28 +68,41 @@ impl NodeDetailsToHelper for Arc<Mutex<Vec<NodeDetails>>> {
     }
     fn set_sync_time(&mut self, which_node: Vec<String>, new_sync_time: u128) {
         for n_info in self.lock().unwrap().iter_mut() {
-            if which_node.len() > 0 {
+            if !which_node.is_empty() {
                 for n_addr in which_node.iter() {
                     if n_addr.eq(&n_info.addr.clone()) {
-                        n_info.synced_time_as_secs = new_sync_time;
+                        n_info.synced_time = new_sync_time;
                     }
                 }
             } else {
-                n_info.synced_time_as_secs = new_sync_time;
+                n_info.synced_time = new_sync_time;
             }
         }
     }
-    fn send_state_to_all(&self, my_node_addr: String, state: StateType) {
-        let msg_array = Message {
-            id: helper::get_sys_time_in_nano(),
-            sender: my_node_addr.clone(),
-            kind: MessageKind::State,
-            payload: state.to_byte_array(),
-        }
-        .to_byte_array();
-        for receiver_addr in self.to_node_list().iter() {
-            if my_node_addr.eq(receiver_addr) == false {
-                _ = helper::client(receiver_addr.to_string(), msg_array.clone());
+    fn send_state_to_all(
+        &self,
+        msg: &Message,
+        compression_threshold: usize,
+        transport: &dyn Transport,
+        metrics: &Metrics,
+    ) {
+        let my_node_addr = &msg.sender;
+        let msg_array = msg.to_byte_array();
+        let compressed_array =
+            codec::compress(msg, compression_threshold).map(|encoded| encoded.to_byte_array());
+        let mut tmp_node_list = self.lock().unwrap().clone();
+        tmp_node_list.sort();
+        for n_info in tmp_node_list.iter() {
+            if !my_node_addr.eq(&n_info.addr) {
+                // eski node'lar sıkıştırılmamış mesaj alır
+                let msg_array = match &compressed_array {
+                    Some(compressed) if codec::accepts_compressed(&n_info.codecs) => compressed,
+                    _ => &msg_array,
+                };
+                metrics.record_sent(&MessageKind::State, msg_array.len());
+                if transport.request(n_info.dial_addr(), msg_array).id == ERR_CONNECT {
+                    metrics.record_connect_failure();
+                }
             }
         }
     }
@@ -88,11 +116,105 @@ impl NodeDetailsToHelper for Arc<Mutex<Vec<NodeDetails>>> {
         tmp_node_list
     }
 
+    fn dial_addr_of(&self, node_addr: &str) -> String {
+        for n_info in self.lock().unwrap().iter() {
+            if n_info.addr.eq(node_addr) {
+                return n_info.dial_addr().to_string();
+            }
+        }
+        node_addr.to_string()
+    }
+
+    fn to_node_metadata(&self) -> Vec<NodeMetadata> {
+        let mut tmp_metadata = Vec::new();
+        for n_info in self.lock().unwrap().iter() {
+            if n_info.metadata_version > 0
+                || !n_info.metadata.is_empty()
+                || !n_info.node_id.is_empty()
+            {
+                tmp_metadata.push(NodeMetadata {
+                    addr: n_info.addr.clone(),
+                    node_id: n_info.node_id.clone(),
+                    version: n_info.metadata_version,
+                    metadata: n_info.metadata.clone(),
+                });
+            }
+        }
+        tmp_metadata.sort();
+        tmp_metadata
+    }
+
+    // kendi metadata'mız sadece set_metadata ile değişir
+    fn merge_node_metadata(&self, income_metadata: &[NodeMetadata], my_node_addr: &str) -> bool {
+        let mut updated = false;
+        for n_info in self.lock().unwrap().iter_mut() {
+            if n_info.addr.eq(my_node_addr) {
+                continue;
+            }
+            for income in income_metadata.iter() {
+                if !income.addr.eq(&n_info.addr) {
+                    continue;
+                }
+                if n_info.node_id.is_empty() && !income.node_id.is_empty() {
+                    n_info.node_id = income.node_id.clone();
+                    updated = true;
+                }
+                if income.node_id.eq(&n_info.node_id) && income.version > n_info.metadata_version {
+                    n_info.metadata = income.metadata.clone();
+                    n_info.metadata_version = income.version;
+                    updated = true;
+                }
+            }
+        }
+        updated
+    }
+
+    // node_addr'deki node'un id'si listede başka bir adreste de varsa node taşınmıştır,
+    // eski kayıt yeni adrese güncellenir ve (node_id, eski adres) döner
+    fn merge_moved_node(&self, node_addr: &str) -> Option<(String, String)> {
+        let mut node_list = self.lock().unwrap();
+        let new_index = node_list.iter().position(|n| n.addr.eq(node_addr))?;
+        let node_id = node_list[new_index].node_id.clone();
+        if node_id.is_empty() {
+            return None;
+        }
+        let old_index = node_list
+            .iter()
+            .position(|n| n.node_id.eq(&node_id) && !n.addr.eq(node_addr))?;
+        let new_info = node_list.remove(new_index);
+        let old_index = match old_index > new_index {
+            true => old_index - 1,
+            false => old_index,
+        };
+        let old_info = &mut node_list[old_index];
+        let old_addr = std::mem::replace(&mut old_info.addr, new_info.addr);
+        old_info.reach_addr = String::new();
+        old_info.observed_addr = new_info.observed_addr;
+        old_info.last_access_time = old_info.last_access_time.max(new_info.last_access_time);
+        if new_info.protocol_version > 0 {
+            old_info.protocol_version = new_info.protocol_version;
+            old_info.codecs = new_info.codecs;
+            old_info.features = new_info.features;
+            old_info.advertised_addrs = new_info.advertised_addrs;
+            old_info.routes = new_info.routes;
+            old_info.reachability = new_info.reachability;
+        }
+        if new_info.metadata_version >= old_info.metadata_version {
+            old_info.metadata = new_info.metadata;
+            old_info.metadata_version = new_info.metadata_version;
+        }
+        if old_info.status == NodeStatus::Offline {
+            // yeni adres bir sonraki ping turunda hemen denenir
+            old_info.status = NodeStatus::Unknown;
+        }
+        Some((node_id, old_addr))
+    }
+
     fn online_node_count(&self) -> usize {
         let mut r_count = 0;
         for n_info in self.lock().unwrap().clone().iter() {
             if n_info.status == NodeStatus::Online {
-                r_count = r_count + 1;
+                r_count += 1;
             }
         }
         r_count
@@ -108,8 +230,8 @@ impl ConvertVecToStruct for Vec<u8> {
         match String::from_utf8(self.clone()) {
             Ok(income) => {
                 let payload_result: serde_json::Result<Message> = serde_json::from_str(&income);
-                if payload_result.is_ok() {
-                    return payload_result.unwrap();
+                if let Ok(result) = payload_result {
+                    return result;
                 }
                 error!("Message convert error [ 8394 ]");
             }
@@ -122,14 +244,18 @@ impl ConvertVecToStruct for Vec<u8> {
             sender: String::new(),
             kind: MessageKind::Error,
             payload: Vec::new(),
+            cluster_token: String::new(),
+            payload_codec: String::new(),
+            handshake: None,
+            node_metadata: Vec::new(),
         }
     }
     fn to_state_struct(&self) -> StateType {
         match String::from_utf8(self.clone()) {
             Ok(income) => {
                 let payload_result: serde_json::Result<StateType> = serde_json::from_str(&income);
-                if payload_result.is_ok() {
-                    return payload_result.unwrap();
+                if let Ok(result) = payload_result {
+                    return result;
                 }
                 error!("Message convert error [ 8394 ]");
             }
@@ -151,6 +277,45 @@ impl Message {
             }
         }
     }
+
+    // payload sayı dizisi yerine base64 yazılır, mesaj birkaç kat küçülür; eski node'lar
+    // bu biçimi okuyamaz, sadece Dht, Stream gibi yeni türlerin istek ve cevaplarında kullanılır
+    pub fn to_compact_byte_array(&self) -> Vec<u8> {
+        let compact = CompactMessage {
+            id: self.id,
+            sender: &self.sender,
+            kind: &self.kind,
+            payload: &self.payload,
+            cluster_token: &self.cluster_token,
+            payload_codec: &self.payload_codec,
+            handshake: &self.handshake,
+            node_metadata: &self.node_metadata,
+        };
+        match serde_json::to_vec(&compact) {
+            Ok(result) => result,
+            Err(_) => {
+                error!("Message convert error [ 4827 ]");
+                Vec::new()
+            }
+        }
+    }
+}
+
+// Message ile aynı alanlar, sadece payload'ın yazılışı farklı
+#[derive(Serialize)]
+struct CompactMessage<'a> {
+    id: u128,
+    sender: &'a str,
+    kind: &'a MessageKind,
+    #[serde(with = "helper::base64_bytes")]
+    payload: &'a [u8],
+    cluster_token: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    payload_codec: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    handshake: &'a Option<Box<Handshake>>,
+    #[serde(skip_serializing_if = "<[NodeMetadata]>::is_empty")]
+    node_metadata: &'a [NodeMetadata],
 }
 
 impl StateType {
@@ -170,7 +335,31 @@ pub struct Message {
     pub id: u128,
     pub sender: String,
     pub kind: MessageKind,
+    // sayı dizisi veya base64 olarak gelebilir, bkz. to_compact_byte_array
+    #[serde(deserialize_with = "helper::base64_bytes::deserialize")]
     pub payload: Vec<u8>,
+    #[serde(default)]
+    pub cluster_token: String,
+    // payload kodlaması, boş ise ham veri
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub payload_codec: String,
+    // sürüm ve yetenek bilgisi, ping ve ping cevaplarında dolu gelir
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handshake: Option<Box<Handshake>>,
+    // node listesi ile birlikte gönderilen metadata
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub node_metadata: Vec<NodeMetadata>,
+}
+
+/// Metadata of one node as it travels with the node list. The entry with
+/// the larger `version` wins.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NodeMetadata {
+    pub addr: String,
+    #[serde(default)]
+    pub node_id: String,
+    pub version: u64,
+    pub metadata: BTreeMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -183,6 +372,18 @@ pub enum MessageKind {
     // dağıtılacak mesaj
     Distribute,
 
+    // büyük verilerin parça parça gönderimi, payload bir StreamFrame
+    Stream,
+
+    // DHT sorgusu, payload bir DhtQuery, cevabın payload'ı DhtReply
+    Dht,
+
+    // başka bir node'a iletilecek mesaj, payload bir RelayEnvelope
+    Relay,
+
+    // NAT geçişi, payload bir NatRequest, cevabın payload'ı NatReply
+    Nat,
+
     // hatalı mesaj veya işlem tipi
     Error,
 }
@@ -192,8 +393,157 @@ pub struct NodeDetails {
     pub addr: String,
     pub node_hash: String,
     pub last_access_time: u128,
-    pub synced_time_as_secs: u128,
+    // offline'a düştüğü zaman, tekrar deneme bundan itibaren sayılır
+    pub offline_since: u128,
+    pub synced_time: u128,
     pub status: NodeStatus,
+    pub link_quality: LinkQuality,
+    pub metadata: BTreeMap<String, String>,
+    pub metadata_version: u64,
+    // handshake ile öğrenilenler, protocol_version 0 ise henüz handshake görülmedi
+    pub protocol_version: u32,
+    pub node_id: String,
+    pub codecs: Vec<String>,
+    pub features: Vec<String>,
+    // node'un handshake ile bildirdiği adresleri
+    pub advertised_addrs: Vec<String>,
+    // node'un bize bağlandığı IP ile kimlik portu
+    pub observed_addr: String,
+    // addr'e ulaşılamadığında cevap veren adres, boş ise addr kullanılır
+    pub reach_addr: String,
+    // node'un handshake ile bildirdiği, ulaşabildiği node'lar ve adım sayıları
+    pub routes: BTreeMap<String, u8>,
+    // node'un kendi ölçtüğü, dışarıdan bağlantı alıp alamadığı
+    pub reachability: Reachability,
+}
+
+impl NodeDetails {
+    // node'a gönderilen mesajların gideceği adres
+    pub fn dial_addr(&self) -> &str {
+        match self.reach_addr.is_empty() {
+            true => &self.addr,
+            false => &self.reach_addr,
+        }
+    }
+
+    // ulaşılamadığında sırayla denenen adresler
+    pub fn dial_candidates(&self) -> Vec<String> {
+        let mut candidates = vec![self.dial_addr().to_string(), self.addr.clone()];
+        candidates.extend(self.advertised_addrs.iter().cloned());
+        candidates.push(self.observed_addr.clone());
+        let mut unique = Vec::new();
+        for candidate in candidates {
+            if !candidate.is_empty()
+                && !helper::is_unspecified_addr(&candidate)
+                && !unique.contains(&candidate)
+            {
+                unique.push(candidate);
+            }
+        }
+        unique
+    }
+}
+
+// ping sürelerinden hesaplanan bağlantı kalitesi, süreler mikrosaniye cinsinden
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LinkQuality {
+    pub rtt_last_us: u64,
+    pub rtt_ewma_us: u64,
+    pub rtt_min_us: u64,
+    pub rtt_jitter_us: u64,
+    pub success_permille: u32,
+    pub ping_count: u64,
+    pub failure_count: u64,
+}
+
+impl LinkQuality {
+    pub fn record_success(&mut self, rtt_us: u64) {
+        // süreler ilk başarılı ölçümden başlar, önceki başarısız ping'ler sadece oranı düşürür
+        if self.ping_count == self.failure_count {
+            self.rtt_ewma_us = rtt_us;
+            self.rtt_min_us = rtt_us;
+        } else {
+            // EWMA (1/8) ve RFC 3550 tarzı jitter (1/16)
+            self.rtt_ewma_us = (self.rtt_ewma_us * 7 + rtt_us) / 8;
+            self.rtt_min_us = self.rtt_min_us.min(rtt_us);
+            let rtt_diff = rtt_us.abs_diff(self.rtt_last_us);
+            self.rtt_jitter_us = (self.rtt_jitter_us * 15 + rtt_diff) / 16;
+        }
+        self.success_permille = match self.ping_count {
+            0 => 1000,
+            _ => (self.success_permille * 7 + 1000) / 8,
+        };
+        self.rtt_last_us = rtt_us;
+        self.ping_count += 1;
+    }
+
+    pub fn record_failure(&mut self) {
+        self.ping_count += 1;
+        self.failure_count += 1;
+        self.success_permille = self.success_permille * 7 / 8;
+    }
+
+    pub fn success_ratio(&self) -> f64 {
+        self.success_permille as f64 / 1000.0
+    }
+
+    // küçük değer daha sağlıklı bağlantı demek, hiç ölçülmemiş node'lar en sona kalır
+    pub fn score(&self) -> u64 {
+        if self.ping_count == self.failure_count {
+            return u64::MAX;
+        }
+        let penalty = (1000 - self.success_permille.min(1000)) as u64;
+        self.rtt_ewma_us + self.rtt_jitter_us + penalty * 1000
+    }
+}
+
+// node listesinin dışarıya açılan, salt okunur kopyası
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PeerInfo {
+    pub addr: String,
+    pub status: NodeStatus,
+    // son başarılı ping zamanı, node'un Clock'undan milisaniye; unix zamanı olması gerekmez
+    // (ör. MockClock, simülasyon), 0 ise hiç görülmedi
+    pub last_seen: u128,
+    // node'un bize bildirdiği node listesi hash'i
+    pub node_hash: String,
+    pub synced_time: u128,
+    pub link_quality: LinkQuality,
+    pub metadata: BTreeMap<String, String>,
+    // handshake göndermeyen eski node'larda 0 ve boş
+    pub protocol_version: u32,
+    pub node_id: String,
+    // node'un açabildiği payload kodlamaları
+    pub codecs: Vec<String>,
+    pub features: Vec<String>,
+    pub advertised_addrs: Vec<String>,
+    // bu node'un bize bağlanırken görülen adresi
+    pub observed_addr: String,
+    // addr yerine kullanılan adres, boş ise addr
+    pub reach_addr: String,
+    pub reachability: Reachability,
+}
+
+impl From<&NodeDetails> for PeerInfo {
+    fn from(n_info: &NodeDetails) -> Self {
+        PeerInfo {
+            addr: n_info.addr.clone(),
+            status: n_info.status.clone(),
+            last_seen: n_info.last_access_time,
+            node_hash: n_info.node_hash.clone(),
+            synced_time: n_info.synced_time,
+            link_quality: n_info.link_quality.clone(),
+            metadata: n_info.metadata.clone(),
+            protocol_version: n_info.protocol_version,
+            node_id: n_info.node_id.clone(),
+            codecs: n_info.codecs.clone(),
+            features: n_info.features.clone(),
+            advertised_addrs: n_info.advertised_addrs.clone(),
+            observed_addr: n_info.observed_addr.clone(),
+            reach_addr: n_info.reach_addr.clone(),
+            reachability: n_info.reachability.clone(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -203,6 +553,23 @@ pub enum NodeStatus {
     Unknown,
 }
 
+/// Whether peers can open connections to a node's advertised addresses.
+/// `Private` nodes, e.g. behind NAT, are only reached through connections
+/// they opened first.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Reachability {
+    #[default]
+    Unknown,
+    Public,
+    Private,
+}
+
+impl fmt::Display for Reachability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
 impl fmt::Display for NodeStatus {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
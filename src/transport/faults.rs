@@ -0,0 +1,54 @@
+use std::collections::HashSet;
+
+use crate::helper;
+
+// MemoryNetwork ve SimNetwork'ün ortak hata enjeksiyonu: kesik bağlantılar ve seed'li kayıp
+pub(crate) struct Faults {
+    loss_permille: u32,
+    blocked: HashSet<(String, String)>,
+    rng_state: u64,
+}
+
+impl Faults {
+    pub fn new(seed: u64) -> Self {
+        Faults {
+            loss_permille: 0,
+            blocked: HashSet::new(),
+            rng_state: seed.max(1),
+        }
+    }
+
+    pub fn set_loss(&mut self, loss_ratio: f64) {
+        self.loss_permille = (loss_ratio.clamp(0.0, 1.0) * 1000.0).round() as u32;
+    }
+
+    pub fn block(&mut self, from: &str, to: &str) {
+        self.blocked.insert((from.to_string(), to.to_string()));
+    }
+
+    pub fn partition(&mut self, side_a: &[String], side_b: &[String]) {
+        for a in side_a.iter() {
+            for b in side_b.iter() {
+                self.blocked.insert((a.clone(), b.clone()));
+                self.blocked.insert((b.clone(), a.clone()));
+            }
+        }
+    }
+
+    pub fn heal(&mut self) {
+        self.blocked.clear();
+    }
+
+    pub fn is_blocked(&self, from: &str, to: &str) -> bool {
+        self.blocked.contains(&(from.to_string(), to.to_string()))
+    }
+
+    // kayıp açıksa her çağrı bir sayı çeker, aynı seed ve istek sırası aynı istekleri düşürür
+    pub fn is_lost(&mut self) -> bool {
+        self.loss_permille > 0 && self.next_random() % 1000 < self.loss_permille as u64
+    }
+
+    pub fn next_random(&mut self) -> u64 {
+        helper::next_random(&mut self.rng_state)
+    }
+}
@@ -0,0 +1,340 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use super::{
+    error_message, Faults, Transport, TransportConnection, TransportListener, ERR_CONNECT, ERR_READ,
+};
+use crate::structs::{ConvertVecToStruct, Message};
+
+struct MemoryRequest {
+    from: String,
+    data: Vec<u8>,
+    reply: mpsc::Sender<Vec<u8>>,
+}
+
+struct NetworkState {
+    listeners: HashMap<String, mpsc::Sender<MemoryRequest>>,
+    next_port: u16,
+    latency: Duration,
+    link_latency: HashMap<(String, String), Duration>,
+    faults: Faults,
+    reply_timeout: Duration,
+    // NAT arkasındaki adres -> dışarıdan görünen IP
+    nat: HashMap<String, IpAddr>,
+    // (NAT arkasındaki adres, istek gönderdiği adres), cevap bağlantılarına izin verir
+    nat_mappings: HashSet<(String, String)>,
+}
+
+impl NetworkState {
+    fn latency_between(&self, from: &str, to: &str) -> Duration {
+        match self.link_latency.get(&(from.to_string(), to.to_string())) {
+            Some(latency) => *latency,
+            None => self.latency,
+        }
+    }
+
+    // NAT arkasındaki node'un dışarıdan görünen adresi, port korunur
+    fn public_addr(&self, addr: &str) -> String {
+        match (self.nat.get(addr), addr.parse::<SocketAddr>()) {
+            (Some(public_ip), Ok(socket_addr)) => {
+                SocketAddr::new(*public_ip, socket_addr.port()).to_string()
+            }
+            _ => addr.to_string(),
+        }
+    }
+
+    // isteğin ulaşacağı dinleyici adresi, NAT bağlantıyı geçirmiyorsa None
+    fn route(&mut self, from: &str, node_addr: &str) -> Option<String> {
+        let seen_from = self.public_addr(from);
+        if self.nat.contains_key(from) {
+            self.nat_mappings
+                .insert((from.to_string(), node_addr.to_string()));
+        }
+        if self.nat.contains_key(node_addr) {
+            // özel adrese dışarıdan ulaşılamaz
+            return None;
+        }
+        let inside = self
+            .nat
+            .keys()
+            .find(|inside| self.public_addr(inside).eq(node_addr))
+            .cloned();
+        match inside {
+            Some(inside) if self.nat_mappings.contains(&(inside.clone(), seen_from)) => {
+                Some(inside)
+            }
+            Some(_) => None,
+            None => Some(node_addr.to_string()),
+        }
+    }
+}
+
+/// In-process network shared by `MemoryTransport`s, with injectable faults.
+///
+/// Latency, loss and partitions apply to every transport created from the
+/// same network. Loss is drawn from a seeded generator, so a given seed and
+/// message order always drop the same messages.
+#[derive(Clone)]
+pub struct MemoryNetwork {
+    state: Arc<Mutex<NetworkState>>,
+}
+
+impl MemoryNetwork {
+    pub fn new(seed: u64) -> Self {
+        MemoryNetwork {
+            state: Arc::new(Mutex::new(NetworkState {
+                listeners: HashMap::new(),
+                next_port: 10000,
+                latency: Duration::ZERO,
+                link_latency: HashMap::new(),
+                faults: Faults::new(seed),
+                reply_timeout: Duration::from_secs(1),
+                nat: HashMap::new(),
+                nat_mappings: HashSet::new(),
+            })),
+        }
+    }
+
+    pub fn transport(&self) -> MemoryTransport {
+        MemoryTransport {
+            network: self.clone(),
+            local_addr: Arc::new(Mutex::new(String::new())),
+        }
+    }
+
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    pub fn set_link_latency(&self, from: &str, to: &str, latency: Duration) {
+        self.state
+            .lock()
+            .unwrap()
+            .link_latency
+            .insert((from.to_string(), to.to_string()), latency);
+    }
+
+    /// Drops this fraction (0.0 - 1.0) of requests.
+    pub fn set_loss(&self, loss_ratio: f64) {
+        self.state.lock().unwrap().faults.set_loss(loss_ratio);
+    }
+
+    pub fn set_reply_timeout(&self, reply_timeout: Duration) {
+        self.state.lock().unwrap().reply_timeout = reply_timeout;
+    }
+
+    /// Blocks requests from `from` to `to`, the reverse direction still works.
+    pub fn block(&self, from: &str, to: &str) {
+        self.state.lock().unwrap().faults.block(from, to);
+    }
+
+    /// Cuts every link between the two sides in both directions.
+    pub fn partition(&self, side_a: &[String], side_b: &[String]) {
+        self.state.lock().unwrap().faults.partition(side_a, side_b);
+    }
+
+    pub fn heal(&self) {
+        self.state.lock().unwrap().faults.heal();
+    }
+
+    /// Puts `addr` behind a port preserving NAT whose public IP is `public_ip`.
+    ///
+    /// Its requests reach peers from `public_ip` with the same port. Peers
+    /// cannot dial `addr` itself, and reach `public_ip:port` only after
+    /// `addr` has sent a request to them.
+    pub fn set_nat(&self, addr: &str, public_ip: IpAddr) {
+        self.state
+            .lock()
+            .unwrap()
+            .nat
+            .insert(addr.to_string(), public_ip);
+    }
+
+    /// Removes a listener as if its process crashed.
+    pub fn crash(&self, addr: &str) {
+        self.state.lock().unwrap().listeners.remove(addr);
+    }
+
+    pub fn addrs(&self) -> Vec<String> {
+        let mut addrs: Vec<String> = self
+            .state
+            .lock()
+            .unwrap()
+            .listeners
+            .keys()
+            .cloned()
+            .collect();
+        addrs.sort();
+        addrs
+    }
+}
+
+#[derive(Clone)]
+pub struct MemoryTransport {
+    network: MemoryNetwork,
+    local_addr: Arc<Mutex<String>>,
+}
+
+impl Transport for MemoryTransport {
+    fn listen(&self, addr: &str) -> io::Result<Box<dyn TransportListener>> {
+        let mut state = self.network.state.lock().unwrap();
+        let bound_addr = match addr.strip_suffix(":0") {
+            Some(host) => {
+                let port = state.next_port;
+                state.next_port += 1;
+                format!("{}:{}", host, port)
+            }
+            None => addr.to_string(),
+        };
+        if state.listeners.contains_key(&bound_addr) {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, bound_addr));
+        }
+        let (sender, receiver) = mpsc::channel();
+        state.listeners.insert(bound_addr.clone(), sender);
+        *self.local_addr.lock().unwrap() = bound_addr.clone();
+        Ok(Box::new(MemoryListener {
+            addr: bound_addr,
+            receiver,
+        }))
+    }
+
+    fn request(&self, node_addr: &str, msg_data: &[u8]) -> Message {
+        self.exchange(node_addr, msg_data, None)
+    }
+
+    fn request_with_timeout(
+        &self,
+        node_addr: &str,
+        msg_data: &[u8],
+        reply_timeout: Duration,
+    ) -> Message {
+        self.exchange(node_addr, msg_data, Some(reply_timeout))
+    }
+
+    fn close(&self, addr: &str) {
+        self.network.crash(addr);
+    }
+
+    // NAT arkasındaysak bu adreslerden gelen bağlantılar geçer
+    fn punch(&self, node_addrs: &[String]) {
+        let from = self.local_addr.lock().unwrap().clone();
+        let mut state = self.network.state.lock().unwrap();
+        if !state.nat.contains_key(&from) {
+            return;
+        }
+        for node_addr in node_addrs.iter() {
+            state.nat_mappings.insert((from.clone(), node_addr.clone()));
+        }
+    }
+}
+
+impl MemoryTransport {
+    // reply_timeout verilmezse ağın set_reply_timeout ile ayarlanan süresi kullanılır
+    fn exchange(
+        &self,
+        node_addr: &str,
+        msg_data: &[u8],
+        reply_timeout: Option<Duration>,
+    ) -> Message {
+        let from = self.local_addr.lock().unwrap().clone();
+        let (sender, latency, reply_timeout, seen_from) = {
+            let mut state = self.network.state.lock().unwrap();
+            if state.faults.is_blocked(&from, node_addr) {
+                return error_message(ERR_CONNECT);
+            }
+            let Some(listen_addr) = state.route(&from, node_addr) else {
+                return error_message(ERR_CONNECT);
+            };
+            let sender = match state.listeners.get(&listen_addr) {
+                Some(sender) => sender.clone(),
+                None => return error_message(ERR_CONNECT),
+            };
+            if state.faults.is_lost() {
+                return error_message(ERR_CONNECT);
+            }
+            (
+                sender,
+                state.latency_between(&from, node_addr),
+                reply_timeout.unwrap_or(state.reply_timeout),
+                state.public_addr(&from),
+            )
+        };
+
+        if !latency.is_zero() {
+            thread::sleep(latency);
+        }
+        let (reply_sender, reply_receiver) = mpsc::channel();
+        let request = MemoryRequest {
+            from: seen_from,
+            data: msg_data.to_vec(),
+            reply: reply_sender,
+        };
+        if sender.send(request).is_err() {
+            return error_message(ERR_CONNECT);
+        }
+        match reply_receiver.recv_timeout(reply_timeout) {
+            Ok(reply) => {
+                if !latency.is_zero() {
+                    thread::sleep(latency);
+                }
+                reply.to_message_struct()
+            }
+            Err(_) => error_message(ERR_READ),
+        }
+    }
+}
+
+struct MemoryListener {
+    addr: String,
+    receiver: mpsc::Receiver<MemoryRequest>,
+}
+
+impl TransportListener for MemoryListener {
+    fn local_addr(&self) -> String {
+        self.addr.clone()
+    }
+
+    fn accept(&self) -> io::Result<Box<dyn TransportConnection>> {
+        match self.receiver.recv() {
+            Ok(request) => Ok(Box::new(MemoryConnection {
+                from: request.from,
+                data: Some(request.data),
+                reply: request.reply,
+            })),
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "memory listener closed",
+            )),
+        }
+    }
+}
+
+struct MemoryConnection {
+    from: String,
+    data: Option<Vec<u8>>,
+    reply: mpsc::Sender<Vec<u8>>,
+}
+
+impl TransportConnection for MemoryConnection {
+    fn peer_ip(&self) -> Option<IpAddr> {
+        self.from.parse::<SocketAddr>().ok().map(|addr| addr.ip())
+    }
+
+    fn set_timeout(&mut self, _timeout: Duration) {}
+
+    fn read_request(&mut self) -> io::Result<Vec<u8>> {
+        Ok(self.data.take().unwrap_or_default())
+    }
+
+    fn write_response(&mut self, data: &[u8]) -> io::Result<()> {
+        self.reply
+            .send(data.to_vec())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "requester gone"))
+    }
+}
@@ -0,0 +1,191 @@
+use std::{io, net::IpAddr, sync::Arc, thread, time::Duration};
+
+use crate::{
+    metrics::Metrics,
+    structs::{Message, MessageKind},
+};
+
+mod faults;
+mod memory;
+#[cfg(feature = "quic")]
+mod quic;
+mod tcp;
+mod udp;
+#[cfg(unix)]
+mod unix;
+
+pub(crate) use faults::Faults;
+pub use memory::{MemoryNetwork, MemoryTransport};
+#[cfg(feature = "quic")]
+pub use quic::QuicTransport;
+pub use tcp::TcpTransport;
+
+// request() hata kodları, Message.id içinde döner
+pub const ERR_CONNECT: u128 = 5;
+pub const ERR_WRITE: u128 = 8;
+pub const ERR_READ: u128 = 9;
+pub const ERR_EMPTY: u128 = 77;
+
+// tek bir istek veya cevap için okunacak en fazla byte, tüm transport'larda aynı
+pub(super) const MAX_REQUEST_BYTES: usize = 64 * 1024 * 1024;
+
+// bu önekle başlayan adresler aynı makinedeki Unix domain socket'lerdir, ör. unix:/run/p2p/a.sock
+pub const UNIX_PREFIX: &str = "unix:";
+
+pub fn unix_socket_path(addr: &str) -> Option<&str> {
+    addr.strip_prefix(UNIX_PREFIX)
+}
+
+// cevap karşı node'dan değil, bağlantının kendisinden gelen bir hata
+pub fn is_transport_error(result: &Message) -> bool {
+    result.kind == MessageKind::Error
+        && matches!(result.id, ERR_CONNECT | ERR_WRITE | ERR_READ | ERR_EMPTY)
+}
+
+/// Carries one request and its reply between two nodes.
+///
+/// Every message is a single request/response exchange, the same model the
+/// TCP code has always used: connect, write, read the `Ok` reply, close.
+pub trait Transport: Send + Sync {
+    /// Starts listening on `addr`. Port `0` asks for a free port.
+    fn listen(&self, addr: &str) -> io::Result<Box<dyn TransportListener>>;
+
+    /// Sends `msg_data` and waits for the reply. Failures come back as a
+    /// `MessageKind::Error` message whose `id` is one of the `ERR_*` codes.
+    fn request(&self, node_addr: &str, msg_data: &[u8]) -> Message;
+
+    /// Like `request`, but waits up to `reply_timeout` for the reply. Used
+    /// for requests the peer needs time to answer, such as DHT lookups
+    /// that carry large values.
+    fn request_with_timeout(
+        &self,
+        node_addr: &str,
+        msg_data: &[u8],
+        _reply_timeout: Duration,
+    ) -> Message {
+        self.request(node_addr, msg_data)
+    }
+
+    /// Sends a small request whose loss is cheap to repeat, such as a ping.
+    /// Transports with a datagram path use it when enabled, the rest fall
+    /// back to `request`.
+    fn request_datagram(&self, node_addr: &str, msg_data: &[u8]) -> Message {
+        self.request(node_addr, msg_data)
+    }
+
+    /// Unblocks a listener of ours that is waiting in `accept`.
+    fn close(&self, addr: &str);
+
+    /// Lets `request_datagram` use datagrams of up to `max_size` bytes,
+    /// `0` turns them off. Must be called before `listen`.
+    fn set_datagrams(&self, _max_size: usize) {}
+
+    /// Signs outgoing datagrams with `key` and drops incoming ones whose
+    /// signature does not match. An empty key sends them unsigned. Must be
+    /// called before `listen`.
+    fn set_datagram_key(&self, _key: Vec<u8>) {}
+
+    /// Starts receiving datagram requests on the port `listen` bound, if
+    /// datagrams are enabled. Its `accept` returns periodically with an
+    /// error so the caller can notice shutdown.
+    fn listen_datagram(&self, _addr: &str) -> io::Result<Option<Box<dyn TransportListener>>> {
+        Ok(None)
+    }
+
+    /// Makes outgoing connections leave from the listening port, so a NAT
+    /// in front of this node opens a mapping peers can dial back. Must be
+    /// called before `listen`. Transports without ports ignore it.
+    fn set_port_reuse(&self, _enabled: bool) {}
+
+    /// Sends an empty datagram from the listening port to each of
+    /// `node_addrs`, so a NAT in front of this node lets their datagrams
+    /// in. Transports without a datagram path ignore it.
+    fn punch(&self, _node_addrs: &[String]) {}
+
+    /// `true` if fire-and-forget sends should run on the caller's thread.
+    /// The simulator uses this to keep delivery order deterministic.
+    fn is_inline(&self) -> bool {
+        false
+    }
+}
+
+pub trait TransportListener: Send {
+    fn local_addr(&self) -> String;
+    fn accept(&self) -> io::Result<Box<dyn TransportConnection>>;
+}
+
+pub trait TransportConnection: Send {
+    fn peer_ip(&self) -> Option<IpAddr>;
+    fn set_timeout(&mut self, timeout: Duration);
+    fn read_request(&mut self) -> io::Result<Vec<u8>>;
+    fn write_response(&mut self, data: &[u8]) -> io::Result<()>;
+}
+
+// Config.transport değerine göre transport, "quic" sadece quic feature'ı ile derlenir
+pub fn from_name(name: &str) -> io::Result<Arc<dyn Transport>> {
+    match name {
+        "" | "tcp" => Ok(Arc::new(TcpTransport::new())),
+        #[cfg(feature = "quic")]
+        "quic" => Ok(Arc::new(QuicTransport::new()?)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("unknown transport {}", name),
+        )),
+    }
+}
+
+pub fn error_message(result_no: u128) -> Message {
+    Message {
+        id: result_no,
+        sender: String::new(),
+        kind: MessageKind::Error,
+        payload: Vec::new(),
+        cluster_token: String::new(),
+        payload_codec: String::new(),
+        handshake: None,
+        node_metadata: Vec::new(),
+    }
+}
+
+pub fn request_async(
+    transport: Arc<dyn Transport>,
+    node_addr: String,
+    msg_data: Vec<u8>,
+    metrics: Arc<Metrics>,
+) {
+    send_async(transport, node_addr, msg_data, metrics, false);
+}
+
+// request_async gibi, ama küçük state mesajları için datagram kullanılabilir
+pub fn request_datagram_async(
+    transport: Arc<dyn Transport>,
+    node_addr: String,
+    msg_data: Vec<u8>,
+    metrics: Arc<Metrics>,
+) {
+    send_async(transport, node_addr, msg_data, metrics, true);
+}
+
+fn send_async(
+    transport: Arc<dyn Transport>,
+    node_addr: String,
+    msg_data: Vec<u8>,
+    metrics: Arc<Metrics>,
+    datagram: bool,
+) {
+    let inline = transport.is_inline();
+    let send = move || {
+        let result = match datagram {
+            true => transport.request_datagram(&node_addr, &msg_data),
+            false => transport.request(&node_addr, &msg_data),
+        };
+        if result.id == ERR_CONNECT {
+            metrics.record_connect_failure();
+        }
+    };
+    if inline {
+        send();
+        return;
+    }
+    thread::spawn(send);
+}
@@ -0,0 +1,421 @@
+use quinn::{
+    crypto::rustls::{QuicClientConfig, QuicServerConfig},
+    ClientConfig, Connection, Endpoint, IdleTimeout, RecvStream, SendStream, ServerConfig,
+    TransportConfig,
+};
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{self, CryptoProvider},
+    pki_types::{CertificateDer, PrivatePkcs8KeyDer, ServerName, UnixTime},
+    DigitallySignedStruct, SignatureScheme,
+};
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    runtime::{Handle, Runtime},
+    sync::{OwnedSemaphorePermit, Semaphore},
+};
+
+use super::{
+    error_message, Transport, TransportConnection, TransportListener, ERR_CONNECT, ERR_READ,
+    ERR_WRITE, MAX_REQUEST_BYTES,
+};
+use crate::structs::{ConvertVecToStruct, Message};
+
+// sertifikadaki ve bağlanırken kullanılan sunucu adı
+const SERVER_NAME: &str = "litep2p";
+// ulaşılamayan bir adres ping döngüsünü uzun süre bekletmesin
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(1000);
+// bu süre boyunca hiç paket gelmeyen bağlantı kapanır
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+// ping aralığı uzun olsa da açık bağlantı kapanmasın
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(3);
+// cevabın her parçası için en az bu kadar, bağlantının ölçtüğü RTT'nin RTT_FACTOR katı beklenir
+const MIN_REPLY_TIMEOUT: Duration = Duration::from_millis(10);
+const RTT_FACTOR: u32 = 4;
+// bir peer'in aynı anda açabileceği stream sayısı
+const MAX_CONCURRENT_STREAMS: u32 = 32;
+// istekler admission'dan önce belleğe okunur, tüm bağlantılarda aynı anda en fazla bu kadarı tutulur
+const MAX_PENDING_REQUESTS: usize = 64;
+// isteğin tamamı bu süre içinde gelmezse stream bırakılır, TCP'deki job_timeout'un varsayılanı
+const REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sends every request as a stream on one QUIC connection per peer.
+///
+/// Streams do not block each other, and a peer whose address changes keeps
+/// its connection. Certificates are self-signed and not verified, peers are
+/// authenticated by the cluster token like with TCP.
+#[derive(Clone)]
+pub struct QuicTransport {
+    runtime: Arc<Runtime>,
+    endpoint: Arc<Mutex<Option<Endpoint>>>,
+    client_config: ClientConfig,
+    // adres -> açık bağlantı, istekler bu bağlantı üzerinde yeni stream açar
+    connections: Arc<Mutex<HashMap<String, Connection>>>,
+    // close() ile accept() üzerinde bekleyen dinleyiciyi uyandırmak için
+    accepted: Arc<Mutex<Option<mpsc::Sender<Option<QuicConnection>>>>>,
+}
+
+impl QuicTransport {
+    pub fn new() -> io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("quic")
+            .enable_all()
+            .build()?;
+        let provider = Arc::new(crypto::ring::default_provider());
+        let crypto = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .map_err(io::Error::other)?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AnyCertificate { provider }))
+            .with_no_client_auth();
+        let mut client_config = ClientConfig::new(Arc::new(
+            QuicClientConfig::try_from(crypto).map_err(io::Error::other)?,
+        ));
+        client_config.transport_config(transport_config());
+        Ok(QuicTransport {
+            runtime: Arc::new(runtime),
+            endpoint: Arc::new(Mutex::new(None)),
+            client_config,
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            accepted: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    // dinleyici varsa onun endpoint'i kullanılır, yoksa sadece istemci olarak açılır
+    fn endpoint(&self, socket_addr: &SocketAddr) -> io::Result<Endpoint> {
+        let mut endpoint = self.endpoint.lock().unwrap();
+        if let Some(endpoint) = endpoint.as_ref() {
+            return Ok(endpoint.clone());
+        }
+        let local_addr: SocketAddr = match socket_addr {
+            SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+            SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+        };
+        let _runtime_guard = self.runtime.enter();
+        let client_endpoint = Endpoint::client(local_addr)?;
+        *endpoint = Some(client_endpoint.clone());
+        Ok(client_endpoint)
+    }
+
+    fn connection(&self, node_addr: &str) -> Result<Connection, u128> {
+        if let Some(connection) = self.connections.lock().unwrap().get(node_addr) {
+            if connection.close_reason().is_none() {
+                return Ok(connection.clone());
+            }
+        }
+        let socket_addr = node_addr
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .ok_or(ERR_CONNECT)?;
+        let endpoint = self.endpoint(&socket_addr).map_err(|_| ERR_CONNECT)?;
+        // bağlantının sürücüsü runtime üzerinde başlatılır
+        let _runtime_guard = self.runtime.enter();
+        let connecting = endpoint
+            .connect_with(self.client_config.clone(), socket_addr, SERVER_NAME)
+            .map_err(|_| ERR_CONNECT)?;
+        let connection = self
+            .runtime
+            .block_on(async { tokio::time::timeout(CONNECT_TIMEOUT, connecting).await })
+            .map_err(|_| ERR_CONNECT)?
+            .map_err(|_| ERR_CONNECT)?;
+        self.connections
+            .lock()
+            .unwrap()
+            .insert(node_addr.to_string(), connection.clone());
+        Ok(connection)
+    }
+
+    // reply_timeout verilmezse bağlantının RTT'sine göre hesaplanır
+    fn send_request(
+        &self,
+        node_addr: &str,
+        msg_data: &[u8],
+        reply_timeout: Option<Duration>,
+    ) -> Message {
+        let connection = match self.connection(node_addr) {
+            Ok(connection) => connection,
+            Err(result_no) => return error_message(result_no),
+        };
+        let read_timeout =
+            reply_timeout.unwrap_or_else(|| rtt_reply_timeout(connection.rtt(), msg_data));
+        match self.exchange(&connection, msg_data, read_timeout) {
+            Ok(reply) if !reply.is_empty() => reply.to_message_struct(),
+            Ok(_) => error_message(super::ERR_EMPTY),
+            Err(result_no) => {
+                if connection.close_reason().is_some() {
+                    // node yeniden başlamış olabilir, bir sonraki istek yeni bağlantı açar
+                    self.connections.lock().unwrap().remove(node_addr);
+                }
+                error_message(result_no)
+            }
+        }
+    }
+
+    // TCP'deki gibi süre her okuma için ayrı işler, büyük cevaplar parça parça gelebilir
+    fn exchange(
+        &self,
+        connection: &Connection,
+        msg_data: &[u8],
+        read_timeout: Duration,
+    ) -> Result<Vec<u8>, u128> {
+        self.runtime.block_on(async {
+            let (mut send, mut recv) = connection.open_bi().await.map_err(|_| ERR_WRITE)?;
+            send.write_all(msg_data).await.map_err(|_| ERR_WRITE)?;
+            send.finish().map_err(|_| ERR_WRITE)?;
+            let mut reply = Vec::new();
+            loop {
+                match tokio::time::timeout(read_timeout, recv.read_chunk(usize::MAX, true)).await {
+                    Ok(Ok(Some(chunk))) => reply.extend_from_slice(&chunk.bytes),
+                    Ok(Ok(None)) => return Ok(reply),
+                    _ => return Err(ERR_READ),
+                }
+                if reply.len() > MAX_REQUEST_BYTES {
+                    return Err(ERR_READ);
+                }
+            }
+        })
+    }
+}
+
+impl Transport for QuicTransport {
+    fn listen(&self, addr: &str) -> io::Result<Box<dyn TransportListener>> {
+        let socket_addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::AddrNotAvailable))?;
+        let _runtime_guard = self.runtime.enter();
+        let endpoint = Endpoint::server(server_config()?, socket_addr)?;
+        let local_addr = endpoint.local_addr()?;
+        let (sender, receiver) = mpsc::channel();
+        *self.endpoint.lock().unwrap() = Some(endpoint.clone());
+        *self.accepted.lock().unwrap() = Some(sender.clone());
+        self.runtime.spawn(accept_connections(
+            endpoint,
+            sender,
+            self.runtime.handle().clone(),
+        ));
+        Ok(Box::new(QuicListener {
+            local_addr,
+            receiver,
+        }))
+    }
+
+    fn request(&self, node_addr: &str, msg_data: &[u8]) -> Message {
+        self.send_request(node_addr, msg_data, None)
+    }
+
+    fn request_with_timeout(
+        &self,
+        node_addr: &str,
+        msg_data: &[u8],
+        reply_timeout: Duration,
+    ) -> Message {
+        self.send_request(node_addr, msg_data, Some(reply_timeout))
+    }
+
+    fn close(&self, _addr: &str) {
+        if let Some(endpoint) = self.endpoint.lock().unwrap().take() {
+            endpoint.close(0u32.into(), b"shutdown");
+        }
+        self.connections.lock().unwrap().clear();
+        // accept() üzerinde bekleyen dinleyiciyi uyandır
+        if let Some(sender) = self.accepted.lock().unwrap().take() {
+            _ = sender.send(None);
+        }
+    }
+}
+
+// uzak node'larda RTT'ye göre uzar, büyük isteklerin işlenmesi de uzun sürer, her 1 KB için 1 ms eklenir
+fn rtt_reply_timeout(rtt: Duration, msg_data: &[u8]) -> Duration {
+    MIN_REPLY_TIMEOUT.max(rtt * RTT_FACTOR) + Duration::from_millis((msg_data.len() / 1024) as u64)
+}
+
+fn transport_config() -> Arc<TransportConfig> {
+    let mut transport_config = TransportConfig::default();
+    transport_config.max_idle_timeout(IdleTimeout::try_from(IDLE_TIMEOUT).ok());
+    transport_config.keep_alive_interval(Some(KEEP_ALIVE_INTERVAL));
+    transport_config.max_concurrent_bidi_streams(MAX_CONCURRENT_STREAMS.into());
+    transport_config.max_concurrent_uni_streams(0u32.into());
+    Arc::new(transport_config)
+}
+
+// her açılışta yeni bir self-signed sertifika üretilir
+fn server_config() -> io::Result<ServerConfig> {
+    let certified = rcgen::generate_simple_self_signed(vec![SERVER_NAME.to_string()])
+        .map_err(io::Error::other)?;
+    let key = PrivatePkcs8KeyDer::from(certified.signing_key.serialize_der());
+    let crypto =
+        rustls::ServerConfig::builder_with_provider(Arc::new(crypto::ring::default_provider()))
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .map_err(io::Error::other)?
+            .with_no_client_auth()
+            .with_single_cert(vec![certified.cert.der().clone()], key.into())
+            .map_err(io::Error::other)?;
+    let mut server_config = ServerConfig::with_crypto(Arc::new(
+        QuicServerConfig::try_from(crypto).map_err(io::Error::other)?,
+    ));
+    server_config.transport_config(transport_config());
+    Ok(server_config)
+}
+
+async fn accept_connections(
+    endpoint: Endpoint,
+    sender: mpsc::Sender<Option<QuicConnection>>,
+    handle: Handle,
+) {
+    let pending = Arc::new(Semaphore::new(MAX_PENDING_REQUESTS));
+    while let Some(incoming) = endpoint.accept().await {
+        let sender = sender.clone();
+        let handle = handle.clone();
+        let pending = pending.clone();
+        tokio::spawn(async move {
+            let Ok(connection) = incoming.await else {
+                return;
+            };
+            // her stream ayrı bir istek, biri diğerini bekletmez
+            while let Ok((send, recv)) = connection.accept_bi().await {
+                let sender = sender.clone();
+                let handle = handle.clone();
+                let peer_ip = connection.remote_address().ip();
+                let Ok(permit) = pending.clone().acquire_owned().await else {
+                    return;
+                };
+                tokio::spawn(async move {
+                    if let Some(accepted) = read_request(send, recv, peer_ip, handle, permit).await
+                    {
+                        _ = sender.send(Some(accepted));
+                    }
+                });
+            }
+        });
+    }
+}
+
+async fn read_request(
+    send: SendStream,
+    mut recv: RecvStream,
+    peer_ip: IpAddr,
+    handle: Handle,
+    permit: OwnedSemaphorePermit,
+) -> Option<QuicConnection> {
+    let request = tokio::time::timeout(REQUEST_READ_TIMEOUT, recv.read_to_end(MAX_REQUEST_BYTES))
+        .await
+        .ok()?
+        .ok()?;
+    Some(QuicConnection {
+        send,
+        request: Some(request),
+        peer_ip,
+        handle,
+        _permit: permit,
+    })
+}
+
+struct QuicListener {
+    local_addr: SocketAddr,
+    receiver: mpsc::Receiver<Option<QuicConnection>>,
+}
+
+impl TransportListener for QuicListener {
+    fn local_addr(&self) -> String {
+        self.local_addr.to_string()
+    }
+
+    fn accept(&self) -> io::Result<Box<dyn TransportConnection>> {
+        match self.receiver.recv() {
+            Ok(Some(connection)) => Ok(Box::new(connection)),
+            _ => Err(io::Error::from(io::ErrorKind::NotConnected)),
+        }
+    }
+}
+
+// okunmuş bir istek ve cevabın yazılacağı stream, worker thread'inde işlenir
+struct QuicConnection {
+    send: SendStream,
+    request: Option<Vec<u8>>,
+    peer_ip: IpAddr,
+    handle: Handle,
+    // cevap yazılıp bağlantı bırakılana kadar bekleyen istek sayısında sayılır
+    _permit: OwnedSemaphorePermit,
+}
+
+impl TransportConnection for QuicConnection {
+    fn peer_ip(&self) -> Option<IpAddr> {
+        Some(self.peer_ip)
+    }
+
+    fn set_timeout(&mut self, _timeout: Duration) {}
+
+    fn read_request(&mut self) -> io::Result<Vec<u8>> {
+        Ok(self.request.take().unwrap_or_default())
+    }
+
+    fn write_response(&mut self, data: &[u8]) -> io::Result<()> {
+        let send = &mut self.send;
+        self.handle.block_on(async {
+            send.write_all(data).await.map_err(io::Error::other)?;
+            send.finish().map_err(io::Error::other)
+        })
+    }
+}
+
+// self-signed sertifikalar doğrulanmaz, imza yine de kontrol edilir
+#[derive(Debug)]
+struct AnyCertificate {
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for AnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
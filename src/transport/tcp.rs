@@ -0,0 +1,324 @@
+use socket2::{Domain, Socket, Type};
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    net::{IpAddr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use super::unix;
+use super::{
+    error_message, is_transport_error, udp, unix_socket_path, Transport, TransportConnection,
+    TransportListener, ERR_CONNECT, ERR_EMPTY, ERR_READ, ERR_WRITE, MAX_REQUEST_BYTES,
+};
+use crate::structs::{ConvertVecToStruct, Message, MessageKind};
+
+// ulaşılamayan bir adres ping döngüsünü uzun süre bekletmesin
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(1000);
+// UDP ile cevap vermeyen node'a bu süre boyunca sadece TCP ile gidilir
+const TCP_ONLY_PERIOD: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Default)]
+pub struct TcpTransport {
+    // açıksa giden bağlantılar dinlenen porttan çıkar
+    port_reuse: Arc<AtomicBool>,
+    listen_addr: Arc<Mutex<Option<SocketAddr>>>,
+    // 0 değilse bu boyuta kadar olan request_datagram istekleri UDP ile gider
+    datagram_size: Arc<AtomicUsize>,
+    // UDP'ye cevap vermeyip TCP'ye cevap veren node'lar, örn. eski sürümler
+    tcp_only: Arc<Mutex<HashMap<String, Instant>>>,
+    // listen_datagram'ın açtığı soket, port paylaşımı açıkken UDP istekleri buradan çıkar
+    datagram_socket: Arc<Mutex<Option<udp::SharedSocket>>>,
+    // boş değilse UDP paketleri bu anahtarla imzalanır
+    datagram_key: Arc<Mutex<Arc<Vec<u8>>>>,
+}
+
+impl TcpTransport {
+    pub fn new() -> Self {
+        TcpTransport::default()
+    }
+
+    // port paylaşımı açıkken giden bağlantının bağlanacağı yerel adres
+    fn reuse_addr(&self) -> Option<SocketAddr> {
+        match self.port_reuse.load(Ordering::SeqCst) {
+            true => *self.listen_addr.lock().unwrap(),
+            false => None,
+        }
+    }
+
+    fn is_tcp_only(&self, node_addr: &str) -> bool {
+        let mut tcp_only = self.tcp_only.lock().unwrap();
+        match tcp_only.get(node_addr) {
+            Some(since) if since.elapsed() < TCP_ONLY_PERIOD => true,
+            Some(_) => {
+                tcp_only.remove(node_addr);
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+impl Transport for TcpTransport {
+    fn listen(&self, addr: &str) -> io::Result<Box<dyn TransportListener>> {
+        #[cfg(unix)]
+        if let Some(path) = unix_socket_path(addr) {
+            return Ok(Box::new(unix::listen(path)?));
+        }
+        let listener = match self.port_reuse.load(Ordering::SeqCst) {
+            true => reusable_listener(addr)?,
+            false => TcpListener::bind(addr)?,
+        };
+        *self.listen_addr.lock().unwrap() = listener.local_addr().ok();
+        Ok(Box::new(TcpTransportListener { listener }))
+    }
+
+    fn set_port_reuse(&self, enabled: bool) {
+        self.port_reuse.store(enabled, Ordering::SeqCst);
+    }
+
+    fn request(&self, node_addr: &str, msg_data: &[u8]) -> Message {
+        self.request_with_timeout(node_addr, msg_data, read_timeout(msg_data))
+    }
+
+    fn request_with_timeout(
+        &self,
+        node_addr: &str,
+        msg_data: &[u8],
+        reply_timeout: Duration,
+    ) -> Message {
+        #[cfg(unix)]
+        if let Some(path) = unix_socket_path(node_addr) {
+            return unix::request(path, msg_data, reply_timeout);
+        }
+        let stream = match self.reuse_addr() {
+            // aynı adrese açık bir bağlantı varsa port paylaşılamaz, normal bağlanılır
+            Some(local_addr) => connect_from(node_addr, local_addr).or_else(|_| connect(node_addr)),
+            None => connect(node_addr),
+        };
+        match stream {
+            Ok(mut stream) => {
+                _ = stream.set_read_timeout(Some(reply_timeout));
+                exchange(&mut stream, msg_data)
+            }
+            Err(_e) => error_message(ERR_CONNECT),
+        }
+    }
+
+    fn request_datagram(&self, node_addr: &str, msg_data: &[u8]) -> Message {
+        let max_size = self.datagram_size.load(Ordering::SeqCst);
+        if max_size == 0
+            || msg_data.len() > max_size
+            || unix_socket_path(node_addr).is_some()
+            || self.is_tcp_only(node_addr)
+        {
+            return self.request(node_addr, msg_data);
+        }
+        let shared_socket = match self.port_reuse.load(Ordering::SeqCst) {
+            true => self.datagram_socket.lock().unwrap().clone(),
+            false => None,
+        };
+        let reply = match &shared_socket {
+            Some(shared_socket) => udp::request_from(shared_socket, node_addr, msg_data),
+            None => {
+                let key = self.datagram_key.lock().unwrap().clone();
+                udp::request(node_addr, msg_data, &key)
+            }
+        };
+        match reply {
+            Ok(reply) => {
+                let reply = reply.to_message_struct();
+                if reply.kind == MessageKind::Error && reply.id == udp::ERR_TOO_LARGE {
+                    return self.request(node_addr, msg_data);
+                }
+                reply
+            }
+            Err(_) => {
+                let result = self.request(node_addr, msg_data);
+                if !is_transport_error(&result) {
+                    self.tcp_only
+                        .lock()
+                        .unwrap()
+                        .insert(node_addr.to_string(), Instant::now());
+                }
+                result
+            }
+        }
+    }
+
+    fn close(&self, addr: &str) {
+        #[cfg(unix)]
+        if let Some(path) = unix_socket_path(addr) {
+            unix::close(path);
+            return;
+        }
+        // accept() üzerinde bekleyen dinleyiciyi uyandır
+        _ = TcpStream::connect(addr);
+    }
+
+    fn set_datagrams(&self, max_size: usize) {
+        self.datagram_size.store(max_size, Ordering::SeqCst);
+    }
+
+    fn set_datagram_key(&self, key: Vec<u8>) {
+        *self.datagram_key.lock().unwrap() = Arc::new(key);
+    }
+
+    fn listen_datagram(&self, addr: &str) -> io::Result<Option<Box<dyn TransportListener>>> {
+        let max_size = self.datagram_size.load(Ordering::SeqCst);
+        if max_size == 0 || unix_socket_path(addr).is_some() {
+            return Ok(None);
+        }
+        let key = self.datagram_key.lock().unwrap().clone();
+        let listener = udp::listen(addr, max_size, key)?;
+        *self.datagram_socket.lock().unwrap() = Some(listener.shared());
+        Ok(Some(Box::new(listener)))
+    }
+
+    fn punch(&self, node_addrs: &[String]) {
+        if let Some(shared_socket) = self.datagram_socket.lock().unwrap().as_ref() {
+            udp::punch(shared_socket, node_addrs);
+        }
+    }
+}
+
+// büyük isteklerin işlenmesi de uzun sürer, her 1 KB için 1 ms eklenir
+pub(super) fn read_timeout(msg_data: &[u8]) -> Duration {
+    Duration::from_millis(10 + (msg_data.len() / 1024) as u64)
+}
+
+// isteği yazar, cevap JSON'u tamamlanana veya bağlantı kapanana kadar okunur
+pub(super) fn exchange(stream: &mut (impl Read + Write), msg_data: &[u8]) -> Message {
+    if stream.write_all(msg_data).is_err() {
+        return error_message(ERR_WRITE);
+    }
+    match read_request(stream) {
+        Ok(data) if data.is_empty() => error_message(ERR_EMPTY),
+        Ok(data) => data.to_message_struct(),
+        Err(_e) => error_message(ERR_READ),
+    }
+}
+
+fn connect(node_addr: &str) -> io::Result<TcpStream> {
+    let mut last_err = io::Error::from(io::ErrorKind::AddrNotAvailable);
+    for socket_addr in node_addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&socket_addr, CONNECT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = err,
+        }
+    }
+    Err(last_err)
+}
+
+fn reusable_socket(socket_addr: &SocketAddr) -> io::Result<Socket> {
+    let socket = Socket::new(Domain::for_address(*socket_addr), Type::STREAM, None)?;
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+    Ok(socket)
+}
+
+fn reusable_listener(addr: &str) -> io::Result<TcpListener> {
+    let mut last_err = io::Error::from(io::ErrorKind::AddrNotAvailable);
+    for socket_addr in addr.to_socket_addrs()? {
+        let socket = reusable_socket(&socket_addr)?;
+        match socket.bind(&socket_addr.into()) {
+            Ok(()) => {
+                socket.listen(128)?;
+                return Ok(socket.into());
+            }
+            Err(err) => last_err = err,
+        }
+    }
+    Err(last_err)
+}
+
+// NAT'ın bu bağlantı için açtığı eşleme dinlenen portu gösterir, karşı taraf bu porttan geri bağlanabilir
+fn connect_from(node_addr: &str, local_addr: SocketAddr) -> io::Result<TcpStream> {
+    let mut last_err = io::Error::from(io::ErrorKind::AddrNotAvailable);
+    for socket_addr in node_addr.to_socket_addrs()? {
+        if socket_addr.is_ipv4() != local_addr.is_ipv4() {
+            continue;
+        }
+        let socket = reusable_socket(&socket_addr)?;
+        // kapanışta TIME_WAIT kalmasın, aynı porttan tekrar bağlanılabilsin
+        socket.set_linger(Some(Duration::ZERO))?;
+        socket.bind(&local_addr.into())?;
+        match socket.connect_timeout(&socket_addr.into(), CONNECT_TIMEOUT) {
+            Ok(()) => return Ok(socket.into()),
+            Err(err) => last_err = err,
+        }
+    }
+    Err(last_err)
+}
+
+struct TcpTransportListener {
+    listener: TcpListener,
+}
+
+impl TransportListener for TcpTransportListener {
+    fn local_addr(&self) -> String {
+        match self.listener.local_addr() {
+            Ok(addr) => addr.to_string(),
+            Err(_) => String::new(),
+        }
+    }
+
+    fn accept(&self) -> io::Result<Box<dyn TransportConnection>> {
+        let (stream, _) = self.listener.accept()?;
+        Ok(Box::new(TcpConnection { stream }))
+    }
+}
+
+struct TcpConnection {
+    stream: TcpStream,
+}
+
+impl TransportConnection for TcpConnection {
+    fn peer_ip(&self) -> Option<IpAddr> {
+        self.stream.peer_addr().ok().map(|addr| addr.ip())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
+        _ = self.stream.set_read_timeout(Some(timeout));
+        _ = self.stream.set_write_timeout(Some(timeout));
+    }
+
+    fn read_request(&mut self) -> io::Result<Vec<u8>> {
+        read_request(&mut self.stream)
+    }
+
+    fn write_response(&mut self, data: &[u8]) -> io::Result<()> {
+        self.stream.write_all(data)
+    }
+}
+
+pub(super) fn read_request(stream: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut request = Vec::new();
+    let mut read_buf = [0u8; 4096];
+    loop {
+        let n = match stream.read(&mut read_buf) {
+            Ok(n) => n,
+            Err(err) if request.is_empty() => return Err(err),
+            Err(_) => return Ok(request),
+        };
+        if n == 0 {
+            return Ok(request);
+        }
+        request.extend_from_slice(&read_buf[0..n]);
+        // istek tek bir JSON nesnesi, tamamlandığında okuma biter
+        if request.ends_with(b"}")
+            && serde_json::from_slice::<serde::de::IgnoredAny>(&request).is_ok()
+        {
+            return Ok(request);
+        }
+        if request.len() > MAX_REQUEST_BYTES {
+            return Ok(request);
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+
+use super::{error_message, TransportConnection, TransportListener};
+use crate::structs::MessageKind;
+
+// bir UDP paketine sığabilecek en fazla veri
+const MAX_PAYLOAD_BYTES: usize = 65_507;
+// her denemede cevap için beklenen süre, cevap gelmezse istek tekrar gönderilir
+const RETRY_TIMEOUTS: [Duration; 3] = [
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(200),
+];
+// shutdown'da accept() en geç bu kadar sonra döner
+const ACCEPT_POLL: Duration = Duration::from_millis(200);
+// cevap paket sınırını aşıyor, istek TCP ile tekrarlanmalı
+pub const ERR_TOO_LARGE: u128 = 10;
+// imzalı paketlerin başındaki HMAC-MD5
+const MAC_LEN: usize = 16;
+const HMAC_BLOCK_LEN: usize = 64;
+
+// kaynak IP'si taklit edilebildiği için paketler cluster_secret'ten türetilen anahtarla imzalanır
+fn hmac_md5(key: &[u8], data: &[u8]) -> [u8; MAC_LEN] {
+    let mut block = [0u8; HMAC_BLOCK_LEN];
+    if key.len() > HMAC_BLOCK_LEN {
+        block[..MAC_LEN].copy_from_slice(&md5::compute(key).0);
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = md5::Context::new();
+    inner.consume(block.map(|byte| byte ^ 0x36));
+    inner.consume(data);
+    let mut outer = md5::Context::new();
+    outer.consume(block.map(|byte| byte ^ 0x5c));
+    outer.consume(inner.compute().0);
+    outer.compute().0
+}
+
+// anahtar boşsa paket olduğu gibi gönderilir
+fn seal(key: &[u8], data: &[u8]) -> Vec<u8> {
+    if key.is_empty() {
+        return data.to_vec();
+    }
+    let mut datagram = hmac_md5(key, data).to_vec();
+    datagram.extend_from_slice(data);
+    datagram
+}
+
+// imzası tutmayan paket None döner
+fn open(key: &[u8], datagram: &[u8]) -> Option<Vec<u8>> {
+    if key.is_empty() {
+        return Some(datagram.to_vec());
+    }
+    if datagram.len() < MAC_LEN {
+        return None;
+    }
+    let (mac, data) = datagram.split_at(MAC_LEN);
+    // karşılaştırma süresi ilk farklı byte'ı ele vermesin
+    let diff = hmac_md5(key, data)
+        .iter()
+        .zip(mac)
+        .fold(0u8, |diff, (expected, actual)| diff | (expected ^ actual));
+    match diff {
+        0 => Some(data.to_vec()),
+        _ => None,
+    }
+}
+
+// isteği gönderir ve cevabı bekler, cevap gelmezse aynı paket tekrar gönderilir;
+// aynı mesaj id'si tekrar işlenmediği için tekrar göndermek güvenlidir
+pub fn request(node_addr: &str, msg_data: &[u8], key: &[u8]) -> io::Result<Vec<u8>> {
+    let socket_addr = resolve(node_addr)?;
+    let local_addr: SocketAddr = match socket_addr {
+        SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+        SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+    };
+    let socket = UdpSocket::bind(local_addr)?;
+    socket.connect(socket_addr)?;
+    let datagram = seal(key, msg_data);
+    let mut reply = vec![0u8; MAX_PAYLOAD_BYTES];
+    let mut last_err = io::Error::from(io::ErrorKind::TimedOut);
+    for timeout in RETRY_TIMEOUTS {
+        socket.set_read_timeout(Some(timeout))?;
+        socket.send(&datagram)?;
+        match socket.recv(&mut reply) {
+            Ok(reply_size) => match open(key, &reply[..reply_size]) {
+                Some(reply) => return Ok(reply),
+                // imzası tutmayan cevap gelmemiş sayılır
+                None => last_err = io::Error::from(io::ErrorKind::InvalidData),
+            },
+            // karşı tarafta UDP dinleyen yok
+            Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => return Err(err),
+            Err(err) => last_err = err,
+        }
+    }
+    Err(last_err)
+}
+
+// request gibi, ama istek dinlenen soketten çıkar ve cevap dinleyici üzerinden gelir;
+// NAT'ın bu porta açtığı eşleme cevabın ve delik açılan peer'in isteklerinin gelmesini sağlar
+pub fn request_from(
+    shared: &SharedSocket,
+    node_addr: &str,
+    msg_data: &[u8],
+) -> io::Result<Vec<u8>> {
+    let socket_addr = resolve(node_addr)?;
+    let (sender, receiver) = mpsc::channel();
+    {
+        let mut pending = shared.pending.lock().unwrap();
+        // aynı peer'e giden başka bir istek cevap bekliyor, cevaplar karışmasın
+        if pending.contains_key(&socket_addr) {
+            drop(pending);
+            return request(node_addr, msg_data, &shared.key);
+        }
+        pending.insert(socket_addr, sender);
+    }
+    let datagram = seal(&shared.key, msg_data);
+    let mut result = Err(io::Error::from(io::ErrorKind::TimedOut));
+    for timeout in RETRY_TIMEOUTS {
+        if let Err(err) = shared.socket.send_to(&datagram, socket_addr) {
+            result = Err(err);
+            break;
+        }
+        if let Ok(reply) = receiver.recv_timeout(timeout) {
+            result = Ok(reply);
+            break;
+        }
+    }
+    shared.pending.lock().unwrap().remove(&socket_addr);
+    result
+}
+
+// dinlenen porttan boş paketler gönderir, önümüzdeki NAT bu adreslerden gelen paketleri geçirir
+pub fn punch(shared: &SharedSocket, node_addrs: &[String]) {
+    for node_addr in node_addrs.iter() {
+        if let Ok(socket_addr) = resolve(node_addr) {
+            _ = shared.socket.send_to(&[], socket_addr);
+        }
+    }
+}
+
+fn resolve(node_addr: &str) -> io::Result<SocketAddr> {
+    node_addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::from(io::ErrorKind::AddrNotAvailable))
+}
+
+pub fn listen(addr: &str, max_size: usize, key: Arc<Vec<u8>>) -> io::Result<DatagramListener> {
+    let socket = UdpSocket::bind(addr)?;
+    socket.set_read_timeout(Some(ACCEPT_POLL))?;
+    Ok(DatagramListener {
+        shared: SharedSocket {
+            socket: Arc::new(socket),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            key,
+        },
+        max_size,
+    })
+}
+
+// dinlenen soket ve bu soketten gönderilip cevabı beklenen istekler
+#[derive(Debug, Clone)]
+pub struct SharedSocket {
+    socket: Arc<UdpSocket>,
+    // peer adresi -> cevabın iletileceği kanal
+    pending: Arc<Mutex<HashMap<SocketAddr, mpsc::Sender<Vec<u8>>>>>,
+    // giden paketleri imzalayan, gelenleri doğrulayan anahtar
+    key: Arc<Vec<u8>>,
+}
+
+impl SharedSocket {
+    // bekleyen bir isteğin cevabıysa iletir
+    fn deliver_reply(&self, peer: SocketAddr, data: &[u8]) -> bool {
+        #[derive(Deserialize)]
+        struct Reply {
+            kind: MessageKind,
+        }
+        let is_reply = serde_json::from_slice::<Reply>(data)
+            .is_ok_and(|reply| matches!(reply.kind, MessageKind::Ok | MessageKind::Error));
+        if !is_reply {
+            return false;
+        }
+        match self.pending.lock().unwrap().get(&peer) {
+            Some(sender) => sender.send(data.to_vec()).is_ok(),
+            None => false,
+        }
+    }
+}
+
+pub struct DatagramListener {
+    shared: SharedSocket,
+    max_size: usize,
+}
+
+impl DatagramListener {
+    pub fn shared(&self) -> SharedSocket {
+        self.shared.clone()
+    }
+}
+
+impl TransportListener for DatagramListener {
+    fn local_addr(&self) -> String {
+        match self.shared.socket.local_addr() {
+            Ok(addr) => addr.to_string(),
+            Err(_) => String::new(),
+        }
+    }
+
+    fn accept(&self) -> io::Result<Box<dyn TransportConnection>> {
+        let mut datagram = vec![0u8; MAX_PAYLOAD_BYTES];
+        let (datagram_size, peer) = self.shared.socket.recv_from(&mut datagram)?;
+        // delik açmak için gönderilen boş paketler istek değildir, imzalanmazlar
+        if datagram_size == 0 {
+            return Err(io::Error::from(io::ErrorKind::WouldBlock));
+        }
+        // imzası tutmayan paket cevapsız bırakılır, gönderen TCP'ye geçer ve orada token'la reddedilir
+        let Some(request) = open(&self.shared.key, &datagram[..datagram_size]) else {
+            return Err(io::Error::from(io::ErrorKind::InvalidData));
+        };
+        // request_from'un cevapları istek değildir
+        if request.is_empty() || self.shared.deliver_reply(peer, &request) {
+            return Err(io::Error::from(io::ErrorKind::WouldBlock));
+        }
+        let request_size = request.len();
+        let mut connection = DatagramConnection {
+            socket: self.shared.socket.try_clone()?,
+            peer,
+            request: Some(request),
+            max_size: self.max_size,
+            key: self.shared.key.clone(),
+        };
+        if request_size > self.max_size {
+            _ = connection.write_response(&error_message(ERR_TOO_LARGE).to_byte_array());
+            return Err(io::Error::from(io::ErrorKind::InvalidData));
+        }
+        Ok(Box::new(connection))
+    }
+}
+
+// tek paketlik istek ve cevabı
+struct DatagramConnection {
+    socket: UdpSocket,
+    peer: SocketAddr,
+    request: Option<Vec<u8>>,
+    max_size: usize,
+    key: Arc<Vec<u8>>,
+}
+
+impl TransportConnection for DatagramConnection {
+    fn peer_ip(&self) -> Option<IpAddr> {
+        Some(self.peer.ip())
+    }
+
+    fn set_timeout(&mut self, _timeout: Duration) {}
+
+    fn read_request(&mut self) -> io::Result<Vec<u8>> {
+        Ok(self.request.take().unwrap_or_default())
+    }
+
+    fn write_response(&mut self, data: &[u8]) -> io::Result<()> {
+        if data.len() > self.max_size {
+            let too_large = error_message(ERR_TOO_LARGE).to_byte_array();
+            self.socket
+                .send_to(&seal(&self.key, &too_large), self.peer)?;
+            return Ok(());
+        }
+        self.socket.send_to(&seal(&self.key, data), self.peer)?;
+        Ok(())
+    }
+}
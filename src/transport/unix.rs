@@ -0,0 +1,102 @@
+use std::{
+    fs,
+    io::{self, Write},
+    net::IpAddr,
+    os::unix::{
+        fs::{FileTypeExt, MetadataExt},
+        net::{UnixListener, UnixStream},
+    },
+    time::Duration,
+};
+
+use super::{
+    error_message,
+    tcp::{exchange, read_request},
+    TransportConnection, TransportListener, ERR_CONNECT, UNIX_PREFIX,
+};
+use crate::structs::Message;
+
+pub fn request(path: &str, msg_data: &[u8], reply_timeout: Duration) -> Message {
+    match UnixStream::connect(path) {
+        Ok(mut stream) => {
+            _ = stream.set_read_timeout(Some(reply_timeout));
+            exchange(&mut stream, msg_data)
+        }
+        Err(_e) => error_message(ERR_CONNECT),
+    }
+}
+
+// kapanmadan kalmış soket dosyası silinir, dosyayı dinleyen bir node varsa adres kullanımdadır;
+// soket olmayan dosyalara dokunulmaz, bind hata verir
+pub fn listen(path: &str) -> io::Result<UnixSocketListener> {
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if metadata.file_type().is_socket() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, path.to_string()));
+            }
+            fs::remove_file(path)?;
+        }
+    }
+    let listener = UnixListener::bind(path)?;
+    let inode = fs::metadata(path)?.ino();
+    Ok(UnixSocketListener {
+        path: path.to_string(),
+        inode,
+        listener,
+    })
+}
+
+// accept() üzerinde bekleyen dinleyiciyi uyandırır, soket dosyası dinleyici drop edilince silinir
+pub fn close(path: &str) {
+    _ = UnixStream::connect(path);
+}
+
+pub struct UnixSocketListener {
+    path: String,
+    // yeniden başlayan node aynı yola yeni soket açmışsa o silinmez
+    inode: u64,
+    listener: UnixListener,
+}
+
+impl Drop for UnixSocketListener {
+    fn drop(&mut self) {
+        if fs::metadata(&self.path).is_ok_and(|metadata| metadata.ino() == self.inode) {
+            _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl TransportListener for UnixSocketListener {
+    fn local_addr(&self) -> String {
+        format!("{}{}", UNIX_PREFIX, self.path)
+    }
+
+    fn accept(&self) -> io::Result<Box<dyn TransportConnection>> {
+        let (stream, _) = self.listener.accept()?;
+        Ok(Box::new(UnixConnection { stream }))
+    }
+}
+
+struct UnixConnection {
+    stream: UnixStream,
+}
+
+impl TransportConnection for UnixConnection {
+    // aynı makinedeki node, IP listeleri uygulanmaz
+    fn peer_ip(&self) -> Option<IpAddr> {
+        None
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) {
+        _ = self.stream.set_read_timeout(Some(timeout));
+        _ = self.stream.set_write_timeout(Some(timeout));
+    }
+
+    fn read_request(&mut self) -> io::Result<Vec<u8>> {
+        read_request(&mut self.stream)
+    }
+
+    fn write_response(&mut self, data: &[u8]) -> io::Result<()> {
+        self.stream.write_all(data)
+    }
+}
@@ -1,84 +1,267 @@
 use std::{
-    sync::{mpsc, Arc, Mutex},
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
     thread,
+    time::{Duration, Instant},
 };
 
-use log::trace;
+use tracing::{error, trace};
 
 pub struct ThreadPool {
-    workers: Vec<Worker>,
-    sender: Option<mpsc::Sender<Job>>,
+    workers: Mutex<Vec<Worker>>,
+    retired: Mutex<Vec<Worker>>,
+    sender: Mutex<Option<mpsc::Sender<Job>>>,
+    receiver: Arc<Mutex<mpsc::Receiver<Job>>>,
+    stats: Arc<PoolCounters>,
+    job_timeout: Duration,
+    next_id: AtomicUsize,
 }
 
 type Job = Box<dyn FnOnce() + Send + 'static>;
 
+#[derive(Default)]
+struct PoolCounters {
+    queued: AtomicUsize,
+    busy: AtomicUsize,
+    completed: AtomicUsize,
+    panicked: AtomicUsize,
+    timed_out: AtomicUsize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    pub size: usize,
+    pub busy: usize,
+    pub queued: usize,
+    pub completed: usize,
+    pub panicked: usize,
+    pub timed_out: usize,
+}
+
 impl ThreadPool {
     /// Create a new ThreadPool.
     ///
-    /// The size is the number of threads in the pool.
+    /// The size is the number of threads in the pool, `job_timeout` is how
+    /// long a single job may hold a worker before it is replaced.
     ///
     /// # Panics
     ///
     /// The `new` function will panic if the size is zero.
-    pub fn new(size: usize) -> ThreadPool {
+    pub fn new(size: usize, job_timeout: Duration) -> ThreadPool {
         assert!(size > 0);
         let (sender, receiver) = mpsc::channel();
 
-        let receiver = Arc::new(Mutex::new(receiver));
+        let pool = ThreadPool {
+            workers: Mutex::new(Vec::with_capacity(size)),
+            retired: Mutex::new(Vec::new()),
+            sender: Mutex::new(Some(sender)),
+            receiver: Arc::new(Mutex::new(receiver)),
+            stats: Arc::new(PoolCounters::default()),
+            job_timeout,
+            next_id: AtomicUsize::new(0),
+        };
+        pool.resize(size);
+        pool
+    }
 
-        let mut workers = Vec::with_capacity(size);
+    /// Queues a job, returns `false` if the pool is already shut down.
+    pub fn execute<F>(&self, f: F) -> bool
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.replace_timed_out_workers();
 
-        for id in 0..size {
-            workers.push(Worker::new(id, Arc::clone(&receiver)));
+        let job = Box::new(f);
+        match self.sender.lock().unwrap().as_ref() {
+            Some(sender) => {
+                self.stats.queued.fetch_add(1, Ordering::SeqCst);
+                if sender.send(job).is_err() {
+                    self.stats.queued.fetch_sub(1, Ordering::SeqCst);
+                    error!("worker pool disconnected");
+                    return false;
+                }
+                true
+            }
+            None => false,
         }
+    }
 
-        ThreadPool {
-            workers,
-            sender: Some(sender),
+    /// Grows or shrinks the pool. Retired workers finish their current job first.
+    pub fn resize(&self, size: usize) {
+        assert!(size > 0);
+        let mut workers = self.workers.lock().unwrap();
+        while workers.len() < size {
+            let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+            workers.push(Worker::new(
+                id,
+                Arc::clone(&self.receiver),
+                Arc::clone(&self.stats),
+            ));
+        }
+        while workers.len() > size {
+            if let Some(worker) = workers.pop() {
+                worker.retire();
+                self.retired.lock().unwrap().push(worker);
+            }
         }
+        drop(workers);
+        self.join_finished_retired();
     }
 
-    pub fn execute<F>(&self, f: F)
-    where
-        F: FnOnce() + Send + 'static,
-    {
-        let job = Box::new(f);
+    pub fn size(&self) -> usize {
+        self.workers.lock().unwrap().len()
+    }
 
-        self.sender.as_ref().unwrap().send(job).unwrap();
+    pub fn stats(&self) -> PoolStats {
+        self.replace_timed_out_workers();
+        PoolStats {
+            size: self.size(),
+            busy: self.stats.busy.load(Ordering::SeqCst),
+            queued: self.stats.queued.load(Ordering::SeqCst),
+            completed: self.stats.completed.load(Ordering::SeqCst),
+            panicked: self.stats.panicked.load(Ordering::SeqCst),
+            timed_out: self.stats.timed_out.load(Ordering::SeqCst),
+        }
     }
-}
 
-impl Drop for ThreadPool {
-    fn drop(&mut self) {
-        drop(self.sender.take());
+    /// Stops accepting jobs, lets queued jobs finish and joins the workers,
+    /// including retired ones. A job that is past `job_timeout` gets one more
+    /// `job_timeout` to finish before its thread is left behind.
+    pub fn shutdown(&self) {
+        drop(self.sender.lock().unwrap().take());
+        let deadline = Instant::now() + self.job_timeout;
+
+        let mut workers: Vec<Worker> = self.workers.lock().unwrap().drain(..).collect();
+        workers.append(&mut self.retired.lock().unwrap());
+        for mut worker in workers {
+            trace!(worker = worker.id, "shutting down worker");
+            let Some(thread) = worker.thread.take() else {
+                continue;
+            };
+            loop {
+                if thread.is_finished() {
+                    _ = thread.join();
+                    break;
+                }
+                if worker.is_timed_out(self.job_timeout) {
+                    error!(worker = worker.id, "worker job timed out");
+                    worker.retire();
+                    self.stats.timed_out.fetch_add(1, Ordering::SeqCst);
+                }
+                if worker.is_overrunning(self.job_timeout) && Instant::now() > deadline {
+                    error!(worker = worker.id, "worker job still running; not joined");
+                    break;
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+        }
+    }
 
-        for worker in &mut self.workers {
-            //println!("Shutting down worker {}", worker.id);
-            if let Some(thread) = worker.thread.take() {
-                thread.join().unwrap();
+    // süresi dolan işi tutan worker bırakılır, yerine yenisi açılır
+    fn replace_timed_out_workers(&self) {
+        let mut workers = self.workers.lock().unwrap();
+        let mut replaced = 0;
+        let mut index = 0;
+        while index < workers.len() {
+            if !workers[index].is_timed_out(self.job_timeout) {
+                index += 1;
+                continue;
             }
+            let worker = workers.remove(index);
+            error!(worker = worker.id, "worker job timed out");
+            worker.retire();
+            self.stats.timed_out.fetch_add(1, Ordering::SeqCst);
+            self.retired.lock().unwrap().push(worker);
+            replaced += 1;
+        }
+        drop(workers);
+        self.join_finished_retired();
+        if replaced == 0 || self.sender.lock().unwrap().is_none() {
+            return;
+        }
+        let mut workers = self.workers.lock().unwrap();
+        for _ in 0..replaced {
+            let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+            workers.push(Worker::new(
+                id,
+                Arc::clone(&self.receiver),
+                Arc::clone(&self.stats),
+            ));
         }
     }
+
+    // işini bitirip çıkan emekli worker'lar beklenir, liste büyümez
+    fn join_finished_retired(&self) {
+        self.retired.lock().unwrap().retain_mut(|worker| {
+            match worker.thread.take_if(|thread| thread.is_finished()) {
+                Some(thread) => {
+                    _ = thread.join();
+                    false
+                }
+                None => true,
+            }
+        });
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
 }
 
-#[allow(dead_code)]
 struct Worker {
     id: usize,
     thread: Option<thread::JoinHandle<()>>,
+    retired: Arc<AtomicBool>,
+    busy_since: Arc<Mutex<Option<Instant>>>,
 }
 
 impl Worker {
-    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Worker {
+    fn new(
+        id: usize,
+        receiver: Arc<Mutex<mpsc::Receiver<Job>>>,
+        stats: Arc<PoolCounters>,
+    ) -> Worker {
+        let retired = Arc::new(AtomicBool::new(false));
+        let busy_since = Arc::new(Mutex::new(None));
+        let retired_cloned = retired.clone();
+        let busy_since_cloned = busy_since.clone();
         let thread = thread::spawn(move || loop {
-            let message = receiver.lock().unwrap().recv();
+            if retired_cloned.load(Ordering::SeqCst) {
+                trace!(worker = id, "worker retired; shutting down");
+                break;
+            }
+
+            // job panic olsa bile kilit zehirlenmez, kaldığı yerden devam eder
+            let message = receiver
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .recv_timeout(Duration::from_millis(100));
 
             match message {
                 Ok(job) => {
-                    trace!("Worker {id} got a job; executing.");
-                    job();
+                    trace!(worker = id, "worker got a job; executing");
+                    stats.queued.fetch_sub(1, Ordering::SeqCst);
+                    stats.busy.fetch_add(1, Ordering::SeqCst);
+                    *busy_since_cloned.lock().unwrap() = Some(Instant::now());
+
+                    if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                        error!(worker = id, "worker job panicked; restarting");
+                        stats.panicked.fetch_add(1, Ordering::SeqCst);
+                    } else {
+                        stats.completed.fetch_add(1, Ordering::SeqCst);
+                    }
+
+                    *busy_since_cloned.lock().unwrap() = None;
+                    stats.busy.fetch_sub(1, Ordering::SeqCst);
                 }
-                Err(_) => {
-                    trace!("Worker {id} disconnected; shutting down.");
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    trace!(worker = id, "worker disconnected; shutting down");
                     break;
                 }
             }
@@ -87,6 +270,161 @@ impl Worker {
         Worker {
             id,
             thread: Some(thread),
+            retired,
+            busy_since,
+        }
+    }
+
+    fn retire(&self) {
+        self.retired.store(true, Ordering::SeqCst);
+    }
+
+    fn is_retired(&self) -> bool {
+        self.retired.load(Ordering::SeqCst)
+    }
+
+    fn is_timed_out(&self, job_timeout: Duration) -> bool {
+        !self.is_retired() && self.is_overrunning(job_timeout)
+    }
+
+    fn is_overrunning(&self, job_timeout: Duration) -> bool {
+        if job_timeout.is_zero() {
+            return false;
         }
+        match *self.busy_since.lock().unwrap() {
+            Some(started) => started.elapsed() > job_timeout,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_for(pool: &ThreadPool, cond: impl Fn(&PoolStats) -> bool) -> PoolStats {
+        let started = Instant::now();
+        loop {
+            let stats = pool.stats();
+            if cond(&stats) || started.elapsed() > Duration::from_secs(5) {
+                return stats;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    // kanal kapanana veya bir değer gelene kadar worker'ı meşgul eden iş
+    fn blocking_job(pool: &ThreadPool) -> mpsc::Sender<()> {
+        let (release, wait) = mpsc::channel::<()>();
+        pool.execute(move || {
+            _ = wait.recv();
+        });
+        release
+    }
+
+    #[test]
+    fn a_panicking_job_does_not_take_the_worker_down() {
+        let pool = ThreadPool::new(1, Duration::ZERO);
+        pool.execute(|| panic!("job failed"));
+        let (done, finished) = mpsc::channel();
+        pool.execute(move || done.send(()).unwrap());
+
+        assert!(finished.recv_timeout(Duration::from_secs(5)).is_ok());
+        let stats = wait_for(&pool, |stats| stats.completed == 1);
+        assert_eq!(stats.panicked, 1);
+        assert_eq!(stats.completed, 1);
+        assert_eq!(stats.size, 1);
+    }
+
+    #[test]
+    fn timed_out_workers_are_replaced_without_new_work() {
+        let pool = ThreadPool::new(1, Duration::from_millis(50));
+        let release = blocking_job(&pool);
+        wait_for(&pool, |stats| stats.busy == 1);
+        thread::sleep(Duration::from_millis(100));
+
+        // yeni iş gelmeden stats() süresi dolan worker'ı değiştirir
+        let stats = pool.stats();
+        assert_eq!(stats.timed_out, 1);
+        assert_eq!(stats.size, 1);
+
+        let (done, finished) = mpsc::channel();
+        pool.execute(move || done.send(()).unwrap());
+        assert!(finished.recv_timeout(Duration::from_secs(5)).is_ok());
+
+        // takılan iş bittiyse shutdown onun thread'ini de bekler
+        release.send(()).unwrap();
+        pool.shutdown();
+        assert_eq!(pool.stats().completed, 2);
+        assert_eq!(pool.stats().busy, 0);
+    }
+
+    #[test]
+    fn shutdown_does_not_wait_forever_for_a_stuck_job() {
+        let pool = ThreadPool::new(1, Duration::from_millis(50));
+        let release = blocking_job(&pool);
+        wait_for(&pool, |stats| stats.busy == 1);
+
+        let started = Instant::now();
+        pool.shutdown();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(pool.stats().timed_out, 1);
+        drop(release);
+    }
+
+    #[test]
+    fn resize_grows_and_shrinks_and_retired_workers_are_joined() {
+        let pool = ThreadPool::new(2, Duration::ZERO);
+        pool.resize(4);
+        assert_eq!(pool.size(), 4);
+
+        let releases: Vec<_> = (0..4).map(|_| blocking_job(&pool)).collect();
+        wait_for(&pool, |stats| stats.busy == 4);
+        pool.resize(1);
+        assert_eq!(pool.size(), 1);
+        assert_eq!(pool.stats().size, 1);
+
+        // emekli worker'lar ellerindeki işi bitirir, shutdown hepsini bekler
+        for release in releases {
+            release.send(()).unwrap();
+        }
+        let (done, finished) = mpsc::channel();
+        pool.execute(move || done.send(()).unwrap());
+        assert!(finished.recv_timeout(Duration::from_secs(5)).is_ok());
+        pool.shutdown();
+        assert_eq!(pool.stats().completed, 5);
+        assert_eq!(pool.stats().busy, 0);
+    }
+
+    #[test]
+    fn stats_count_busy_queued_and_completed_jobs() {
+        let pool = ThreadPool::new(2, Duration::ZERO);
+        assert_eq!(
+            pool.stats(),
+            PoolStats {
+                size: 2,
+                ..PoolStats::default()
+            }
+        );
+
+        let releases: Vec<_> = (0..3).map(|_| blocking_job(&pool)).collect();
+        let stats = wait_for(&pool, |stats| stats.busy == 2);
+        assert_eq!(stats.busy, 2);
+        assert_eq!(stats.queued, 1);
+
+        for release in releases {
+            release.send(()).unwrap();
+        }
+        let stats = wait_for(&pool, |stats| stats.completed == 3);
+        assert_eq!(
+            stats,
+            PoolStats {
+                size: 2,
+                completed: 3,
+                ..PoolStats::default()
+            }
+        );
+        pool.shutdown();
+        assert!(!pool.execute(|| {}));
     }
 }
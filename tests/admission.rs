@@ -0,0 +1,81 @@
+mod common;
+
+use goxoy_litep2p::{Config, EventType, MessagePool, NodeStatus};
+use std::{
+    io::{Read, Write},
+    net::{Shutdown, TcpStream},
+    thread,
+    time::Duration,
+};
+
+fn secret_config(node_id: &str) -> Config {
+    Config {
+        cluster_id: "test".to_string(),
+        cluster_secret: "secret".to_string(),
+        ..common::config(node_id, &[])
+    }
+}
+
+// ham byte'ları gönderip cevabı döner
+fn send_raw(addr: &str, data: &[u8]) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    stream.write_all(data).unwrap();
+    stream.shutdown(Shutdown::Write).unwrap();
+    let mut reply = Vec::new();
+    _ = stream.read_to_end(&mut reply);
+    String::from_utf8(reply).unwrap()
+}
+
+#[test]
+fn unreadable_requests_are_rejected_and_not_queued() {
+    let mut nodes = common::start_cluster(vec![secret_config("adm-a"), secret_config("adm-b")]);
+    assert!(common::pump_until(&mut nodes, Duration::from_secs(10), common::all_online));
+    let addr = nodes[0].local_addr();
+
+    // çözülemeyen veri Error mesajı olur, token'ı olmadığı için reddedilir
+    let reply = send_raw(&addr, b"{\"not\":\"a message\"}");
+    assert!(reply.contains("\"id\":13"), "reply: {}", reply);
+    // geçerli token ile gelen Ok türü istek cevaplanır ama kuyruğa girmez
+    let ok_request = format!(
+        "{{\"id\":42,\"sender\":\"{}\",\"kind\":\"Ok\",\"payload\":[],\"cluster_token\":\"{:x}\"}}",
+        nodes[1].local_addr(),
+        md5::compute("test:secret")
+    );
+    let reply = send_raw(&addr, ok_request.as_bytes());
+    assert!(reply.contains("\"kind\":\"Ok\""), "reply: {}", reply);
+
+    // kuyruk tıkanmadıysa sonraki mesaj okunur
+    nodes[1].send_to(addr.clone(), b"after garbage".to_vec());
+    let mut received = None;
+    for _ in 0..300 {
+        if let EventType::OnMessage(msg) = nodes[0].on_event() {
+            received = Some(msg.payload);
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(received, Some(b"after garbage".to_vec()));
+}
+
+#[test]
+fn nodes_of_another_cluster_are_not_admitted() {
+    let mut other = MessagePool::new();
+    assert!(other.start_with_config(Config {
+        cluster_secret: "other".to_string(),
+        ..secret_config("adm-other")
+    }));
+    let mut nodes = common::start_cluster(vec![secret_config("adm-c"), secret_config("adm-d")]);
+    nodes[1].add_node_to_list(other.local_addr());
+    assert!(common::pump_until(
+        &mut nodes,
+        Duration::from_secs(10),
+        |nodes| common::all_online(nodes)
+            && nodes.iter().all(|node| node.peer(&other.local_addr()).is_none())
+    ));
+    for node in nodes.iter() {
+        assert_eq!(node.peers_by_status(NodeStatus::Online).len(), 1);
+    }
+}
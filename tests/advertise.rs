@@ -0,0 +1,41 @@
+mod common;
+
+use goxoy_litep2p::MessagePool;
+use std::time::Duration;
+
+#[test]
+fn peers_dial_the_advertised_addr_and_record_the_observed_one() {
+    // tüm arayüzleri dinler, kendini 127.0.0.2 olarak bildirir; bağlantıları 127.0.0.1'den gelir
+    let mut conf = common::config("adv-hidden", &[]);
+    conf.bind_addr = "0.0.0.0:0".to_string();
+    conf.advertise_addr = vec!["127.0.0.2:0".to_string()];
+    let mut hidden = MessagePool::new();
+    assert!(hidden.start_with_config(conf));
+    let port = hidden.listen_addr().rsplit(':').next().unwrap().to_string();
+    assert_eq!(hidden.listen_addr(), format!("0.0.0.0:{port}"));
+    let advertised = format!("127.0.0.2:{port}");
+    assert_eq!(hidden.local_addr(), advertised);
+
+    let mut peer = MessagePool::new();
+    assert!(peer.start_with_config(common::config(
+        "adv-peer",
+        std::slice::from_ref(&advertised)
+    )));
+    let mut nodes = vec![hidden, peer];
+    let observed = format!("127.0.0.1:{port}");
+    let seen = common::pump_until(&mut nodes, Duration::from_secs(10), |nodes| {
+        common::all_online(nodes)
+            && nodes[1]
+                .peer(&advertised)
+                .is_some_and(|info| info.observed_addr == observed)
+    });
+    assert!(seen, "peer never recorded the observed address");
+
+    let info = nodes[1].peer(&advertised).unwrap();
+    assert_eq!(info.addr, advertised);
+    assert_eq!(info.advertised_addrs, std::slice::from_ref(&advertised));
+    // bildirilen adres cevap verdiği için başka adres denenmedi
+    assert_eq!(info.reach_addr, "");
+    // node listesinde dinlenen veya görülen adres ayrı bir node olarak yer almaz
+    assert_eq!(nodes[1].peers().len(), 1);
+}
@@ -0,0 +1,60 @@
+#![allow(dead_code)]
+
+use goxoy_litep2p::{Config, MessagePool, NodeStatus};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+// diske yazmayan, işletim sisteminin seçtiği portu dinleyen node ayarı
+pub fn config(node_id: &str, bootstrap: &[String]) -> Config {
+    Config {
+        debug: false,
+        store_node_list: false,
+        addr: "127.0.0.1:0".to_string(),
+        node_id: node_id.to_string(),
+        bootstrap: bootstrap.to_vec(),
+        ..Config::default()
+    }
+}
+
+// ilk node'un adresi diğerlerinin bootstrap'i olur
+pub fn start_cluster(configs: Vec<Config>) -> Vec<MessagePool> {
+    let mut nodes: Vec<MessagePool> = Vec::new();
+    for mut conf in configs {
+        if let Some(first) = nodes.first() {
+            conf.bootstrap.push(first.local_addr());
+        }
+        let mut node = MessagePool::new();
+        assert!(node.start_with_config(conf), "node could not be started");
+        nodes.push(node);
+    }
+    nodes
+}
+
+// koşul sağlanana veya süre dolana kadar node'ların olaylarını işler
+pub fn pump_until(
+    nodes: &mut [MessagePool],
+    timeout: Duration,
+    condition: impl Fn(&[MessagePool]) -> bool,
+) -> bool {
+    let started = Instant::now();
+    while started.elapsed() < timeout {
+        for node in nodes.iter_mut() {
+            for _ in 0..32 {
+                node.on_event();
+            }
+        }
+        if condition(nodes) {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+pub fn all_online(nodes: &[MessagePool]) -> bool {
+    nodes
+        .iter()
+        .all(|node| node.peers_by_status(NodeStatus::Online).len() == nodes.len() - 1)
+}
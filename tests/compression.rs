@@ -0,0 +1,108 @@
+mod common;
+
+use goxoy_litep2p::{
+    EventType, Handshake, Message, MessageKind, MessagePool, NodeStatus, TcpTransport, Transport,
+};
+use std::{
+    collections::BTreeMap,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+// kodlama bildirmeyen eski bir node gibi cevap verir, gelen Distribute mesajlarını olduğu gibi iletir
+fn old_peer() -> (String, mpsc::Receiver<Message>) {
+    let transport = TcpTransport::new();
+    let listener = transport.listen("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr();
+    let (sender, receiver) = mpsc::channel();
+    let reply_sender = addr.clone();
+    thread::spawn(move || loop {
+        let Ok(mut connection) = listener.accept() else {
+            return;
+        };
+        let Ok(request) = connection.read_request() else {
+            continue;
+        };
+        let Ok(msg) = serde_json::from_slice::<Message>(&request) else {
+            continue;
+        };
+        let mut handshake = Handshake::local("old-peer".to_string(), BTreeMap::new());
+        handshake.codecs = Vec::new();
+        let reply = Message {
+            id: 0,
+            sender: reply_sender.clone(),
+            kind: MessageKind::Ok,
+            payload: Vec::new(),
+            cluster_token: String::new(),
+            payload_codec: String::new(),
+            handshake: Some(Box::new(handshake)),
+            node_metadata: Vec::new(),
+        };
+        _ = connection.write_response(&reply.to_byte_array());
+        if msg.kind == MessageKind::Distribute && sender.send(msg).is_err() {
+            return;
+        }
+    });
+    (addr, receiver)
+}
+
+// kolay sıkışan, eşik değerinden çok büyük payload
+fn compressible(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 7) as u8).collect()
+}
+
+#[test]
+fn peers_without_lz4_get_uncompressed_payloads() {
+    let (old_addr, distributed) = old_peer();
+    let mut nodes = vec![MessagePool::new()];
+    assert!(nodes[0].start_with_config(common::config("lz4-new", &[])));
+    nodes[0].add_node_to_list(old_addr.clone());
+    let online = common::pump_until(&mut nodes, Duration::from_secs(10), |nodes| {
+        nodes[0]
+            .peer(&old_addr)
+            .is_some_and(|peer| peer.status == NodeStatus::Online)
+    });
+    assert!(online, "old peer never came online");
+    assert!(nodes[0].peer(&old_addr).unwrap().codecs.is_empty());
+
+    let payload = compressible(64 * 1024);
+    nodes[0].distribute(payload.clone());
+    let msg = distributed
+        .recv_timeout(Duration::from_secs(5))
+        .expect("old peer got nothing");
+    assert_eq!(msg.payload_codec, "");
+    assert_eq!(msg.payload, payload);
+}
+
+#[test]
+fn large_payloads_to_lz4_peers_are_sent_compressed() {
+    let mut nodes = common::start_cluster(vec![
+        common::config("lz4-sender", &[]),
+        common::config("lz4-receiver", &[]),
+    ]);
+    assert!(common::pump_until(
+        &mut nodes,
+        Duration::from_secs(10),
+        common::all_online
+    ));
+    let receiver_addr = nodes[1].local_addr();
+    let receiver_codecs = nodes[0].peer(&receiver_addr).unwrap().codecs;
+    assert_eq!(receiver_codecs, ["lz4"]);
+
+    let payload = compressible(256 * 1024);
+    let sent_before = nodes[0].metrics().bytes_out;
+    nodes[0].distribute(payload.clone());
+    let started = Instant::now();
+    let mut received = None;
+    while received.is_none() && started.elapsed() < Duration::from_secs(5) {
+        match nodes[1].on_event() {
+            EventType::OnMessage(msg) => received = Some(msg.payload),
+            _ => thread::sleep(Duration::from_millis(1)),
+        }
+    }
+    assert_eq!(received, Some(payload.clone()));
+    // sayı dizisi olarak yazılan sıkıştırılmamış payload en az kendi boyu kadar tutardı
+    let sent = nodes[0].metrics().bytes_out - sent_before;
+    assert!(sent < payload.len() as u64 / 10, "{sent} bytes sent");
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use goxoy_litep2p::{EventType, Message, MessageKind, MessagePool, TcpTransport, Transport};
+use std::{thread, time::Duration};
+
+fn distribute(id: u128, sender: &str, payload: &[u8]) -> Message {
+    Message {
+        id,
+        sender: sender.to_string(),
+        kind: MessageKind::Distribute,
+        payload: payload.to_vec(),
+        cluster_token: String::new(),
+        payload_codec: String::new(),
+        handshake: None,
+        node_metadata: Vec::new(),
+    }
+}
+
+fn online_pair(dedup_window: usize) -> Vec<MessagePool> {
+    let mut configs = vec![
+        common::config("dedup-receiver", &[]),
+        common::config("dedup-sender", &[]),
+    ];
+    configs[0].dedup_window = dedup_window;
+    let mut nodes = common::start_cluster(configs);
+    assert!(common::pump_until(
+        &mut nodes,
+        Duration::from_secs(10),
+        common::all_online
+    ));
+    nodes
+}
+
+// gelen mesajların payload'ları, gelişi sırasıyla
+fn received_payloads(node: &mut MessagePool) -> Vec<Vec<u8>> {
+    let mut payloads = Vec::new();
+    for _ in 0..100 {
+        if let EventType::OnMessage(msg) = node.on_event() {
+            payloads.push(msg.payload);
+        }
+        thread::sleep(Duration::from_millis(2));
+    }
+    payloads
+}
+
+#[test]
+fn a_repeated_message_id_is_delivered_once() {
+    let mut nodes = online_pair(16);
+    let (addr, sender) = (nodes[0].local_addr(), nodes[1].local_addr());
+    let transport = TcpTransport::new();
+    let msg = distribute(0x1234, &sender, b"once");
+
+    // ikinci kopya da Ok alır, gönderen tekrar denemez
+    for _ in 0..2 {
+        let reply = transport.request(&addr, &msg.to_byte_array());
+        assert_eq!(reply.kind, MessageKind::Ok);
+    }
+    assert_eq!(received_payloads(&mut nodes[0]), vec![b"once".to_vec()]);
+    assert_eq!(nodes[0].metrics().duplicates, 1);
+}
+
+#[test]
+fn ids_that_left_the_window_are_delivered_again() {
+    let mut nodes = online_pair(2);
+    let (addr, sender) = (nodes[0].local_addr(), nodes[1].local_addr());
+    let transport = TcpTransport::new();
+
+    // pencere 2 id tutar: 1 hâlâ içindeyken tekrarı düşer, 2 ve 3'ten sonra unutulur;
+    // cevap mesaj kuyruğa girdikten sonra döner, sıra korunur
+    for (id, payload) in [
+        (1, "first"),
+        (1, "first"),
+        (2, "second"),
+        (3, "third"),
+        (1, "first"),
+    ] {
+        let msg = distribute(id, &sender, payload.as_bytes());
+        let reply = transport.request(&addr, &msg.to_byte_array());
+        assert_eq!(reply.kind, MessageKind::Ok);
+    }
+    let payloads: Vec<String> = received_payloads(&mut nodes[0])
+        .into_iter()
+        .map(|payload| String::from_utf8(payload).unwrap())
+        .collect();
+    assert_eq!(payloads, ["first", "second", "third", "first"]);
+    assert_eq!(nodes[0].metrics().duplicates, 1);
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use goxoy_litep2p::{Config, MessagePool};
+use std::time::Duration;
+
+fn dht_config(node_id: &str) -> Config {
+    Config {
+        dht: true,
+        ..common::config(node_id, &[])
+    }
+}
+
+fn start_dht_cluster() -> Vec<MessagePool> {
+    let mut nodes = common::start_cluster(vec![
+        dht_config("dht-a"),
+        dht_config("dht-b"),
+        dht_config("dht-c"),
+    ]);
+    assert!(common::pump_until(
+        &mut nodes,
+        Duration::from_secs(10),
+        common::all_online
+    ));
+    nodes
+}
+
+#[test]
+fn large_values_round_trip_over_tcp() {
+    // değer tek node'da saklanır, diğer node'lar onu ağdan okumak zorunda kalır
+    let mut nodes = common::start_cluster(
+        ["dht-a", "dht-b", "dht-c"]
+            .iter()
+            .map(|node_id| Config {
+                dht_replication: 1,
+                ..dht_config(node_id)
+            })
+            .collect(),
+    );
+    assert!(common::pump_until(
+        &mut nodes,
+        Duration::from_secs(10),
+        common::all_online
+    ));
+    // 64 KiB sınırının hemen altı, cevap birçok TCP okumasına yayılır
+    let value: Vec<u8> = (0..60 * 1024).map(|i| (i % 251) as u8).collect();
+    assert_eq!(nodes[0].dht_put("large", value.clone()), 1);
+    for node in nodes.iter() {
+        assert_eq!(node.dht_get("large").as_ref(), Some(&value));
+    }
+    assert!(nodes[1].find_node("dht-c").is_some());
+}
+
+#[test]
+fn values_above_the_limit_are_rejected() {
+    let mut nodes = start_dht_cluster();
+    assert_eq!(nodes[0].dht_put("too-large", vec![7u8; 64 * 1024 + 1]), 0);
+    assert_eq!(nodes[1].dht_get("too-large"), None);
+}
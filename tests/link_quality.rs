@@ -0,0 +1,75 @@
+use goxoy_litep2p::LinkQuality;
+
+#[test]
+fn the_first_sample_sets_every_rtt_value() {
+    let mut quality = LinkQuality::default();
+    quality.record_success(8_000);
+    assert_eq!(quality.rtt_last_us, 8_000);
+    assert_eq!(quality.rtt_ewma_us, 8_000);
+    assert_eq!(quality.rtt_min_us, 8_000);
+    assert_eq!(quality.rtt_jitter_us, 0);
+    assert_eq!(quality.success_permille, 1000);
+    assert_eq!(quality.score(), 8_000);
+}
+
+#[test]
+fn rtt_values_start_from_the_first_success_after_failures() {
+    let mut quality = LinkQuality::default();
+    quality.record_failure();
+    assert_eq!(quality.score(), u64::MAX);
+    quality.record_success(8_000);
+    assert_eq!(quality.rtt_ewma_us, 8_000);
+    assert_eq!(quality.rtt_min_us, 8_000);
+    assert_eq!(quality.rtt_jitter_us, 0);
+    // başarısız ping oranı düşürmeye devam eder
+    assert_eq!(quality.success_permille, 125);
+    assert_eq!((quality.ping_count, quality.failure_count), (2, 1));
+    assert_eq!(quality.score(), 8_000 + 875 * 1000);
+}
+
+#[test]
+fn the_average_follows_samples_by_one_eighth() {
+    let mut quality = LinkQuality::default();
+    quality.record_success(8_000);
+    quality.record_success(16_000);
+    assert_eq!(quality.rtt_ewma_us, 9_000);
+    quality.record_success(1_000);
+    assert_eq!(quality.rtt_ewma_us, (9_000 * 7 + 1_000) / 8);
+    assert_eq!(quality.rtt_last_us, 1_000);
+}
+
+#[test]
+fn the_minimum_only_goes_down() {
+    let mut quality = LinkQuality::default();
+    for rtt_us in [5_000, 3_000, 9_000, 4_000] {
+        quality.record_success(rtt_us);
+    }
+    assert_eq!(quality.rtt_min_us, 3_000);
+}
+
+#[test]
+fn jitter_follows_the_difference_to_the_last_sample() {
+    let mut quality = LinkQuality::default();
+    quality.record_success(10_000);
+    quality.record_success(26_000);
+    assert_eq!(quality.rtt_jitter_us, 16_000 / 16);
+    quality.record_success(10_000);
+    assert_eq!(quality.rtt_jitter_us, (1_000 * 15 + 16_000) / 16);
+    // aynı süre tekrar gelirse jitter azalır
+    quality.record_success(10_000);
+    assert_eq!(quality.rtt_jitter_us, (1_937 * 15) / 16);
+}
+
+#[test]
+fn failures_lower_the_success_ratio_and_the_score() {
+    let mut healthy = LinkQuality::default();
+    let mut lossy = LinkQuality::default();
+    for _ in 0..4 {
+        healthy.record_success(20_000);
+        lossy.record_success(5_000);
+        lossy.record_failure();
+    }
+    assert_eq!(healthy.success_ratio(), 1.0);
+    assert!(lossy.success_ratio() < 0.7);
+    assert!(healthy.score() < lossy.score());
+}
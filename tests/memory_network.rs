@@ -0,0 +1,49 @@
+mod common;
+
+use goxoy_litep2p::{MemoryNetwork, MessagePool, NodeStatus};
+use std::{sync::Arc, time::Duration};
+
+fn peer_status(node: &MessagePool, peer_addr: &str) -> Option<NodeStatus> {
+    node.peers()
+        .into_iter()
+        .find(|peer| peer.addr.eq(peer_addr))
+        .map(|peer| peer.status)
+}
+
+#[test]
+fn partitioned_node_goes_offline_and_comes_back_after_healing() {
+    let network = MemoryNetwork::new(9);
+    let mut nodes: Vec<MessagePool> = Vec::new();
+    for name in ["part-a", "part-b", "part-c"] {
+        let mut conf = common::config(name, &[]);
+        conf.addr = "10.0.0.1:0".to_string();
+        if let Some(first) = nodes.first() {
+            conf.bootstrap.push(first.local_addr());
+        }
+        let mut node = MessagePool::with_transport(Arc::new(network.transport()));
+        assert!(node.start_with_config(conf));
+        nodes.push(node);
+    }
+    assert!(common::pump_until(
+        &mut nodes,
+        Duration::from_secs(10),
+        common::all_online
+    ));
+
+    let addr_c = nodes[2].local_addr();
+    network.partition(
+        &[nodes[0].local_addr(), nodes[1].local_addr()],
+        std::slice::from_ref(&addr_c),
+    );
+    let split = common::pump_until(&mut nodes, Duration::from_secs(10), |nodes| {
+        peer_status(&nodes[0], &addr_c) == Some(NodeStatus::Offline)
+            && peer_status(&nodes[1], &addr_c) == Some(NodeStatus::Offline)
+    });
+    assert!(split, "partitioned node is still online");
+
+    network.heal();
+    assert!(
+        common::pump_until(&mut nodes, Duration::from_secs(20), common::all_online),
+        "nodes did not come back after healing"
+    );
+}
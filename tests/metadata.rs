@@ -0,0 +1,63 @@
+mod common;
+
+use goxoy_litep2p::EventType;
+use std::{
+    collections::BTreeMap,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn large_metadata_reaches_peers() {
+    // ping cevabı birkaç KB'a çıkar, tek okumaya sığmaz
+    let blob: String = (0..3000).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+    let mut big = common::config("meta-a", &[]);
+    big.metadata = BTreeMap::from([("blob".to_string(), blob.clone())]);
+    let mut nodes = common::start_cluster(vec![big, common::config("meta-b", &[])]);
+
+    let synced = common::pump_until(&mut nodes, Duration::from_secs(10), |nodes| {
+        common::all_online(nodes)
+            && nodes[1]
+                .peers()
+                .iter()
+                .any(|peer| peer.metadata.get("blob") == Some(&blob))
+    });
+    assert!(synced, "peer with large metadata never came online");
+}
+
+#[test]
+fn tagged_messages_reach_only_matching_peers() {
+    let mut configs = vec![common::config("tag-sender", &[])];
+    for (name, role) in [
+        ("tag-indexer-1", "indexer"),
+        ("tag-indexer-2", "indexer"),
+        ("tag-storage", "storage"),
+    ] {
+        let mut conf = common::config(name, &[]);
+        conf.metadata = BTreeMap::from([("role".to_string(), role.to_string())]);
+        configs.push(conf);
+    }
+    let mut nodes = common::start_cluster(configs);
+    let synced = common::pump_until(&mut nodes, Duration::from_secs(10), |nodes| {
+        common::all_online(nodes)
+            && nodes[0]
+                .peers()
+                .iter()
+                .all(|peer| peer.metadata.contains_key("role"))
+    });
+    assert!(synced, "metadata never reached the sender");
+
+    nodes[0].distribute_to_tagged("role", "indexer", b"for indexers".to_vec());
+    let mut received = vec![false; nodes.len()];
+    let started = Instant::now();
+    // eşleşmeyen node'a yanlışlıkla giden mesaj da gelebilsin diye süre sonuna kadar beklenir
+    while started.elapsed() < Duration::from_secs(2) {
+        for (index, node) in nodes.iter_mut().enumerate() {
+            if let EventType::OnMessage(msg) = node.on_event() {
+                received[index] |= msg.payload == b"for indexers";
+            }
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(received, [false, true, true, false]);
+}
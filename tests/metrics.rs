@@ -0,0 +1,37 @@
+mod common;
+
+use goxoy_litep2p::MessagePool;
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+#[test]
+fn shutdown_stops_the_metrics_endpoint() {
+    // işletim sisteminin verdiği boş bir port metrics_addr olarak kullanılır
+    let metrics_addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let mut conf = common::config("metrics", &[]);
+    conf.metrics_addr = metrics_addr.to_string();
+    let mut node = MessagePool::new();
+    assert!(node.start_with_config(conf));
+
+    let mut client = TcpStream::connect(metrics_addr).unwrap();
+    client.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").unwrap();
+    let mut response = String::new();
+    client.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+
+    // hiçbir şey göndermeyen bağlantı kapanışı engellememeli
+    let _idle = TcpStream::connect(metrics_addr).unwrap();
+    thread::sleep(Duration::from_millis(200));
+    node.shutdown();
+    assert!(
+        TcpListener::bind(metrics_addr).is_ok(),
+        "metrics listener is still open after shutdown"
+    );
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use goxoy_litep2p::{
+    MemoryNetwork, Message, MessageKind, MessagePool, NodeStatus, Reachability, TcpTransport,
+    Transport,
+};
+use std::{
+    net::{IpAddr, UdpSocket},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+// addr'ler sabit verilir, NAT'lar node'lar ping atmaya başlamadan kurulur
+fn start_nodes(network: &MemoryNetwork, addrs: &[&str]) -> Vec<MessagePool> {
+    let mut nodes = Vec::new();
+    for (index, addr) in addrs.iter().enumerate() {
+        let mut conf = common::config(&format!("nat-{}", index), &[]);
+        conf.addr = addr.to_string();
+        conf.nat_traversal = true;
+        if index > 0 {
+            conf.bootstrap.push(addrs[0].to_string());
+        }
+        let mut node = MessagePool::with_transport(Arc::new(network.transport()));
+        assert!(node.start_with_config(conf), "node could not be started");
+        nodes.push(node);
+    }
+    nodes
+}
+
+fn ip(value: &str) -> IpAddr {
+    value.parse().unwrap()
+}
+
+// ping'lerin cevabı kısa sürede gelir, başka node'lara bağlanarak cevap veren NAT istekleri gelmez
+fn slow_network(seed: u64) -> MemoryNetwork {
+    let network = MemoryNetwork::new(seed);
+    network.set_latency(Duration::from_millis(10));
+    network.set_reply_timeout(Duration::from_millis(15));
+    network
+}
+
+#[test]
+fn nodes_without_nat_are_public() {
+    let network = slow_network(1);
+    let mut nodes = start_nodes(&network, &["10.0.0.1:20000", "10.0.0.2:20000"]);
+
+    let checked = common::pump_until(&mut nodes, Duration::from_secs(10), |nodes| {
+        nodes
+            .iter()
+            .all(|node| node.reachability() == Reachability::Public)
+    });
+    assert!(checked, "reachability was not measured as public");
+}
+
+#[test]
+fn nodes_behind_nat_are_private_but_online() {
+    let network = slow_network(2);
+    network.set_nat("10.0.1.1:20001", ip("198.51.100.1"));
+    let mut nodes = start_nodes(&network, &["10.0.0.1:20000", "10.0.1.1:20001"]);
+
+    let checked = common::pump_until(&mut nodes, Duration::from_secs(10), |nodes| {
+        nodes[1].reachability() == Reachability::Private
+            && nodes[0].reachability() == Reachability::Public
+    });
+    assert!(checked, "reachability behind NAT was not measured");
+    assert_eq!(
+        nodes[1].public_addr().as_deref(),
+        Some("198.51.100.1:20001")
+    );
+    let online = common::pump_until(&mut nodes, Duration::from_secs(5), |nodes| {
+        nodes[0]
+            .peers()
+            .iter()
+            .any(|peer| peer.addr.eq("10.0.1.1:20001") && peer.status == NodeStatus::Online)
+    });
+    assert!(online, "private node is not online for the public node");
+}
+
+#[test]
+fn nodes_behind_different_nats_reach_each_other_after_punching() {
+    let network = slow_network(3);
+    network.set_nat("10.0.1.1:20001", ip("198.51.100.1"));
+    network.set_nat("10.0.2.1:20002", ip("198.51.100.2"));
+    let mut nodes = start_nodes(
+        &network,
+        &["10.0.0.1:20000", "10.0.1.1:20001", "10.0.2.1:20002"],
+    );
+
+    let punched = common::pump_until(&mut nodes, Duration::from_secs(20), |nodes| {
+        nodes[1].peers().iter().any(|peer| {
+            peer.addr.eq("10.0.2.1:20002")
+                && peer.status == NodeStatus::Online
+                && peer.reach_addr.eq("198.51.100.2:20002")
+        })
+    });
+    assert!(punched, "nodes behind NAT did not reach each other");
+}
+
+fn message(sender: &str, kind: MessageKind) -> Message {
+    Message {
+        id: 1,
+        sender: sender.to_string(),
+        kind,
+        payload: Vec::new(),
+        cluster_token: String::new(),
+        payload_codec: String::new(),
+        handshake: None,
+        node_metadata: Vec::new(),
+    }
+}
+
+#[test]
+fn datagrams_leave_from_the_listening_port_when_punching() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    server
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    let server_addr = server.local_addr().unwrap().to_string();
+
+    let client = TcpTransport::new();
+    client.set_port_reuse(true);
+    client.set_datagrams(1200);
+    let client_addr = client.listen("127.0.0.1:0").unwrap().local_addr();
+    let client_datagrams = client.listen_datagram(&client_addr).unwrap().unwrap();
+    // cevaplar dinleyici üzerinden isteği bekleyene iletilir
+    thread::spawn(move || loop {
+        _ = client_datagrams.accept();
+    });
+
+    client.punch(std::slice::from_ref(&server_addr));
+    let mut buf = [0u8; 2048];
+    let (size, from) = server.recv_from(&mut buf).unwrap();
+    assert_eq!((size, from.to_string()), (0, client_addr.clone()));
+
+    let reply = message(&server_addr, MessageKind::Ok).to_byte_array();
+    let server_thread = thread::spawn(move || {
+        let (_, from) = server.recv_from(&mut buf).unwrap();
+        server.send_to(&reply, from).unwrap();
+        from.to_string()
+    });
+    let ping = message(&client_addr, MessageKind::State).to_byte_array();
+    let result = client.request_datagram(&server_addr, &ping);
+    assert_eq!(server_thread.join().unwrap(), client_addr);
+    assert_eq!(result.kind, MessageKind::Ok);
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use goxoy_litep2p::{EventType, MessagePool, NodeStatus};
+use std::{
+    fs, thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn restarted_nodes_keep_their_id_and_move_to_the_new_port() {
+    // storage_dir boş, dosyalar çalışma klasörüne yazılır; bu dosyadaki tek test klasörü değiştirir
+    let work_dir = std::env::temp_dir().join(format!("litep2p-node-id-{}", std::process::id()));
+    fs::create_dir_all(&work_dir).unwrap();
+    std::env::set_current_dir(&work_dir).unwrap();
+    let moving_config = |bootstrap: &[String]| {
+        let mut conf = common::config("", bootstrap);
+        conf.store_node_list = true;
+        conf
+    };
+
+    let mut moving = MessagePool::new();
+    assert!(moving.start_with_config(moving_config(&[])));
+    let (node_id, old_addr) = (moving.node_id(), moving.local_addr());
+    assert!(!node_id.is_empty());
+    let mut nodes = vec![moving];
+    nodes.push(MessagePool::new());
+    assert!(nodes[1].start_with_config(common::config("stays", std::slice::from_ref(&old_addr))));
+    assert!(common::pump_until(
+        &mut nodes,
+        Duration::from_secs(10),
+        common::all_online
+    ));
+
+    // aynı storage_dir ile farklı bir portta yeniden başlar
+    let mut stays = nodes.pop().unwrap();
+    nodes.pop().unwrap().shutdown();
+    let mut moved = MessagePool::new();
+    assert!(moved.start_with_config(moving_config(&[stays.local_addr()])));
+    let new_addr = moved.local_addr();
+    assert_ne!(new_addr, old_addr);
+    assert_eq!(moved.node_id(), node_id);
+
+    let mut address_changed = false;
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(10) {
+        if let EventType::OnNodeAddressChanged(id, from, to) = stays.on_event() {
+            assert_eq!(
+                (id.as_str(), from.as_str()),
+                (node_id.as_str(), old_addr.as_str())
+            );
+            address_changed = to.eq(&new_addr);
+        }
+        moved.on_event();
+        let peers = stays.peers();
+        if address_changed
+            && peers.iter().all(|peer| !peer.addr.eq(&old_addr))
+            && stays
+                .peer(&new_addr)
+                .is_some_and(|peer| peer.status == NodeStatus::Online)
+        {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(address_changed, "moved node was not merged");
+    let peers = stays.peers();
+    assert_eq!(
+        peers.iter().filter(|peer| peer.node_id == node_id).count(),
+        1,
+        "duplicate entries: {peers:?}"
+    );
+    assert!(peers.iter().all(|peer| !peer.addr.eq(&old_addr)));
+    assert_eq!(
+        stays.peer_by_id(&node_id).map(|peer| peer.addr),
+        Some(new_addr)
+    );
+
+    moved.shutdown();
+    _ = fs::remove_dir_all(&work_dir);
+}
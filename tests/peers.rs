@@ -0,0 +1,91 @@
+mod common;
+
+use goxoy_litep2p::{MemoryNetwork, MessagePool, NodeStatus, PeerInfo};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+fn addrs(peers: &[PeerInfo]) -> Vec<String> {
+    let mut addrs: Vec<String> = peers.iter().map(|peer| peer.addr.clone()).collect();
+    addrs.sort();
+    addrs
+}
+
+#[test]
+fn accessors_report_the_cluster_as_seen_by_a_node() {
+    let network = MemoryNetwork::new(3);
+    let mut nodes: Vec<MessagePool> = Vec::new();
+    for (name, role) in [
+        ("peers-a", ""),
+        ("peers-near", "indexer"),
+        ("peers-far", "storage"),
+        ("peers-gone", "indexer"),
+    ] {
+        let mut conf = common::config(name, &[]);
+        conf.addr = "10.0.0.1:0".to_string();
+        if !role.is_empty() {
+            conf.metadata = BTreeMap::from([("role".to_string(), role.to_string())]);
+        }
+        if let Some(first) = nodes.first() {
+            conf.bootstrap.push(first.local_addr());
+        }
+        let mut node = MessagePool::with_transport(Arc::new(network.transport()));
+        assert!(node.start_with_config(conf));
+        nodes.push(node);
+    }
+    let addr: Vec<String> = nodes.iter().map(|node| node.local_addr()).collect();
+    network.set_link_latency(&addr[0], &addr[2], Duration::from_millis(20));
+    network.set_link_latency(&addr[2], &addr[0], Duration::from_millis(20));
+
+    // gecikme ölçülene kadar birkaç ping beklenir
+    let measured = common::pump_until(&mut nodes, Duration::from_secs(20), |nodes| {
+        common::all_online(nodes)
+            && nodes[0]
+                .peers()
+                .iter()
+                .all(|peer| peer.link_quality.ping_count >= 3 && peer.metadata.contains_key("role"))
+    });
+    assert!(measured, "cluster never came online");
+
+    nodes[3].shutdown();
+    let gone = common::pump_until(&mut nodes[..3], Duration::from_secs(10), |nodes| {
+        nodes[0]
+            .peer(&addr[3])
+            .is_some_and(|peer| peer.status == NodeStatus::Offline)
+    });
+    assert!(gone, "stopped node never went offline");
+
+    let observer = &nodes[0];
+    // kendisi listede yer almaz
+    let mut others = addr[1..].to_vec();
+    others.sort();
+    assert_eq!(addrs(&observer.peers()), others);
+    assert_eq!(observer.peer(&addr[1]).unwrap().node_id, "peers-near");
+    assert!(observer.peer("10.0.0.9:1").is_none());
+
+    assert_eq!(observer.peer_by_id("peers-far").unwrap().addr, addr[2]);
+    assert!(observer.peer_by_id("peers-nobody").is_none());
+    assert!(observer.peer_by_id("").is_none());
+
+    let mut online = vec![addr[1].clone(), addr[2].clone()];
+    online.sort();
+    assert_eq!(addrs(&observer.peers_by_status(NodeStatus::Online)), online);
+    assert_eq!(
+        addrs(&observer.peers_by_status(NodeStatus::Offline)),
+        [addr[3].clone()]
+    );
+
+    // offline olan indexer da metadata ile bulunur
+    let mut indexers = vec![addr[1].clone(), addr[3].clone()];
+    indexers.sort();
+    assert_eq!(
+        addrs(&observer.peers_with_metadata("role", "indexer")),
+        indexers
+    );
+    assert!(observer.peers_with_metadata("role", "cache").is_empty());
+
+    // en yakın online node gecikmesiz olan, offline node hiç seçilmez
+    assert_eq!(observer.nearest_peer().unwrap().addr, addr[1]);
+
+    let local = observer.local_node().unwrap();
+    assert_eq!(local.addr, addr[0]);
+    assert_eq!(local.node_id, "peers-a");
+}
@@ -0,0 +1,76 @@
+#![cfg(feature = "quic")]
+
+mod common;
+
+use goxoy_litep2p::{Message, MessageKind, MessagePool, QuicTransport, Transport};
+use std::{thread, time::Duration};
+
+fn message(sender: &str, kind: MessageKind, payload: Vec<u8>) -> Message {
+    Message {
+        id: 1,
+        sender: sender.to_string(),
+        kind,
+        payload,
+        cluster_token: String::new(),
+        payload_codec: String::new(),
+        handshake: None,
+        node_metadata: Vec::new(),
+    }
+}
+
+// dinleyici tek bir isteği okur, beklettikten sonra verilen cevabı yazar
+fn serve_once(transport: &QuicTransport, reply_payload: Vec<u8>, delay: Duration) -> String {
+    let listener = transport.listen("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr();
+    let reply = message(&addr, MessageKind::Ok, reply_payload).to_byte_array();
+    thread::spawn(move || {
+        let mut connection = listener.accept().unwrap();
+        connection.read_request().unwrap();
+        thread::sleep(delay);
+        connection.write_response(&reply).unwrap();
+        // cevap gönderilene kadar dinleyici ve stream açık kalsın
+        thread::sleep(Duration::from_secs(1));
+    });
+    addr
+}
+
+#[test]
+fn large_replies_arrive_over_loopback() {
+    let server = QuicTransport::new().unwrap();
+    let addr = serve_once(&server, vec![7u8; 1024 * 1024], Duration::ZERO);
+    let client = QuicTransport::new().unwrap();
+    let request = message("127.0.0.1:1", MessageKind::State, Vec::new());
+    let reply = client.request(&addr, &request.to_byte_array());
+    assert_eq!(reply.kind, MessageKind::Ok);
+    assert_eq!(reply.payload.len(), 1024 * 1024);
+}
+
+#[test]
+fn explicit_reply_timeouts_wait_for_slow_peers() {
+    let server = QuicTransport::new().unwrap();
+    let addr = serve_once(&server, b"late".to_vec(), Duration::from_millis(200));
+    let client = QuicTransport::new().unwrap();
+    let request = message("127.0.0.1:1", MessageKind::State, Vec::new());
+    let reply =
+        client.request_with_timeout(&addr, &request.to_byte_array(), Duration::from_secs(2));
+    assert_eq!(reply.kind, MessageKind::Ok);
+    assert_eq!(reply.payload, b"late");
+}
+
+#[test]
+fn nodes_come_online_over_quic() {
+    let configs = ["quic-a", "quic-b", "quic-c"]
+        .iter()
+        .map(|name| {
+            let mut conf = common::config(name, &[]);
+            conf.transport = "quic".to_string();
+            conf
+        })
+        .collect();
+    let mut nodes: Vec<MessagePool> = common::start_cluster(configs);
+    assert!(common::pump_until(
+        &mut nodes,
+        Duration::from_secs(15),
+        common::all_online
+    ));
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use goxoy_litep2p::{
+    EventType, Handshake, MemoryNetwork, Message, MessageKind, MessagePool, TcpTransport, Transport,
+};
+use std::{collections::BTreeMap, sync::Arc, thread, time::Duration};
+
+// c sadece b'yi tanır, a ile c arası baştan kesik; a'nın c'ye rotası b üzerinden gider
+fn partitioned_line(seed: u64) -> Vec<MessagePool> {
+    let network = MemoryNetwork::new(seed);
+    let mut nodes: Vec<MessagePool> = Vec::new();
+    for name in ["relay-a", "relay-b", "relay-c"] {
+        let mut conf = common::config(name, &[]);
+        conf.addr = "10.0.0.1:0".to_string();
+        if let Some(previous) = nodes.last() {
+            conf.bootstrap.push(previous.local_addr());
+        }
+        let mut node = MessagePool::with_transport(Arc::new(network.transport()));
+        assert!(node.start_with_config(conf));
+        nodes.push(node);
+    }
+    let (addr_a, addr_c) = (nodes[0].local_addr(), nodes[2].local_addr());
+    network.partition(std::slice::from_ref(&addr_a), std::slice::from_ref(&addr_c));
+
+    let routed = common::pump_until(&mut nodes, Duration::from_secs(10), |nodes| {
+        nodes[0]
+            .next_hop(&addr_c)
+            .is_some_and(|(relay_addr, hops)| relay_addr.eq(&nodes[1].local_addr()) && hops == 2)
+    });
+    assert!(routed, "no route to the partitioned node");
+    nodes
+}
+
+// payload c'ye ulaşana kadar node'ların olaylarını işler
+fn relay_to_c(nodes: &mut [MessagePool], payload: &[u8]) -> bool {
+    let addr_c = nodes[2].local_addr();
+    nodes[0].send_to(addr_c, payload.to_vec());
+    for _ in 0..500 {
+        if let EventType::OnMessage(msg) = nodes[2].on_event() {
+            if msg.payload == payload {
+                return true;
+            }
+        }
+        for node in nodes.iter_mut().take(2) {
+            node.on_event();
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+#[test]
+fn messages_reach_unreachable_nodes_through_a_neighbour() {
+    let mut nodes = partitioned_line(7);
+    assert!(
+        relay_to_c(&mut nodes, b"through b"),
+        "relayed message never arrived"
+    );
+}
+
+#[test]
+fn relayed_payloads_are_not_encoded_twice() {
+    let mut nodes = partitioned_line(11);
+    // sıkıştırılamayan 1 MB
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let payload: Vec<u8> = (0..1024 * 1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect();
+    let sent_before = nodes[0].metrics().bytes_out;
+    assert!(
+        relay_to_c(&mut nodes, &payload),
+        "relayed payload never arrived"
+    );
+    // başlık ve base64 ile yaklaşık 1.8 kat; iki kez sayı dizisine çevrilseydi 10 katı geçerdi
+    let sent = nodes[0].metrics().bytes_out - sent_before;
+    assert!(
+        sent < payload.len() as u64 * 2,
+        "{sent} bytes sent for a 1 MB payload"
+    );
+}
+
+#[test]
+fn ping_replies_carry_large_route_tables() {
+    // her bilinen node handshake'e bir rota ekler, büyük cluster'da cevap birkaç KB olur
+    let transport = TcpTransport::new();
+    let listener = transport.listen("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr();
+    let mut handshake = Handshake::local("routes".to_string(), BTreeMap::new());
+    handshake.routes = (0..500)
+        .map(|i| (format!("10.0.{}.{}:1111", i / 250, i % 250), 2))
+        .collect();
+    let reply = Message {
+        id: 0,
+        sender: addr.clone(),
+        kind: MessageKind::Ok,
+        payload: Vec::new(),
+        cluster_token: String::new(),
+        payload_codec: String::new(),
+        handshake: Some(Box::new(handshake)),
+        node_metadata: Vec::new(),
+    };
+    let server = thread::spawn(move || {
+        let mut connection = listener.accept().unwrap();
+        connection.read_request().unwrap();
+        connection.write_response(&reply.to_byte_array()).unwrap();
+    });
+
+    let ping = Message {
+        id: 1,
+        sender: "127.0.0.1:1".to_string(),
+        kind: MessageKind::State,
+        payload: Vec::new(),
+        cluster_token: String::new(),
+        payload_codec: String::new(),
+        handshake: None,
+        node_metadata: Vec::new(),
+    };
+    let result = transport.request(&addr, &ping.to_byte_array());
+    server.join().unwrap();
+    assert_eq!(result.kind, MessageKind::Ok);
+    assert_eq!(result.handshake.map(|hs| hs.routes.len()), Some(500));
+}
@@ -0,0 +1,136 @@
+use goxoy_litep2p::{Config, NodeStatus, Simulation};
+
+fn sees(sim: &Simulation, index: usize, peer: usize, status: NodeStatus) -> bool {
+    let peer_addr = sim.addr(peer);
+    sim.node(index).is_some_and(|node| {
+        node.peers()
+            .iter()
+            .any(|info| info.addr.eq(&peer_addr) && info.status == status)
+    })
+}
+
+#[test]
+fn converges_from_a_cold_start() {
+    let mut sim = Simulation::new(7, 8);
+    let took_millis = sim.run_until_converged(20_000);
+    assert!(took_millis.is_some(), "{:?}", sim.node_hashes());
+    for index in 1..sim.node_count() {
+        assert!(sees(&sim, 0, index, NodeStatus::Online));
+    }
+}
+
+#[test]
+fn runs_with_the_same_seed_are_identical() {
+    let mut first = Simulation::new(11, 5);
+    let mut second = Simulation::new(11, 5);
+    first.set_loss(0.1);
+    second.set_loss(0.1);
+    first.run_for(5_000);
+    second.run_for(5_000);
+    assert_eq!(first.node_hashes(), second.node_hashes());
+    assert_eq!(first.events(), second.events());
+}
+
+#[test]
+fn converges_after_node_lists_were_lost() {
+    // katılım sırasında kaybolan node listeleri, node'lar farklı hash bildirdikçe tekrar gönderilir
+    for seed in 1..=10 {
+        let mut sim = Simulation::new(seed, 5);
+        sim.set_loss(0.3);
+        sim.run_for(2_000);
+        sim.set_loss(0.0);
+        if let Err(reason) = sim.check_converges_within(30_000) {
+            panic!("seed {}: {}", seed, reason);
+        }
+    }
+}
+
+#[test]
+fn partitioned_sides_go_offline_and_rejoin_after_healing() {
+    let mut sim = Simulation::new(3, 6);
+    sim.check_converges_within(20_000).unwrap();
+
+    sim.partition(&[0, 1, 2], &[3, 4, 5]);
+    sim.run_for(5_000);
+    assert!(sees(&sim, 0, 4, NodeStatus::Offline));
+    assert!(sees(&sim, 4, 1, NodeStatus::Offline));
+    assert!(sees(&sim, 0, 1, NodeStatus::Online));
+
+    sim.heal();
+    sim.check_converges_within(30_000).unwrap();
+    assert!(sees(&sim, 0, 4, NodeStatus::Online));
+    assert!(sees(&sim, 4, 1, NodeStatus::Online));
+}
+
+#[test]
+fn crashed_node_rejoins_after_restart() {
+    let mut sim = Simulation::new(5, 4);
+    sim.check_converges_within(20_000).unwrap();
+
+    sim.crash(2);
+    sim.run_for(5_000);
+    assert!(sees(&sim, 0, 2, NodeStatus::Offline));
+
+    assert!(sim.restart(2));
+    sim.check_converges_within(30_000).unwrap();
+    for index in [0, 1, 3] {
+        assert!(sees(&sim, 2, index, NodeStatus::Online));
+        assert!(sees(&sim, index, 2, NodeStatus::Online));
+    }
+}
+
+#[test]
+fn bootstrap_names_are_resolved_through_the_stub_resolver() {
+    let seed_conf = Config {
+        addr: "127.0.0.1:10000".to_string(),
+        store_node_list: false,
+        ..Config::default()
+    };
+    let joining_conf = Config {
+        addr: "127.0.0.1:10001".to_string(),
+        store_node_list: false,
+        bootstrap: vec!["seed.test:10000".to_string()],
+        dns_refresh_secs: 1,
+        ..Config::default()
+    };
+    let mut sim = Simulation::with_configs(1, vec![seed_conf, joining_conf]);
+
+    // isim henüz çözülmüyor, node yalnız kalır
+    sim.run_for(3_000);
+    assert!(sim
+        .node(1)
+        .unwrap()
+        .peers_by_status(NodeStatus::Online)
+        .is_empty());
+
+    // kayıt eklenince bir sonraki yenilemede node cluster'a katılır
+    sim.resolver()
+        .set("seed.test:10000", vec!["127.0.0.1:10000".to_string()]);
+    sim.check_converges_within(10_000).unwrap();
+    assert!(sees(&sim, 1, 0, NodeStatus::Online));
+    assert!(sees(&sim, 0, 1, NodeStatus::Online));
+}
+
+#[test]
+fn offline_nodes_are_retested_after_ten_seconds() {
+    let mut sim = Simulation::new(9, 2);
+    sim.check_converges_within(20_000).unwrap();
+    sim.set_tick(1);
+
+    sim.crash(1);
+    let mut went_offline = None;
+    while went_offline.is_none() && sim.elapsed() < 60_000 {
+        sim.step();
+        if sees(&sim, 0, 1, NodeStatus::Offline) {
+            went_offline = Some(sim.now());
+        }
+    }
+    let went_offline = went_offline.expect("crashed node never went offline");
+
+    // tekrar deneme 10 000 ms dolduktan sonraki ilk turda yapılır, yine ulaşılamayan node listeden çıkar
+    sim.run_for(went_offline + 10_000 - sim.now());
+    assert_eq!(sim.now(), went_offline + 10_000);
+    assert!(sees(&sim, 0, 1, NodeStatus::Offline));
+    sim.step();
+    assert!(sim.node(0).unwrap().peer(&sim.addr(1)).is_none());
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use goxoy_litep2p::{EventType, MessagePool};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+fn storage_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("litep2p-{}-{}", name, std::process::id()));
+    _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn start(dir: &Path, bootstrap: &[String]) -> MessagePool {
+    let mut conf = common::config("", bootstrap);
+    conf.store_node_list = true;
+    conf.storage_dir = dir.to_str().unwrap().to_string();
+    let mut node = MessagePool::new();
+    assert!(node.start_with_config(conf));
+    node
+}
+
+// klasördeki node listesi dosyaları
+fn list_files(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|name| name.ends_with(".json"))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn pools_in_one_process_keep_their_own_state_and_files() {
+    let (dir_a, dir_b) = (storage_dir("pool-a"), storage_dir("pool-b"));
+    let first = start(&dir_a, &[]);
+    let second = start(&dir_b, &[first.local_addr()]);
+    let mut nodes = vec![first, second];
+    assert!(common::pump_until(
+        &mut nodes,
+        Duration::from_secs(10),
+        common::all_online
+    ));
+
+    // her pool kendi kimliğini kendi klasörüne yazar
+    let (id_a, id_b) = (nodes[0].node_id(), nodes[1].node_id());
+    assert_ne!(id_a, id_b);
+    assert_eq!(fs::read_to_string(dir_a.join("node_id")).unwrap(), id_a);
+    assert_eq!(fs::read_to_string(dir_b.join("node_id")).unwrap(), id_b);
+    assert_eq!(list_files(&dir_a), [format!("{id_a}.json")]);
+    assert_eq!(list_files(&dir_b), [format!("{id_b}.json")]);
+
+    // node listesi, kuyruk ve sayaçlar paylaşılmaz
+    assert_eq!(nodes[0].peers().len(), 1);
+    assert_eq!(nodes[0].peers()[0].node_id, id_b);
+    assert_eq!(nodes[1].peers()[0].node_id, id_a);
+    let addr_b = nodes[1].local_addr();
+    nodes[0].send_to(addr_b, b"only for b".to_vec());
+    let mut received = [false, false];
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(2) {
+        for (index, node) in nodes.iter_mut().enumerate() {
+            if let EventType::OnMessage(msg) = node.on_event() {
+                received[index] |= msg.payload == b"only for b";
+            }
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(received, [false, true]);
+    let distributed =
+        |node: &MessagePool| node.metrics().messages_received.get("Distribute").copied();
+    assert_eq!(distributed(&nodes[0]), None);
+    assert_eq!(distributed(&nodes[1]), Some(1));
+
+    // yeniden başlayan pool yalnız kendi klasöründeki kimliği okur
+    let second = nodes.pop().unwrap();
+    nodes.pop().unwrap().shutdown();
+    let restarted = start(&dir_a, &[second.local_addr()]);
+    assert_eq!(restarted.node_id(), id_a);
+
+    _ = fs::remove_dir_all(&dir_a);
+    _ = fs::remove_dir_all(&dir_b);
+}
@@ -0,0 +1,195 @@
+mod common;
+
+use goxoy_litep2p::{EventType, MessagePool, MockClock};
+use std::{
+    io::{self, Cursor, Read, Seek, SeekFrom, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+// sıkıştırılamayan, tekrarlanabilir veri
+fn noise(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+// ilk limit byte'tan sonrası okunamaz, gönderen stream'in ortasında vazgeçer
+struct BrokenSource {
+    inner: Cursor<Vec<u8>>,
+    limit: u64,
+}
+
+impl Read for BrokenSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.inner.position() >= self.limit {
+            return Err(io::Error::other("source broke"));
+        }
+        let readable = buf.len().min((self.limit - self.inner.position()) as usize);
+        self.inner.read(&mut buf[..readable])
+    }
+}
+
+impl Seek for BrokenSource {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+// upstream'e giden bağlantıları aktarır, her cevabı delay kadar geciktirir; yavaş bir alıcı veya uzak bir link gibi
+fn delaying_proxy(upstream: String, delay: Duration) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy_addr = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        for client in listener.incoming() {
+            let Ok(mut client) = client else { continue };
+            let Ok(mut server) = TcpStream::connect(&upstream) else {
+                continue;
+            };
+            let (mut client_reader, mut server_writer) =
+                (client.try_clone().unwrap(), server.try_clone().unwrap());
+            thread::spawn(move || {
+                _ = io::copy(&mut client_reader, &mut server_writer);
+                _ = server_writer.shutdown(Shutdown::Write);
+            });
+            thread::spawn(move || {
+                let mut buf = vec![0u8; 64 * 1024];
+                while let Ok(read_len) = server.read(&mut buf) {
+                    if read_len == 0 {
+                        break;
+                    }
+                    thread::sleep(delay);
+                    if client.write_all(&buf[..read_len]).is_err() {
+                        break;
+                    }
+                }
+                _ = client.shutdown(Shutdown::Write);
+            });
+        }
+    });
+    proxy_addr
+}
+
+fn start(name: &str, clock: Option<&MockClock>) -> MessagePool {
+    let mut node = MessagePool::new();
+    if let Some(clock) = clock {
+        node.set_clock(Arc::new(clock.clone()));
+    }
+    assert!(node.start_with_config(common::config(name, &[])));
+    node
+}
+
+#[test]
+fn streams_arrive_without_nested_json() {
+    let mut sender = start("stream-sender", None);
+    let mut receiver = start("stream-receiver", None);
+    let data = noise(256 * 1024);
+    let stream_id = sender.send_stream(receiver.local_addr(), Cursor::new(data.clone()));
+
+    let mut received = Vec::new();
+    let mut completed = (false, false);
+    let started = Instant::now();
+    while !(completed.0 && completed.1) && started.elapsed() < Duration::from_secs(20) {
+        match receiver.on_event() {
+            EventType::OnStreamData(_, id, offset, chunk) if id == stream_id => {
+                assert_eq!(offset, received.len() as u64);
+                received.extend_from_slice(&chunk);
+            }
+            EventType::OnStreamCompleted(_, id) if id == stream_id => completed.1 = true,
+            EventType::OnStreamFailed(_, _, reason) => panic!("receiver failed: {reason}"),
+            _ => {}
+        }
+        match sender.on_event() {
+            EventType::OnStreamCompleted(_, id) if id == stream_id => completed.0 = true,
+            EventType::OnStreamFailed(_, _, reason) => panic!("sender failed: {reason}"),
+            EventType::OnWait() => thread::sleep(Duration::from_millis(1)),
+            _ => {}
+        }
+    }
+    assert!(completed.0 && completed.1, "stream did not complete");
+    assert_eq!(received, data);
+    // parçalar ham, mesaj payload'ı base64 yazılır; sayı dizisi olarak yazılsaydı veri katlarca büyürdü
+    let bytes_out = sender.metrics().bytes_out;
+    assert!(
+        bytes_out < data.len() as u64 * 3 / 2,
+        "{bytes_out} bytes sent for {} bytes of data",
+        data.len()
+    );
+}
+
+#[test]
+fn abandoned_incoming_streams_expire() {
+    let clock = MockClock::new(1_000_000);
+    let mut receiver = start("stream-idle-receiver", Some(&clock));
+    let mut sender = start("stream-idle-sender", None);
+    let source = BrokenSource {
+        inner: Cursor::new(noise(64 * 1024)),
+        limit: 16 * 1024,
+    };
+    let stream_id = sender.send_stream(receiver.local_addr(), source);
+
+    let mut sender_failed = false;
+    let mut first_chunk = false;
+    let started = Instant::now();
+    while !(sender_failed && first_chunk) && started.elapsed() < Duration::from_secs(10) {
+        match sender.on_event() {
+            EventType::OnStreamFailed(_, id, _) if id == stream_id => sender_failed = true,
+            EventType::OnWait() => thread::sleep(Duration::from_millis(1)),
+            _ => {}
+        }
+        if let EventType::OnStreamData(_, id, _, _) = receiver.on_event() {
+            first_chunk |= id == stream_id;
+        }
+    }
+    assert!(sender_failed && first_chunk, "stream did not break halfway");
+    assert!(
+        (0..100).all(|_| !matches!(receiver.on_event(), EventType::OnStreamFailed(..))),
+        "stream expired before its idle timeout"
+    );
+
+    clock.advance(Duration::from_secs(61));
+    let expired = (0..100).any(|_| {
+        matches!(
+            receiver.on_event(),
+            EventType::OnStreamFailed(peer, id, _) if id == stream_id && peer == sender.local_addr()
+        )
+    });
+    assert!(expired, "abandoned stream never expired");
+}
+
+#[test]
+fn streams_survive_a_slow_receiver() {
+    let mut sender = start("stream-slow-sender", None);
+    let mut receiver = start("stream-slow-receiver", None);
+    // her parçanın ack'i 200 ms gecikir, transport'un varsayılan okuma süresinden çok uzun
+    let proxy_addr = delaying_proxy(receiver.local_addr(), Duration::from_millis(200));
+    let data = noise(64 * 1024);
+    let stream_id = sender.send_stream(proxy_addr, Cursor::new(data.clone()));
+
+    let mut received = Vec::new();
+    let mut completed = false;
+    let started = Instant::now();
+    while !completed && started.elapsed() < Duration::from_secs(20) {
+        if let EventType::OnStreamData(_, id, _, chunk) = receiver.on_event() {
+            if id == stream_id {
+                received.extend_from_slice(&chunk);
+            }
+        }
+        match sender.on_event() {
+            EventType::OnStreamCompleted(_, id) if id == stream_id => completed = true,
+            EventType::OnStreamFailed(_, _, reason) => panic!("sender failed: {reason}"),
+            EventType::OnWait() => thread::sleep(Duration::from_millis(1)),
+            _ => {}
+        }
+    }
+    assert!(completed, "stream did not complete");
+    assert_eq!(received, data);
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use goxoy_litep2p::{Message, MessageKind, MessagePool, TcpTransport, Transport};
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+fn ping(cluster_token: &str) -> Message {
+    Message {
+        id: 1,
+        sender: "127.0.0.1:1".to_string(),
+        kind: MessageKind::State,
+        payload: Vec::new(),
+        cluster_token: cluster_token.to_string(),
+        payload_codec: String::new(),
+        handshake: None,
+        node_metadata: Vec::new(),
+    }
+}
+
+// node'un imzaladığı gibi: md5 ile HMAC, anahtar cluster_id ve cluster_secret'ten türetilir
+fn sign(cluster_id: &str, cluster_secret: &str, data: &[u8]) -> Vec<u8> {
+    let key = md5::compute(format!("datagram:{cluster_id}:{cluster_secret}")).0;
+    let mut block = [0u8; 64];
+    block[..16].copy_from_slice(&key);
+    let mut inner = md5::Context::new();
+    inner.consume(block.map(|byte| byte ^ 0x36));
+    inner.consume(data);
+    let mut outer = md5::Context::new();
+    outer.consume(block.map(|byte| byte ^ 0x5c));
+    outer.consume(inner.compute().0);
+    let mut datagram = outer.compute().0.to_vec();
+    datagram.extend_from_slice(data);
+    datagram
+}
+
+fn reply_to(node_addr: &str, datagram: &[u8]) -> Option<Vec<u8>> {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_millis(500)))
+        .unwrap();
+    socket.send_to(datagram, node_addr).unwrap();
+    let mut reply = vec![0u8; 65_507];
+    let reply_size = socket.recv(&mut reply).ok()?;
+    reply.truncate(reply_size);
+    Some(reply)
+}
+
+#[test]
+fn datagrams_without_a_valid_signature_are_dropped() {
+    let mut conf = common::config("udp-signed", &[]);
+    conf.udp = true;
+    conf.cluster_id = "udp".to_string();
+    conf.cluster_secret = "secret".to_string();
+    let mut node = MessagePool::new();
+    assert!(node.start_with_config(conf));
+    let node_addr = node.local_addr();
+    // token açık gönderildiği için bilinebilir, kaynak IP'si de taklit edilebilir
+    let token = format!("{:x}", md5::compute("udp:secret"));
+    let request = ping(&token).to_byte_array();
+
+    assert!(
+        reply_to(&node_addr, &request).is_none(),
+        "unsigned datagram answered"
+    );
+    let forged = sign("udp", "guess", &request);
+    assert!(
+        reply_to(&node_addr, &forged).is_none(),
+        "forged datagram answered"
+    );
+
+    let signed = sign("udp", "secret", &request);
+    let reply = reply_to(&node_addr, &signed).expect("signed datagram not answered");
+    assert_eq!(reply[..16], sign("udp", "secret", &reply[16..])[..16]);
+    node.shutdown();
+}
+
+#[test]
+fn nodes_sharing_a_secret_come_online_over_udp() {
+    let configs = ["udp-a", "udp-b"]
+        .iter()
+        .map(|name| {
+            let mut conf = common::config(name, &[]);
+            conf.udp = true;
+            conf.cluster_id = "udp".to_string();
+            conf.cluster_secret = "secret".to_string();
+            conf
+        })
+        .collect();
+    let mut nodes = common::start_cluster(configs);
+    assert!(common::pump_until(
+        &mut nodes,
+        Duration::from_secs(10),
+        common::all_online
+    ));
+}
+
+#[test]
+fn datagram_requests_fall_back_to_tcp_after_retransmissions() {
+    // node UDP dinlemez, aynı portta paketleri yutan ama cevap vermeyen bir soket vardır
+    let mut node = MessagePool::new();
+    assert!(node.start_with_config(common::config("udp-blackhole", &[])));
+    let node_addr = node.local_addr();
+    let blackhole = UdpSocket::bind(node_addr.parse::<SocketAddr>().unwrap()).unwrap();
+
+    let transport = TcpTransport::new();
+    transport.set_datagrams(1400);
+    let started = Instant::now();
+    let reply = transport.request_datagram(&node_addr, &ping("").to_byte_array());
+    assert_eq!(reply.kind, MessageKind::Ok);
+    // üç deneme 50 + 100 + 200 ms bekler
+    assert!(started.elapsed() >= Duration::from_millis(350));
+
+    blackhole
+        .set_read_timeout(Some(Duration::from_millis(100)))
+        .unwrap();
+    let mut datagram = [0u8; 2048];
+    let mut attempts = 0;
+    while blackhole.recv(&mut datagram).is_ok() {
+        attempts += 1;
+    }
+    assert_eq!(attempts, 3);
+
+    // TCP'ye cevap veren node'a bir süre doğrudan TCP ile gidilir
+    let started = Instant::now();
+    let reply = transport.request_datagram(&node_addr, &ping("").to_byte_array());
+    assert_eq!(reply.kind, MessageKind::Ok);
+    assert!(started.elapsed() < Duration::from_millis(50));
+    assert!(blackhole.recv(&mut datagram).is_err());
+}
@@ -0,0 +1,60 @@
+#![cfg(unix)]
+
+mod common;
+
+use goxoy_litep2p::{MessagePool, NodeStatus};
+use std::{path::Path, time::Duration};
+
+// aynı anda çalışan test süreçleri birbirinin soketini silmesin
+fn socket_addr(name: &str) -> String {
+    format!("unix:/tmp/litep2p-{}-{}.sock", std::process::id(), name)
+}
+
+#[test]
+fn socket_and_tcp_nodes_form_one_cluster() {
+    let mut configs = vec![
+        common::config("uds-a", &[]),
+        common::config("uds-b", &[]),
+        common::config("uds-tcp", &[]),
+    ];
+    configs[0].addr = socket_addr("a");
+    configs[1].addr = socket_addr("b");
+    let mut nodes = common::start_cluster(configs);
+    assert_eq!(nodes[0].local_addr(), socket_addr("a"));
+    assert_eq!(nodes[1].local_addr(), socket_addr("b"));
+
+    let online = common::pump_until(&mut nodes, Duration::from_secs(20), common::all_online);
+    assert!(online, "mixed cluster did not come online");
+    let tcp_peers: Vec<String> = nodes[2]
+        .peers_by_status(NodeStatus::Online)
+        .into_iter()
+        .map(|peer| peer.addr)
+        .collect();
+    assert!(tcp_peers.contains(&socket_addr("a")));
+    assert!(tcp_peers.contains(&socket_addr("b")));
+
+    // kapanan node soket dosyasını siler
+    for node in nodes.iter_mut() {
+        node.shutdown();
+    }
+    drop(nodes);
+    for name in ["a", "b"] {
+        let path = socket_addr(name);
+        assert!(!Path::new(path.trim_start_matches("unix:")).exists());
+    }
+}
+
+#[test]
+fn a_second_node_cannot_take_a_live_socket() {
+    let addr = socket_addr("taken");
+    let mut conf = common::config("uds-first", &[]);
+    conf.addr = addr.clone();
+    let mut first = MessagePool::new();
+    assert!(first.start_with_config(conf));
+
+    let mut conf = common::config("uds-second", &[]);
+    conf.addr = addr;
+    let mut second = MessagePool::new();
+    assert!(!second.start_with_config(conf));
+    first.shutdown();
+}